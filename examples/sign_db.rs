@@ -0,0 +1,90 @@
+//! アプリDB（apps.tar.gz）の署名ツール
+//!
+//! `an sync` が検証する minisign 形式の署名を作成します。
+//! CIで公開するtarballの署名と、タップ運用者の鍵作成に使用します。
+//!
+//! ```bash
+//! # 鍵を作成（公開鍵は `an repo add --public-key` / `sync.public_key` に、秘密鍵はCIのシークレットに）
+//! cargo run --example sign_db -- --generate
+//!
+//! # 署名（秘密鍵は環境変数 AN_DB_SECRET_KEY から読み込む）
+//! AN_DB_SECRET_KEY=... cargo run --example sign_db -- apps.tar.gz > apps.tar.gz.minisig
+//! ```
+//!
+//! 秘密鍵は `base64(鍵ID 8バイト || Ed25519秘密鍵 64バイト)` です。
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ed25519_compact::{KeyPair, SecretKey, Seed};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// minisign の署名アルゴリズム（Ed25519、事前ハッシュなし）
+const ALGORITHM: &[u8; 2] = b"Ed";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("--generate") => generate(),
+        Some(path) => sign(path),
+        None => {
+            eprintln!("usage: sign_db --generate | sign_db <FILE>");
+            std::process::exit(2);
+        }
+    }
+}
+
+/// 鍵ペアを作成して表示
+fn generate() {
+    let key_pair = KeyPair::generate();
+    let mut key_id = [0u8; 8];
+    key_id.copy_from_slice(&Seed::generate()[..8]);
+
+    let mut public_key = ALGORITHM.to_vec();
+    public_key.extend_from_slice(&key_id);
+    public_key.extend_from_slice(&key_pair.pk[..]);
+
+    let mut secret_key = key_id.to_vec();
+    secret_key.extend_from_slice(&key_pair.sk[..]);
+
+    println!("public key: {}", STANDARD.encode(public_key));
+    println!("secret key: {}", STANDARD.encode(secret_key));
+}
+
+/// ファイルに署名して標準出力へ書き出し
+fn sign(path: &str) {
+    let secret = std::env::var("AN_DB_SECRET_KEY").expect("AN_DB_SECRET_KEY が設定されていません");
+    let secret = STANDARD
+        .decode(secret.trim())
+        .expect("AN_DB_SECRET_KEY がbase64ではありません");
+    assert_eq!(secret.len(), 8 + SecretKey::BYTES, "秘密鍵の長さが不正です");
+
+    let (key_id, secret_key) = secret.split_at(8);
+    let secret_key = SecretKey::from_slice(secret_key).expect("秘密鍵が不正です");
+    let data = std::fs::read(path).expect("ファイルを読み込めません");
+
+    let signature = secret_key.sign(&data, None);
+    let file_name = std::path::Path::new(path)
+        .file_name()
+        .unwrap()
+        .to_string_lossy();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let trusted_comment = format!("timestamp:{}\tfile:{}", timestamp, file_name);
+
+    // グローバル署名は（署名 || 信頼コメント）に対する署名
+    let mut global = signature.to_vec();
+    global.extend_from_slice(trusted_comment.as_bytes());
+    let global_signature = secret_key.sign(&global, None);
+
+    let mut signature_line = ALGORITHM.to_vec();
+    signature_line.extend_from_slice(key_id);
+    signature_line.extend_from_slice(&signature[..]);
+
+    println!("untrusted comment: signature from an sign_db");
+    println!("{}", STANDARD.encode(signature_line));
+    println!("trusted comment: {}", trusted_comment);
+    println!("{}", STANDARD.encode(&global_signature[..]));
+}
@@ -2,7 +2,9 @@
 //!
 //! clapを使用したコマンドライン引数のパースを定義します。
 
+use crate::db::app::SourceType;
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 /// AN (安装) - Unified Package Manager for Linux
 #[derive(Parser)]
@@ -11,6 +13,26 @@ use clap::{Parser, Subcommand};
 #[command(version)]
 #[command(about = "AN - Unified Package Manager for Linux", long_about = None)]
 pub struct Cli {
+    /// 設定ファイルのパス（デフォルト: ~/.config/an/config.toml）
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// 設定値を一時的に上書き（例: -o flatpak.remote=flathub-beta）
+    #[arg(short = 'o', long = "option", global = true, value_name = "KEY=VALUE")]
+    pub options: Vec<String>,
+
+    /// システム全体を対象に操作（/opt/an, /usr/local/bin, /var/lib/an、特権が必要）
+    #[arg(long, global = true)]
+    pub system: bool,
+
+    /// 警告とエラー以外の出力を抑制
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// 配置したファイルや実行したコマンドなど詳細を表示
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -20,8 +42,13 @@ pub enum Commands {
     /// アプリをインストール
     #[command(visible_alias = "i")]
     Install {
-        /// ファイルパスまたはアプリ名
-        target: String,
+        /// ファイルパスまたはアプリ名（複数指定可）
+        #[arg(required_unless_present = "file")]
+        targets: Vec<String>,
+
+        /// インストールするアプリを記載したマニフェストファイル
+        #[arg(short, long, value_name = "FILE")]
+        file: Option<PathBuf>,
 
         /// カスタムコマンド名を指定（AppImage用）
         #[arg(short, long)]
@@ -34,6 +61,10 @@ pub enum Commands {
         /// 元ファイルを削除（移動モード、AppImage用）
         #[arg(short = 'm', long = "move")]
         move_file: bool,
+
+        /// 使用するソースの形式を指定（flatpak, appimage, deb）
+        #[arg(long, value_name = "TYPE")]
+        via: Option<SourceType>,
     },
 
     /// アプリを削除（パージ）
@@ -43,6 +74,28 @@ pub enum Commands {
         target: String,
     },
 
+    /// Anfileに記載した状態になるようアプリをインストール・更新・削除
+    Apply {
+        /// Anfileのパス
+        #[arg(value_name = "FILE", default_value = "Anfile")]
+        file: PathBuf,
+
+        /// Anfileに記載のないANでインストールしたアプリを削除
+        #[arg(long)]
+        prune: bool,
+
+        /// 計画の表示のみ行う
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// 現在のインストール状態をAnfileの形式で書き出す
+    Export {
+        /// 出力先（省略時は標準出力）
+        #[arg(value_name = "FILE")]
+        file: Option<PathBuf>,
+    },
+
     /// Flatpakエイリアスを生成
     #[command(visible_alias = "l")]
     Link,
@@ -59,16 +112,155 @@ pub enum Commands {
     Search {
         /// 検索クエリ（アプリ名または説明）
         query: Option<String>,
+
+        /// 表示する最大件数
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// カテゴリで絞り込む
+        #[arg(long)]
+        category: Option<String>,
+
+        /// インストールタイプで絞り込む（appimage / deb / flatpak / script）
+        #[arg(long = "type", value_name = "TYPE")]
+        source_type: Option<SourceType>,
+
+        /// 対応アーキテクチャで絞り込む
+        #[arg(long)]
+        arch: Option<String>,
     },
 
+    /// アプリDBのカテゴリ一覧（アプリ数付き）
+    Categories,
+
     /// アプリ詳細を表示
     Info {
         /// アプリ名
         name: String,
     },
 
-    /// アプリDBをGitHubから同期
-    Sync,
+    /// アプリDBを登録済みリポジトリから同期
+    Sync {
+        /// 同期するリポジトリ名（省略時は全リポジトリ）
+        repo: Option<String>,
+
+        /// 同期結果をJSONで出力
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// アプリDBリポジトリ（タップ）を管理
+    Repo {
+        #[command(subcommand)]
+        action: RepoAction,
+    },
+
+    /// 設定を表示・変更
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// アプリDBのメンテナンス
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+}
+
+impl Commands {
+    /// インストール先・インストール記録・アプリDBを変更するコマンドか
+    ///
+    /// 変更するコマンドの実行中はグローバルロックを保持します。
+    pub fn mutates(&self) -> bool {
+        match self {
+            Commands::Install { .. }
+            | Commands::Remove { .. }
+            | Commands::Link
+            | Commands::Update
+            | Commands::Sync { .. } => true,
+            Commands::Apply { dry_run, .. } => !dry_run,
+            Commands::Repo { action } => !matches!(action, RepoAction::List),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum DbAction {
+    /// 旧ツール（AM）のプログラム一覧をアプリ定義に変換
+    ImportLegacy {
+        /// 旧ツールのプログラム一覧のディレクトリ
+        #[arg(default_value = "_legacy/programs")]
+        legacy_dir: PathBuf,
+
+        /// 出力先ディレクトリ（要確認のエントリは needs-review/ に出力）
+        #[arg(long, default_value = "imported")]
+        output: PathBuf,
+    },
+
+    /// アプリ定義を検査（問題があれば終了コード1）
+    Lint {
+        /// 検査するディレクトリ（省略時はアプリDB全体）
+        path: Option<PathBuf>,
+
+        /// ダウンロードURLにアクセスできるかも確認
+        #[arg(long)]
+        check_urls: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// 設定値を表示
+    Get {
+        /// 設定キー（例: paths.apps_dir）
+        key: String,
+    },
+
+    /// 設定値を変更（空文字列でデフォルトに戻す）
+    Set {
+        /// 設定キー（例: paths.apps_dir）
+        key: String,
+
+        /// 設定値
+        value: String,
+    },
+
+    /// 全設定値を表示
+    #[command(visible_alias = "ls")]
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum RepoAction {
+    /// リポジトリを登録して同期
+    Add {
+        /// リポジトリ名
+        name: String,
+
+        /// 取得元（git URL・ローカルパス・HTTPSの .tar.gz）
+        url: String,
+
+        /// 優先度（大きいほど優先、デフォルト: 10、公式DBは 0）
+        #[arg(short, long, allow_hyphen_values = true)]
+        priority: Option<i32>,
+
+        /// 署名を検証する公開鍵（minisign形式、tarballの取得元のみ）
+        #[arg(long, value_name = "KEY")]
+        public_key: Option<String>,
+    },
+
+    /// リポジトリの登録を解除
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// リポジトリ名
+        name: String,
+    },
+
+    /// 登録済みリポジトリを表示
+    #[command(visible_alias = "ls")]
+    List,
 }
 
 #[cfg(test)]
@@ -87,25 +279,63 @@ mod tests {
         let cli = Cli::parse_from(["an", "install", "firefox"]);
         match cli.command {
             Commands::Install {
-                target,
+                targets,
+                file,
                 name,
                 desktop,
                 move_file,
+                via,
             } => {
-                assert_eq!(target, "firefox");
+                assert_eq!(targets, ["firefox"]);
+                assert!(file.is_none());
                 assert!(name.is_none());
                 assert!(!desktop);
                 assert!(!move_file);
+                assert!(via.is_none());
             }
             _ => panic!("Expected Install command"),
         }
     }
 
+    #[test]
+    fn test_install_multiple() {
+        let cli = Cli::parse_from(["an", "install", "firefox", "gimp", "vlc"]);
+        match cli.command {
+            Commands::Install { targets, file, .. } => {
+                assert_eq!(targets, ["firefox", "gimp", "vlc"]);
+                assert!(file.is_none());
+            }
+            _ => panic!("Expected Install command"),
+        }
+
+        let cli = Cli::parse_from(["an", "install", "-f", "team-apps.toml"]);
+        match cli.command {
+            Commands::Install { targets, file, .. } => {
+                assert!(targets.is_empty());
+                assert_eq!(file, Some(PathBuf::from("team-apps.toml")));
+            }
+            _ => panic!("Expected Install command"),
+        }
+
+        // アプリ名もマニフェストもない場合はエラー
+        assert!(Cli::try_parse_from(["an", "install"]).is_err());
+    }
+
+    #[test]
+    fn test_install_via() {
+        let cli = Cli::parse_from(["an", "install", "firefox", "--via", "flatpak"]);
+        match cli.command {
+            Commands::Install { via, .. } => assert_eq!(via, Some(SourceType::Flatpak)),
+            _ => panic!("Expected Install command"),
+        }
+        assert!(Cli::try_parse_from(["an", "install", "firefox", "--via", "snap"]).is_err());
+    }
+
     #[test]
     fn test_install_alias() {
         let cli = Cli::parse_from(["an", "i", "firefox"]);
         match cli.command {
-            Commands::Install { target, .. } => assert_eq!(target, "firefox"),
+            Commands::Install { targets, .. } => assert_eq!(targets, ["firefox"]),
             _ => panic!("Expected Install command"),
         }
     }
@@ -115,12 +345,13 @@ mod tests {
         let cli = Cli::parse_from(["an", "install", "app.AppImage", "-n", "myapp", "-d", "-m"]);
         match cli.command {
             Commands::Install {
-                target,
+                targets,
                 name,
                 desktop,
                 move_file,
+                ..
             } => {
-                assert_eq!(target, "app.AppImage");
+                assert_eq!(targets, ["app.AppImage"]);
                 assert_eq!(name, Some("myapp".to_string()));
                 assert!(desktop);
                 assert!(move_file);
@@ -184,7 +415,10 @@ mod tests {
     fn test_search_command() {
         let cli = Cli::parse_from(["an", "search", "firefox"]);
         match cli.command {
-            Commands::Search { query } => assert_eq!(query, Some("firefox".to_string())),
+            Commands::Search { query, limit, .. } => {
+                assert_eq!(query, Some("firefox".to_string()));
+                assert!(limit.is_none());
+            }
             _ => panic!("Expected Search command"),
         }
     }
@@ -193,11 +427,110 @@ mod tests {
     fn test_search_without_query() {
         let cli = Cli::parse_from(["an", "search"]);
         match cli.command {
-            Commands::Search { query } => assert!(query.is_none()),
+            Commands::Search { query, .. } => assert!(query.is_none()),
             _ => panic!("Expected Search command"),
         }
     }
 
+    #[test]
+    fn test_search_limit() {
+        let cli = Cli::parse_from(["an", "search", "code", "--limit", "5"]);
+        match cli.command {
+            Commands::Search { limit, .. } => assert_eq!(limit, Some(5)),
+            _ => panic!("Expected Search command"),
+        }
+    }
+
+    #[test]
+    fn test_search_filters() {
+        let cli = Cli::parse_from([
+            "an",
+            "search",
+            "--category",
+            "Graphics",
+            "--type",
+            "flatpak",
+            "--arch",
+            "aarch64",
+        ]);
+        match cli.command {
+            Commands::Search {
+                query,
+                category,
+                source_type,
+                arch,
+                ..
+            } => {
+                assert!(query.is_none());
+                assert_eq!(category.as_deref(), Some("Graphics"));
+                assert_eq!(source_type, Some(SourceType::Flatpak));
+                assert_eq!(arch.as_deref(), Some("aarch64"));
+            }
+            _ => panic!("Expected Search command"),
+        }
+        assert!(Cli::try_parse_from(["an", "search", "--type", "snap"]).is_err());
+    }
+
+    #[test]
+    fn test_categories_command() {
+        let cli = Cli::parse_from(["an", "categories"]);
+        assert!(matches!(cli.command, Commands::Categories));
+    }
+
+    #[test]
+    fn test_apply_command() {
+        let cli = Cli::parse_from(["an", "apply"]);
+        match cli.command {
+            Commands::Apply {
+                file,
+                prune,
+                dry_run,
+            } => {
+                assert_eq!(file, PathBuf::from("Anfile"));
+                assert!(!prune);
+                assert!(!dry_run);
+            }
+            _ => panic!("Expected Apply command"),
+        }
+
+        let cli = Cli::parse_from(["an", "apply", "team.Anfile", "--prune", "--dry-run"]);
+        match cli.command {
+            Commands::Apply {
+                file,
+                prune,
+                dry_run,
+            } => {
+                assert_eq!(file, PathBuf::from("team.Anfile"));
+                assert!(prune);
+                assert!(dry_run);
+            }
+            _ => panic!("Expected Apply command"),
+        }
+    }
+
+    #[test]
+    fn test_export_command() {
+        let cli = Cli::parse_from(["an", "export"]);
+        assert!(matches!(cli.command, Commands::Export { file: None }));
+        let cli = Cli::parse_from(["an", "export", "Anfile"]);
+        assert!(matches!(cli.command, Commands::Export { file: Some(_) }));
+    }
+
+    #[test]
+    fn test_mutates() {
+        let mutates = |args: &[&str]| Cli::parse_from(args).command.mutates();
+        assert!(mutates(&["an", "install", "firefox"]));
+        assert!(mutates(&["an", "remove", "firefox"]));
+        assert!(mutates(&["an", "apply"]));
+        assert!(mutates(&["an", "sync"]));
+        assert!(mutates(&["an", "repo", "add", "team", "/srv/apps"]));
+        assert!(!mutates(&["an", "apply", "--dry-run"]));
+        assert!(!mutates(&["an", "export"]));
+        assert!(!mutates(&["an", "list"]));
+        assert!(!mutates(&["an", "search", "fire"]));
+        assert!(!mutates(&["an", "repo", "list"]));
+    }
+
     #[test]
     fn test_info_command() {
         let cli = Cli::parse_from(["an", "info", "firefox"]);
@@ -210,6 +543,138 @@ mod tests {
     #[test]
     fn test_sync_command() {
         let cli = Cli::parse_from(["an", "sync"]);
-        assert!(matches!(cli.command, Commands::Sync));
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                repo: None,
+                json: false
+            }
+        ));
+    }
+
+    #[test]
+    fn test_config_set_command() {
+        let cli = Cli::parse_from(["an", "config", "set", "flatpak.remote", "flathub-beta"]);
+        match cli.command {
+            Commands::Config {
+                action: ConfigAction::Set { key, value },
+            } => {
+                assert_eq!(key, "flatpak.remote");
+                assert_eq!(value, "flathub-beta");
+            }
+            _ => panic!("Expected Config Set command"),
+        }
+    }
+
+    #[test]
+    fn test_install_system_flag() {
+        let cli = Cli::parse_from(["an", "install", "firefox", "--system"]);
+        assert!(cli.system);
+        assert!(matches!(cli.command, Commands::Install { .. }));
+
+        let cli = Cli::parse_from(["an", "install", "firefox"]);
+        assert!(!cli.system);
+    }
+
+    #[test]
+    fn test_global_config_options() {
+        let cli = Cli::parse_from([
+            "an",
+            "list",
+            "--config",
+            "/tmp/an.toml",
+            "-o",
+            "paths.bin_dir=/tmp/bin",
+        ]);
+        assert_eq!(cli.config, Some(PathBuf::from("/tmp/an.toml")));
+        assert_eq!(cli.options, vec!["paths.bin_dir=/tmp/bin".to_string()]);
+    }
+
+    #[test]
+    fn test_verbosity_flags() {
+        let cli = Cli::parse_from(["an", "install", "firefox", "-q"]);
+        assert!(cli.quiet);
+        assert!(!cli.verbose);
+
+        let cli = Cli::parse_from(["an", "--verbose", "sync"]);
+        assert!(cli.verbose);
+
+        // 同時指定はエラー
+        assert!(Cli::try_parse_from(["an", "list", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn test_repo_add_command() {
+        let cli = Cli::parse_from([
+            "an",
+            "repo",
+            "add",
+            "team",
+            "https://example.com/team.git",
+            "--priority",
+            "-5",
+        ]);
+        match cli.command {
+            Commands::Repo {
+                action:
+                    RepoAction::Add {
+                        name,
+                        url,
+                        priority,
+                        ..
+                    },
+            } => {
+                assert_eq!(name, "team");
+                assert_eq!(url, "https://example.com/team.git");
+                assert_eq!(priority, Some(-5));
+            }
+            _ => panic!("Expected Repo Add command"),
+        }
+    }
+
+    #[test]
+    fn test_sync_with_repo() {
+        let cli = Cli::parse_from(["an", "sync", "team"]);
+        assert!(matches!(cli.command, Commands::Sync { repo: Some(r), .. } if r == "team"));
+    }
+
+    #[test]
+    fn test_db_import_legacy() {
+        let cli = Cli::parse_from(["an", "db", "import-legacy", "--output", "out"]);
+        match cli.command {
+            Commands::Db {
+                action: DbAction::ImportLegacy { legacy_dir, output },
+            } => {
+                assert_eq!(legacy_dir, PathBuf::from("_legacy/programs"));
+                assert_eq!(output, PathBuf::from("out"));
+            }
+            _ => panic!("Expected Db ImportLegacy"),
+        }
+    }
+
+    #[test]
+    fn test_db_lint() {
+        let cli = Cli::parse_from(["an", "db", "lint", "apps", "--check-urls"]);
+        match cli.command {
+            Commands::Db {
+                action: DbAction::Lint { path, check_urls },
+            } => {
+                assert_eq!(path, Some(PathBuf::from("apps")));
+                assert!(check_urls);
+            }
+            _ => panic!("Expected Db Lint"),
+        }
+    }
+
+    #[test]
+    fn test_sync_json() {
+        let cli = Cli::parse_from(["an", "sync", "--json"]);
+        assert!(matches!(
+            cli.command,
+            Commands::Sync {
+                repo: None,
+                json: true
+            }
+        ));
     }
 }
@@ -0,0 +1,1231 @@
+//! ライブラリAPI
+//!
+//! AN をGUIやプロビジョニングツールに組み込むための入口です。
+//! 各操作は表示を行わず構造化された結果を返し、進行状況は [`Event`] で通知します。
+//! 確認プロンプトは呼び出し側の責務です。
+//!
+//! ```no_run
+//! use an_installer::{Client, Event, InstallOptions};
+//!
+//! let client = Client::new().on_event(|event| {
+//!     if let Event::Installed { record } = event {
+//!         println!("installed {}", record.name);
+//!     }
+//! });
+//!
+//! let record = client.install("firefox", &InstallOptions::default())?;
+//! for package in client.installed()? {
+//!     println!("{} ({})", package.name, package.source_type);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::config::{self, Config};
+use crate::db::app::{self, AppConfig, SourceType};
+use crate::db::index;
+use crate::db::installed::{self, InstallRecord};
+use crate::db::legacy::{self, ImportSummary};
+use crate::db::lint::{self, LintReport};
+use crate::db::manifest::{Manifest, ManifestEntry};
+use crate::db::plan::{self, Action, Plan};
+use crate::db::repo::{self, Repository};
+use crate::db::search;
+use crate::db::sync::{self, SyncResult, SyncSummary};
+use crate::errors::AnError;
+use crate::events::{self, Event, Observer};
+use crate::handlers::{self, flatpak, remote, InstalledPackage, PackageHandler};
+use crate::utils::fs as fs_utils;
+use crate::utils::lock;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+pub use crate::db::search::SearchOptions;
+pub use crate::handlers::InstallOptions;
+
+/// 「もしかして」で表示する候補の最大件数
+const SUGGESTION_LIMIT: usize = 3;
+
+/// インストール形式ごとのインストール済みパッケージ
+#[derive(Debug)]
+pub struct PackageGroup {
+    /// インストール形式
+    pub source_type: SourceType,
+    /// 形式を扱うツールが利用可能か（Flatpak未導入など）
+    pub available: bool,
+    /// インストール済みパッケージ
+    pub packages: Vec<InstalledPackage>,
+    /// 一覧の取得に失敗した場合のエラー
+    pub error: Option<String>,
+}
+
+/// アプリDBのエントリに対するインストール状態
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstallStatus {
+    /// 未インストール
+    NotInstalled,
+    /// インストール済み
+    Installed(InstalledPackage),
+    /// インストール済みだが、アプリDBにより新しいバージョンがある
+    Outdated {
+        /// インストール済みパッケージ
+        package: InstalledPackage,
+        /// アプリDBのバージョン
+        available: String,
+    },
+}
+
+/// インストール済みアプリの配置先
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallDetails {
+    /// インストール済みパッケージ
+    pub package: InstalledPackage,
+    /// 配置したファイル（AppImage本体・デスクトップエントリなど）
+    pub files: Vec<PathBuf>,
+    /// コマンド（Flatpakのラッパー、AppImageのシンボリックリンク）
+    pub wrappers: Vec<PathBuf>,
+    /// ディスク使用量（バイト、判明している場合）
+    pub disk_usage: Option<u64>,
+}
+
+/// 一括インストールのアプリごとの結果
+#[derive(Debug)]
+pub struct BatchResult {
+    /// 対象（アプリ名またはファイルパス）
+    pub name: String,
+    /// インストール結果
+    pub result: Result<Option<InstallRecord>>,
+}
+
+/// AN の操作を提供するクライアント
+#[derive(Default)]
+pub struct Client {
+    observer: Option<Rc<Observer>>,
+    /// 一括操作で先にダウンロードしたファイル（URLごと、インストール時に使用）
+    prefetched: RefCell<HashMap<String, Result<PathBuf>>>,
+}
+
+impl Client {
+    /// 設定ファイルと環境変数の設定でクライアントを作成
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 設定を指定してクライアントを作成
+    ///
+    /// 設定はプロセス全体で共有されるため、既に異なる設定を使用している場合
+    /// （別の設定でクライアントを作成した、または操作を実行した後）は E907 になります。
+    pub fn with_config(config: Config) -> Result<Self> {
+        config::init(config)?;
+        Ok(Self::default())
+    }
+
+    /// イベントの通知先を設定
+    pub fn on_event(mut self, observer: impl Fn(&Event) + 'static) -> Self {
+        self.observer = Some(Rc::new(observer));
+        self
+    }
+
+    /// 現在の設定
+    pub fn config(&self) -> &'static Config {
+        config::get()
+    }
+
+    /// 通知先を設定した状態で処理を実行
+    ///
+    /// 通知先を設定していない場合は呼び出し元の通知先（CLIの表示など）に従います。
+    fn scoped<T>(&self, f: impl FnOnce() -> T) -> T {
+        match self.observer {
+            Some(ref observer) => events::with_observer(observer.clone(), f),
+            None => f(),
+        }
+    }
+
+    /// グローバルロックを取得して実行（インストール先・記録・アプリDBを変更する操作）
+    fn locked<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.scoped(|| {
+            let _lock = lock::acquire()?;
+            f()
+        })
+    }
+
+    fn emit(&self, event: Event) {
+        events::emit(event);
+    }
+
+    /// アプリDBの全アプリ
+    ///
+    /// 同じ名前のアプリは優先度の高いリポジトリのものを返します。
+    pub fn apps(&self) -> Result<Vec<AppConfig>> {
+        app::all()
+    }
+
+    /// アプリDBから名前で取得
+    pub fn app(&self, name: &str) -> Result<Option<AppConfig>> {
+        app::find_by_name(name)
+    }
+
+    /// アプリDBを名前・エイリアス・説明・キーワードで検索
+    ///
+    /// インデックスで絞り込んで一致度順に並べ、一致したエントリのみ読み込みます。
+    /// クエリが空の場合は `options` の条件を満たす全アプリを名前順に返します。
+    pub fn search(&self, query: &str, options: &SearchOptions) -> Result<Vec<AppConfig>> {
+        let index = index::load(&app::db_dir())?;
+        search::search(&index.entries, query, options)
+            .into_iter()
+            .map(|entry| entry.load())
+            .collect()
+    }
+
+    /// アプリDBのカテゴリとアプリ数（カテゴリ名順）
+    pub fn categories(&self) -> Result<Vec<(String, usize)>> {
+        let index = index::load(&app::db_dir())?;
+        Ok(search::categories(&index.entries))
+    }
+
+    /// アプリDBにない名前に近いアプリ名の候補（「もしかして」表示用）
+    pub fn suggest(&self, name: &str) -> Result<Vec<String>> {
+        let index = index::load(&app::db_dir())?;
+        Ok(search::suggest(&index.entries, name, SUGGESTION_LIMIT))
+    }
+
+    /// アプリDBのアプリをインストール
+    pub fn install(&self, name: &str, options: &InstallOptions) -> Result<Option<InstallRecord>> {
+        let app_config = self.app(name)?.ok_or_else(|| AnError::AppNotInDatabase {
+            name: name.to_string(),
+        })?;
+        self.install_app(&app_config, options)
+    }
+
+    /// アプリDBのエントリからインストール
+    ///
+    /// 複数のソースがある場合は `options.via`、設定 `install.prefer`、アーキテクチャで
+    /// 候補を絞り込んで順に試し、失敗したら次のソースにフォールバックします。
+    /// `options.version` を指定した場合は、URLに `{version}` を含むソースのみ使用できます。
+    pub fn install_app(
+        &self,
+        app_config: &AppConfig,
+        options: &InstallOptions,
+    ) -> Result<Option<InstallRecord>> {
+        let candidates = app_config.candidates(
+            options.via,
+            self.config().install_preference(),
+            std::env::consts::ARCH,
+        )?;
+        self.locked(|| {
+            let mut failure: Option<(SourceType, anyhow::Error)> = None;
+            for source in candidates {
+                // 次の候補があるため、前の候補の失敗は警告して続行
+                if let Some((source_type, e)) = failure.take() {
+                    self.emit(Event::SourceFailed {
+                        name: app_config.app.name.clone(),
+                        source_type,
+                        message: e.to_string(),
+                    });
+                }
+                match with_version(app_config, source, options.version.as_deref())
+                    .and_then(|config| self.install_source(&config, options))
+                {
+                    Ok(record) => return Ok(record),
+                    Err(e) => failure = Some((source.source_type, e)),
+                }
+            }
+            // 最後の候補の失敗を返す
+            Err(match failure {
+                Some((_, e)) => e,
+                None => AnError::NoSuitableSource {
+                    name: app_config.app.name.clone(),
+                    message: "ソースがありません".to_string(),
+                }
+                .into(),
+            })
+        })
+    }
+
+    /// 複数のアプリを順にインストール
+    ///
+    /// 途中で失敗しても残りのアプリのインストールを続け、アプリごとの結果を記載順で返します。
+    /// 対象は既存のファイルならローカルファイル、それ以外はアプリDBのアプリ名として扱い、
+    /// `source` / `version` の指定は `options` より優先します。
+    pub fn install_batch(
+        &self,
+        targets: &[ManifestEntry],
+        options: &InstallOptions,
+    ) -> Vec<BatchResult> {
+        let target_options = |target: &ManifestEntry| InstallOptions {
+            via: target.source.or(options.via),
+            version: target.version.clone().or_else(|| options.version.clone()),
+            ..options.clone()
+        };
+
+        self.scoped(|| {
+            // 操作の間に別の AN が割り込まないよう全体でロックを保持する
+            // （取得できない場合は各操作が個別にエラーを返す）
+            let _lock = lock::acquire().ok();
+            self.prefetch(
+                targets
+                    .iter()
+                    .filter(|target| !Path::new(&target.name).exists())
+                    .map(|target| (target.name.as_str(), target_options(target))),
+            );
+            let results = targets
+                .iter()
+                .enumerate()
+                .map(|(i, target)| {
+                    events::step(format!(
+                        "[{}/{}] {} をインストール中",
+                        i + 1,
+                        targets.len(),
+                        target.name
+                    ));
+                    let path = Path::new(&target.name);
+                    let result = if path.exists() {
+                        self.install_file(path, options)
+                    } else {
+                        self.install(&target.name, &target_options(target))
+                    };
+                    BatchResult {
+                        name: target.name.clone(),
+                        result,
+                    }
+                })
+                .collect();
+            self.discard_prefetched();
+            results
+        })
+    }
+
+    /// 複数のアプリのダウンロードを先に並列で行う
+    ///
+    /// 各アプリの最初の候補のソースがAppImage・Debの場合に、設定
+    /// `install.parallel_downloads` 件まで同時にダウンロードします。
+    /// インストール（`dpkg` など）はこれまでどおり1件ずつ行い、
+    /// ダウンロード済みのファイルを使用します。対象が1件以下の場合は何もしません。
+    fn prefetch<'a>(&self, targets: impl IntoIterator<Item = (&'a str, InstallOptions)>) {
+        let mut downloads: Vec<remote::Download> = Vec::new();
+        for (name, options) in targets {
+            let Some(download) = self.download_for(name, &options) else {
+                continue;
+            };
+            if !downloads
+                .iter()
+                .any(|d| d.url == download.url || d.filename == download.filename)
+            {
+                downloads.push(download);
+            }
+        }
+        if downloads.len() < 2 {
+            return;
+        }
+
+        let results = remote::download_all(&downloads, self.config().parallel_downloads());
+        let mut prefetched = self.prefetched.borrow_mut();
+        for (download, result) in downloads.into_iter().zip(results) {
+            prefetched.insert(download.url, result);
+        }
+    }
+
+    /// アプリのインストールで最初に試すソースのダウンロード（ダウンロードしない形式は `None`）
+    fn download_for(&self, name: &str, options: &InstallOptions) -> Option<remote::Download> {
+        let app_config = self.app(name).ok()??;
+        let candidates = app_config
+            .candidates(
+                options.via,
+                self.config().install_preference(),
+                std::env::consts::ARCH,
+            )
+            .ok()?;
+        let config =
+            with_version(&app_config, candidates.first()?, options.version.as_deref()).ok()?;
+        matches!(
+            config.source.source_type,
+            SourceType::AppImage | SourceType::Deb
+        )
+        .then(|| download_of(&config))
+    }
+
+    /// 使用しなかったダウンロード済みのファイルを削除
+    fn discard_prefetched(&self) {
+        for (_, result) in self.prefetched.borrow_mut().drain() {
+            if let Ok(path) = result {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    /// 1つのソースからインストール
+    fn install_source(
+        &self,
+        app_config: &AppConfig,
+        options: &InstallOptions,
+    ) -> Result<Option<InstallRecord>> {
+        self.scoped(|| {
+            self.emit(Event::Resolved {
+                name: app_config.app.name.clone(),
+                source_type: app_config.source.source_type,
+            });
+
+            let record = match app_config.source.source_type {
+                SourceType::Flatpak => Some(self.install_flatpak(app_config)?),
+                SourceType::AppImage | SourceType::Deb => {
+                    self.install_from_url(app_config, options)?
+                }
+                SourceType::Script => {
+                    return Err(AnError::ValidationError {
+                        message: "スクリプトタイプはまだサポートされていません".to_string(),
+                    }
+                    .into())
+                }
+            };
+
+            if let Some(mut record) = record {
+                // 再インストールでは `an apply` で作成したエイリアスを引き継ぐ
+                if let Some(previous) = installed::load(&record.name)? {
+                    record.aliases = previous.aliases;
+                }
+                installed::save(&record)?;
+                self.emit(Event::Installed {
+                    record: record.clone(),
+                });
+                return Ok(Some(record));
+            }
+            Ok(None)
+        })
+    }
+
+    /// ローカルファイル（.deb / .AppImage / .flatpakref）からインストール
+    pub fn install_file(
+        &self,
+        path: &Path,
+        options: &InstallOptions,
+    ) -> Result<Option<InstallRecord>> {
+        self.locked(|| {
+            let handler = handlers::for_file(path)?;
+            self.emit(Event::Resolved {
+                name: path.display().to_string(),
+                source_type: handler.source_type(),
+            });
+
+            let record = handler.install(path, options)?;
+            if let Some(ref record) = record {
+                installed::save(record)?;
+                self.emit(Event::Installed {
+                    record: record.clone(),
+                });
+            }
+            Ok(record)
+        })
+    }
+
+    /// Flatpakアプリをインストールし、ラッパーを作成
+    fn install_flatpak(&self, app_config: &AppConfig) -> Result<InstallRecord> {
+        let flatpak_id =
+            app_config
+                .source
+                .flatpak_id
+                .as_ref()
+                .ok_or_else(|| AnError::ValidationError {
+                    message: "Flatpak IDが指定されていません".to_string(),
+                })?;
+
+        if !flatpak::is_installed() {
+            return Err(AnError::FlatpakNotInstalled.into());
+        }
+
+        // ブランチ指定時は ID//BRANCH
+        let flatpak_ref = match app_config.source.flatpak_branch {
+            Some(ref branch) => format!("{}//{}", flatpak_id, branch),
+            None => flatpak_id.clone(),
+        };
+        flatpak::install_from_remote(self.config().flatpak_remote(), &flatpak_ref)?;
+
+        self.create_flatpak_command(app_config, flatpak_id)?;
+
+        let mut record = InstallRecord::new(&app_config.app.name, SourceType::Flatpak, flatpak_id);
+        record.version = app_version(app_config);
+        Ok(record)
+    }
+
+    /// Flatpakアプリのコマンド（ラッパー）を作成（同名のコマンドがあれば警告して作成しない）
+    fn create_flatpak_command(&self, app_config: &AppConfig, flatpak_id: &str) -> Result<()> {
+        let alias_name = &app_config.app.name;
+        if flatpak::check_name_conflict(alias_name) {
+            self.emit(Event::Warning {
+                message: format!(
+                    "コマンド名 {} は既に存在するためエイリアスを作成しません",
+                    alias_name
+                ),
+            });
+            return Ok(());
+        }
+
+        let wrapper_options = flatpak::WrapperOptions {
+            command: app_config.source.flatpak_command.clone(),
+            branch: app_config.source.flatpak_branch.clone(),
+            env: app_config.source.flatpak_env.clone(),
+            file_forwarding: app_config.source.flatpak_file_forwarding.unwrap_or(true),
+        };
+        flatpak::create_wrapper_with_options(flatpak_id, alias_name, &wrapper_options)
+    }
+
+    /// URLからダウンロードしてインストール
+    fn install_from_url(
+        &self,
+        app_config: &AppConfig,
+        options: &InstallOptions,
+    ) -> Result<Option<InstallRecord>> {
+        let source_type = app_config.source.source_type;
+        let handler = handlers::for_type(source_type).ok_or_else(|| AnError::ValidationError {
+            message: format!(
+                "{} はダウンロードインストールに対応していません",
+                source_type
+            ),
+        })?;
+
+        // 一括操作で先にダウンロードしていればそのファイルを使用する
+        let download = download_of(app_config);
+        let prefetched = self.prefetched.borrow_mut().remove(&download.url);
+        let downloaded_path = match prefetched {
+            Some(result) => result?,
+            None => remote::download(&download.url, &download.filename)?,
+        };
+
+        let handler_options = InstallOptions {
+            name: options.name.clone(),
+            desktop_entry: app_config
+                .metadata
+                .as_ref()
+                .and_then(|m| m.desktop_entry)
+                .unwrap_or(false)
+                || options.desktop_entry,
+            remove_source: true,
+            via: None,
+            version: None,
+        };
+
+        let mut record = handler.install(&downloaded_path, &handler_options)?;
+        if let Some(ref mut record) = record {
+            // Debはパッケージ名ではなくアプリ名で記録する
+            if record.source_type == SourceType::Deb {
+                record.name = app_config.app.name.clone();
+            }
+            record.version = app_version(app_config);
+        }
+        Ok(record)
+    }
+
+    /// インストール済みアプリを特定
+    ///
+    /// インストール記録があれば優先し、なければ各形式のハンドラで検出します。
+    pub fn locate(&self, name: &str) -> Result<Option<InstalledPackage>> {
+        self.scoped(|| Ok(resolve(name)?.map(|(_, package)| package)))
+    }
+
+    /// アプリDBのエントリのインストール状態
+    ///
+    /// [`locate`](Self::locate) と同じくインストール記録・検出で特定し、
+    /// アプリDBの `metadata.version` と比較します。
+    pub fn status(&self, app_config: &AppConfig) -> Result<InstallStatus> {
+        self.scoped(|| {
+            let Some((handler, mut package)) = resolve(&app_config.app.name)? else {
+                return Ok(InstallStatus::NotInstalled);
+            };
+            if package.version.is_none() {
+                package.version = handler.version(&package.identifier);
+            }
+            Ok(status_of(package, app_version(app_config)))
+        })
+    }
+
+    /// インストール済みアプリの配置先・コマンド・ディスク使用量
+    pub fn details(&self, name: &str) -> Result<Option<InstallDetails>> {
+        self.scoped(|| {
+            let Some((handler, package)) = resolve(name)? else {
+                return Ok(None);
+            };
+
+            let mut files = installed::load(name)?
+                .map(|record| record.files)
+                .unwrap_or_default();
+            // 記録がない場合も、コマンドとそのリンク先は検出できる
+            let command = self.config().bin_dir().join(&package.name);
+            if command.symlink_metadata().is_ok() && !files.contains(&command) {
+                files.push(command.clone());
+            }
+            if let Ok(target) = std::fs::read_link(&command) {
+                if !files.contains(&target) {
+                    files.insert(0, target);
+                }
+            }
+
+            let (wrappers, files) = files.into_iter().partition(|f| *f == command);
+            Ok(Some(InstallDetails {
+                disk_usage: handler.disk_usage(&package.identifier),
+                package,
+                files,
+                wrappers,
+            }))
+        })
+    }
+
+    /// インストール済みアプリを削除（パージ）
+    pub fn remove(&self, name: &str) -> Result<InstalledPackage> {
+        self.locked(|| {
+            let (handler, package) = resolve(name)?.ok_or_else(|| AnError::AppNotInstalled {
+                name: name.to_string(),
+            })?;
+
+            self.emit(Event::Resolved {
+                name: name.to_string(),
+                source_type: package.source_type,
+            });
+
+            handler.remove(&package.identifier)?;
+            if let Some(record) = installed::load(name)? {
+                for alias in &record.aliases {
+                    fs_utils::unplace(&self.config().bin_dir().join(alias))?;
+                }
+            }
+            installed::remove(name)?;
+
+            self.emit(Event::Removed {
+                name: name.to_string(),
+                source_type: package.source_type,
+            });
+            Ok(package)
+        })
+    }
+
+    /// Anfile のあるべき状態とインストール記録から適用計画を作成
+    ///
+    /// アプリ名はアプリDBの名前に揃えます（アプリDBにない場合は E003）。
+    /// `prune` の場合は記載のないANのインストール記録のアプリを削除する計画にします。
+    pub fn plan(&self, desired: &Manifest, prune: bool) -> Result<Plan> {
+        let mut resolved = Manifest::default();
+        for entry in &desired.apps {
+            let app_config = self
+                .app(&entry.name)?
+                .ok_or_else(|| AnError::AppNotInDatabase {
+                    name: entry.name.clone(),
+                })?;
+            if resolved.apps.iter().any(|e| e.name == app_config.app.name) {
+                continue;
+            }
+            resolved.apps.push(ManifestEntry {
+                name: app_config.app.name,
+                ..entry.clone()
+            });
+        }
+
+        let records = installed::load_all()?;
+        let outdated = |record: &InstallRecord| {
+            let available = app_version(&self.app(&record.name).ok()??)?;
+            is_newer(&available, record.version.as_deref()?).then_some(available)
+        };
+        Ok(plan::compute(&resolved, &records, outdated, prune))
+    }
+
+    /// 適用計画を実行
+    ///
+    /// 途中で失敗しても残りの操作を続け、操作ごとの結果を計画の順で返します。
+    /// 削除の結果は `Ok(None)` です。
+    pub fn apply(&self, plan: &Plan) -> Vec<BatchResult> {
+        self.scoped(|| {
+            // 操作の間に別の AN が割り込まないよう全体でロックを保持する
+            // （取得できない場合は各操作が個別にエラーを返す）
+            let _lock = lock::acquire().ok();
+            self.prefetch(plan.actions.iter().filter_map(|action| match action {
+                Action::Install { entry } | Action::Upgrade { entry, .. } => {
+                    Some((entry.name.as_str(), entry_options(entry)))
+                }
+                _ => None,
+            }));
+            let results = plan
+                .actions
+                .iter()
+                .enumerate()
+                .map(|(i, action)| {
+                    events::step(format!(
+                        "[{}/{}] {}",
+                        i + 1,
+                        plan.actions.len(),
+                        action.name()
+                    ));
+                    BatchResult {
+                        name: action.name().to_string(),
+                        result: self.apply_action(action),
+                    }
+                })
+                .collect();
+            self.discard_prefetched();
+            results
+        })
+    }
+
+    fn apply_action(&self, action: &Action) -> Result<Option<InstallRecord>> {
+        let install = |entry: &ManifestEntry| -> Result<Option<InstallRecord>> {
+            match self.install(&entry.name, &entry_options(entry))? {
+                Some(_) => self.set_aliases(&entry.name, &entry.aliases).map(Some),
+                None => Ok(None),
+            }
+        };
+
+        match action {
+            Action::Install { entry } => install(entry),
+            Action::Upgrade {
+                entry, source_type, ..
+            } if entry.source.is_some_and(|s| s != *source_type) => {
+                self.switch_source(entry, install)
+            }
+            Action::Upgrade { entry, .. } => install(entry),
+            Action::SetAliases { name, aliases } => self.set_aliases(name, aliases).map(Some),
+            Action::Remove { name, .. } => self.remove(name).map(|_| None),
+        }
+    }
+
+    /// インストール済みアプリのソースの形式を入れ替え
+    ///
+    /// 新しい形式のインストールに成功してから古い形式を削除し、失敗した場合は元の状態に戻します。
+    /// 古い形式のコマンドがあると新しい形式のコマンド（Flatpakのラッパーなど）を作成できないため、
+    /// インストール中は `.<名前>.an-old` に退避します。
+    fn switch_source(
+        &self,
+        entry: &ManifestEntry,
+        install: impl Fn(&ManifestEntry) -> Result<Option<InstallRecord>>,
+    ) -> Result<Option<InstallRecord>> {
+        let old = installed::load(&entry.name)?.ok_or_else(|| AnError::AppNotInstalled {
+            name: entry.name.clone(),
+        })?;
+        let command = self.config().bin_dir().join(&entry.name);
+        let aside = command.with_file_name(format!(".{}.an-old", entry.name));
+        let moved = command.exists() || command.is_symlink();
+        if moved {
+            fs_utils::rename(&command, &aside)?;
+        }
+
+        let record = match install(entry) {
+            Ok(record) => record,
+            Err(e) => {
+                if moved {
+                    fs_utils::rename(&aside, &command)?;
+                }
+                installed::save(&old)?;
+                return Err(e);
+            }
+        };
+
+        fs_utils::discard(&aside)?;
+        let kept = record.as_ref().map_or(&[][..], |r| &r.files[..]);
+        self.remove_replaced(&old, &command, kept)?;
+
+        // 古い形式のコマンド（debの /usr/bin など）と衝突して作成できなかったラッパーを作成
+        if let Some(ref record) = record {
+            if record.source_type == SourceType::Flatpak && command.symlink_metadata().is_err() {
+                if let Some(app_config) = self.app(&entry.name)? {
+                    self.create_flatpak_command(&app_config, &record.identifier)?;
+                }
+            }
+        }
+        Ok(record)
+    }
+
+    /// 入れ替えた古い形式を削除（新しい形式のコマンド `command` と配置したファイル `kept` は残す）
+    fn remove_replaced(&self, old: &InstallRecord, command: &Path, kept: &[PathBuf]) -> Result<()> {
+        self.scoped(|| {
+            if old.source_type == SourceType::AppImage {
+                // AppImageの削除はコマンドのリンクを辿るため、記録したファイルを直接削除する
+                let replaced = |f: &&PathBuf| f.as_path() != command && !kept.contains(f);
+                for file in old.files.iter().filter(replaced) {
+                    fs_utils::unplace(file)?;
+                }
+            } else if let Some(handler) = handlers::for_type(old.source_type) {
+                handler.remove(&old.identifier)?;
+            }
+            self.emit(Event::Removed {
+                name: old.name.clone(),
+                source_type: old.source_type,
+            });
+            Ok(())
+        })
+    }
+
+    /// インストール済みアプリの追加のコマンド名（エイリアス）を設定
+    ///
+    /// コマンドへのシンボリックリンクをコマンドディレクトリに作成し、
+    /// 指定にないエイリアスは削除してインストール記録に保存します。
+    pub fn set_aliases(&self, name: &str, aliases: &[String]) -> Result<InstallRecord> {
+        self.locked(|| {
+            let mut record = installed::load(name)?.ok_or_else(|| AnError::AppNotInstalled {
+                name: name.to_string(),
+            })?;
+            let bin_dir = self.config().bin_dir();
+
+            for alias in record.aliases.iter().filter(|a| !aliases.contains(a)) {
+                fs_utils::unplace(&bin_dir.join(alias))?;
+            }
+
+            if !aliases.is_empty() {
+                let command =
+                    command_path(&bin_dir, name).ok_or_else(|| AnError::ValidationError {
+                        message: format!(
+                            "{} のコマンドが見つからないためエイリアスを作成できません",
+                            name
+                        ),
+                    })?;
+                for alias in aliases {
+                    let link = bin_dir.join(alias);
+                    let ours = record.aliases.contains(alias)
+                        || std::fs::read_link(&link).is_ok_and(|target| target == command);
+                    if alias == name || (link.symlink_metadata().is_ok() && !ours) {
+                        return Err(AnError::ValidationError {
+                            message: format!("コマンド {} は既に存在します", link.display()),
+                        }
+                        .into());
+                    }
+                    fs_utils::place_symlink(&command, &link)?;
+                }
+            }
+
+            record.aliases = aliases.to_vec();
+            installed::save(&record)?;
+            Ok(record)
+        })
+    }
+
+    /// 現在のインストール状態を Anfile の形式で取得
+    ///
+    /// ANのインストール記録のうちアプリDBにあるアプリを対象とし、形式とエイリアスを含めます。
+    /// バージョンはURLの `{version}` で固定できるソースの場合のみ含めます。
+    pub fn export(&self) -> Result<Manifest> {
+        self.scoped(|| {
+            let mut manifest = Manifest::default();
+            for record in installed::load_all()? {
+                let Some(app_config) = self.app(&record.name)? else {
+                    self.emit(Event::Warning {
+                        message: format!("{} はアプリDBにないため書き出しません", record.name),
+                    });
+                    continue;
+                };
+                let pinnable = app_config
+                    .sources()
+                    .any(|s| s.source_type == record.source_type && s.url.contains("{version}"));
+                manifest.apps.push(ManifestEntry {
+                    name: record.name,
+                    version: record.version.filter(|_| pinnable),
+                    source: Some(record.source_type),
+                    aliases: record.aliases,
+                });
+            }
+            Ok(manifest)
+        })
+    }
+
+    /// インストール形式ごとのインストール済みパッケージ一覧
+    pub fn inventory(&self) -> Vec<PackageGroup> {
+        self.scoped(|| {
+            handlers::registry()
+                .iter()
+                .map(|handler| {
+                    let mut group = PackageGroup {
+                        source_type: handler.source_type(),
+                        available: handler.is_available(),
+                        packages: Vec::new(),
+                        error: None,
+                    };
+                    if group.available {
+                        match handler.list() {
+                            Ok(packages) => {
+                                group.packages = packages
+                                    .into_iter()
+                                    .map(|mut p| {
+                                        p.version = handler.version(&p.identifier);
+                                        p
+                                    })
+                                    .collect();
+                            }
+                            Err(e) => group.error = Some(e.to_string()),
+                        }
+                    }
+                    group
+                })
+                .collect()
+        })
+    }
+
+    /// インストール済みパッケージ一覧（全形式）
+    pub fn installed(&self) -> Result<Vec<InstalledPackage>> {
+        self.scoped(|| {
+            let mut packages = Vec::new();
+            for group in self.inventory() {
+                if let Some(error) = group.error {
+                    self.emit(Event::Warning {
+                        message: format!("{}一覧取得エラー: {}", group.source_type, error),
+                    });
+                }
+                packages.extend(group.packages);
+            }
+            Ok(packages)
+        })
+    }
+
+    /// 登録済みの全リポジトリを同期
+    ///
+    /// 途中のリポジトリで失敗しても残りのリポジトリの同期を続け、リポジトリごとの結果を
+    /// 優先度順で返します。
+    pub fn sync(&self) -> Result<Vec<SyncResult>> {
+        self.locked(sync::sync)
+    }
+
+    /// 指定したリポジトリを同期
+    pub fn sync_repository(&self, name: &str) -> Result<SyncSummary> {
+        self.locked(|| sync::sync_repo(&repo::get(name)?))
+    }
+
+    /// 登録済みリポジトリ（優先度順）
+    pub fn repositories(&self) -> Result<Vec<Repository>> {
+        repo::list()
+    }
+
+    /// リポジトリを登録して同期
+    ///
+    /// `public_key` を指定した場合、同期時に署名を検証します。
+    /// 同期に失敗した場合は登録を取り消します。
+    pub fn add_repository(
+        &self,
+        name: &str,
+        url: &str,
+        priority: Option<i32>,
+        public_key: Option<&str>,
+    ) -> Result<SyncSummary> {
+        self.locked(|| {
+            let repository = repo::add(name, url, priority, public_key)?;
+            let summary = sync::sync_repo(&repository);
+            if summary.is_err() {
+                let _ = repo::remove(name);
+            }
+            summary
+        })
+    }
+
+    /// リポジトリの登録を解除
+    pub fn remove_repository(&self, name: &str) -> Result<()> {
+        self.locked(|| repo::remove(name))
+    }
+
+    /// 旧ツールのプログラム一覧をアプリ定義に変換して `output` へ出力
+    ///
+    /// アプリDBに既にあるアプリは出力しません。
+    pub fn import_legacy(&self, legacy_dir: &Path, output: &Path) -> Result<ImportSummary> {
+        let index = index::load(&app::db_dir())?;
+        legacy::import(legacy_dir, output, |name| index.find(name).is_some())
+    }
+
+    /// アプリ定義を検査
+    ///
+    /// `path` を省略した場合は、アプリDBのローカルのエントリと各リポジトリの同期先を
+    /// それぞれ検査します（リポジトリ間の名前の重複は優先度による上書きのため問題としない）。
+    pub fn lint(&self, path: Option<&Path>, check_urls: bool) -> Result<Vec<LintReport>> {
+        let dirs = match path {
+            Some(path) => vec![path.to_path_buf()],
+            None => repo::search_dirs(&app::db_dir())?
+                .into_iter()
+                .map(|(_, dir)| dir)
+                .filter(|dir| dir.is_dir())
+                .collect(),
+        };
+        dirs.iter()
+            .map(|dir| lint::lint_dir(dir, check_urls))
+            .collect()
+    }
+}
+
+/// アプリDBエントリのバージョンを取得
+fn app_version(app_config: &AppConfig) -> Option<String> {
+    app_config.metadata.as_ref()?.version.clone()
+}
+
+/// アプリのコマンドのパス（コマンドディレクトリになければ PATH から探す）
+fn command_path(bin_dir: &Path, name: &str) -> Option<PathBuf> {
+    let command = bin_dir.join(name);
+    if command.symlink_metadata().is_ok() {
+        return Some(command);
+    }
+    which::which(name).ok()
+}
+
+/// 指定したソースとバージョンでインストールするアプリ定義
+///
+/// バージョンはURLの `{version}` を置き換えるため、それ以外のソースでは指定できません。
+fn with_version(
+    app_config: &AppConfig,
+    source: &app::SourceInfo,
+    version: Option<&str>,
+) -> Result<AppConfig> {
+    let mut config = app_config.with_source(source);
+    if let Some(version) = version {
+        if !source.url.contains("{version}") {
+            return Err(AnError::ValidationError {
+                message: format!(
+                    "{} のソースはバージョンを指定できません（URLに {{version}} がありません）",
+                    source.source_type
+                ),
+            }
+            .into());
+        }
+        config.metadata.get_or_insert_with(Default::default).version = Some(version.to_string());
+    }
+    Ok(config)
+}
+
+/// Anfile のアプリのインストールオプション
+fn entry_options(entry: &ManifestEntry) -> InstallOptions {
+    InstallOptions {
+        via: entry.source,
+        version: entry.version.clone(),
+        ..InstallOptions::default()
+    }
+}
+
+/// URLからインストールするソースのダウンロード元と保存するファイル名
+///
+/// URLのバージョンプレースホルダは展開します。
+fn download_of(app_config: &AppConfig) -> remote::Download {
+    let name = &app_config.app.name;
+    remote::Download {
+        name: name.clone(),
+        url: app::expand_url(&app_config.source.url, app_config),
+        filename: match app_config.source.source_type {
+            SourceType::AppImage => format!("{}.AppImage", name),
+            _ => format!("{}.deb", name),
+        },
+    }
+}
+
+/// インストール済みパッケージとアプリDBのバージョンからインストール状態を判定
+fn status_of(package: InstalledPackage, available: Option<String>) -> InstallStatus {
+    match (available, &package.version) {
+        (Some(available), Some(installed)) if is_newer(&available, installed) => {
+            InstallStatus::Outdated { package, available }
+        }
+        _ => InstallStatus::Installed(package),
+    }
+}
+
+/// `available` が `installed` より新しいバージョンか
+///
+/// エポック（`1:`）を除いた数字の並びで比較します（`1.10` > `1.9`、`1.2` = `1.2.0`）。
+fn is_newer(available: &str, installed: &str) -> bool {
+    version_key(available) > version_key(installed)
+}
+
+fn version_key(version: &str) -> Vec<u64> {
+    let version = version.split_once(':').map_or(version, |(_, v)| v);
+    let mut key: Vec<u64> = version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|part| part.parse().ok())
+        .collect();
+    while key.last() == Some(&0) {
+        key.pop();
+    }
+    key
+}
+
+/// インストール記録または検出からハンドラとパッケージ情報を取得
+fn resolve(name: &str) -> Result<Option<(&'static dyn PackageHandler, InstalledPackage)>> {
+    let from_record = installed::load(name)?.and_then(package_from_record);
+    Ok(from_record.or_else(|| handlers::detect(name).map(|(h, id)| package_for(h, name, id))))
+}
+
+/// 検出結果からパッケージ情報を作成
+fn package_for(
+    handler: &'static dyn PackageHandler,
+    name: &str,
+    identifier: String,
+) -> (&'static dyn PackageHandler, InstalledPackage) {
+    let package = InstalledPackage {
+        name: name.to_string(),
+        identifier,
+        source_type: handler.source_type(),
+        location: None,
+        version: None,
+    };
+    (handler, package)
+}
+
+/// インストール記録からパッケージ情報を作成
+fn package_from_record(
+    record: InstallRecord,
+) -> Option<(&'static dyn PackageHandler, InstalledPackage)> {
+    let handler = handlers::for_type(record.source_type)?;
+    let (handler, mut package) = package_for(handler, &record.name, record.identifier);
+    package.version = record.version;
+    Some((handler, package))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_package_from_record() {
+        let mut record = InstallRecord::new("code", SourceType::Deb, "code");
+        record.version = Some("1.95.3".to_string());
+        let (handler, package) = package_from_record(record).unwrap();
+        assert_eq!(handler.source_type(), SourceType::Deb);
+        assert_eq!(package.identifier, "code");
+        assert_eq!(package.version.as_deref(), Some("1.95.3"));
+
+        let script = InstallRecord::new("tool", SourceType::Script, "tool");
+        assert!(package_from_record(script).is_none());
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("120.0", "119.0.1"));
+        assert!(is_newer("1.10", "1.9"));
+        assert!(is_newer("v2.0", "1.99"));
+        assert!(!is_newer("1.2", "1.2.0"));
+        assert!(!is_newer("1.95.3", "1.95.3-1700000000"));
+        assert!(!is_newer("2.0", "1:2.0-1ubuntu1"));
+        assert!(!is_newer("1.0", "1.1"));
+    }
+
+    #[test]
+    fn test_status_of() {
+        let mut package = InstalledPackage {
+            name: "firefox".to_string(),
+            identifier: "firefox".to_string(),
+            source_type: SourceType::AppImage,
+            location: None,
+            version: Some("119.0".to_string()),
+        };
+        assert_eq!(
+            status_of(package.clone(), Some("120.0".to_string())),
+            InstallStatus::Outdated {
+                package: package.clone(),
+                available: "120.0".to_string()
+            }
+        );
+        assert_eq!(
+            status_of(package.clone(), Some("119.0".to_string())),
+            InstallStatus::Installed(package.clone())
+        );
+        // バージョンが不明な場合は比較しない
+        assert_eq!(
+            status_of(package.clone(), None),
+            InstallStatus::Installed(package.clone())
+        );
+        package.version = None;
+        assert_eq!(
+            status_of(package.clone(), Some("120.0".to_string())),
+            InstallStatus::Installed(package)
+        );
+    }
+
+    #[test]
+    fn test_on_event_receives_events() {
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let sink = received.clone();
+        let client = Client::new().on_event(move |e| sink.borrow_mut().push(e.clone()));
+
+        client.scoped(|| {
+            client.emit(Event::Warning {
+                message: "test".to_string(),
+            })
+        });
+        assert_eq!(
+            *received.borrow(),
+            vec![Event::Warning {
+                message: "test".to_string()
+            }]
+        );
+    }
+
+    /// アプリDB・状態ディレクトリを一時ディレクトリにして実行（ロックファイルなどを作成するため）
+    fn isolated<T>(f: impl FnOnce() -> T) -> T {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        for (key, name) in [
+            ("paths.db_dir", "apps"),
+            ("paths.state_dir", "state"),
+            ("paths.cache_dir", "cache"),
+        ] {
+            config
+                .set(key, dir.path().join(name).to_str().unwrap())
+                .unwrap();
+        }
+        config::with_config(config, f)
+    }
+
+    #[test]
+    fn test_install_app_falls_back_to_next_source() {
+        let source = |source_type| crate::db::app::SourceInfo {
+            source_type,
+            url: String::new(),
+            flatpak_id: None,
+            flatpak_branch: None,
+            flatpak_command: None,
+            flatpak_env: Default::default(),
+            flatpak_file_forwarding: None,
+            architecture: vec![std::env::consts::ARCH.to_string()],
+        };
+        let app_config = AppConfig {
+            app: crate::db::app::AppInfo {
+                name: "tool".to_string(),
+                aliases: Vec::new(),
+                description: "Tool".to_string(),
+                homepage: None,
+            },
+            // Flatpak IDがないため失敗し、次のソースを試す
+            source: source(SourceType::Flatpak),
+            alternatives: vec![source(SourceType::Script)],
+            metadata: None,
+            repo: None,
+        };
+
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let sink = received.clone();
+        let client = Client::new().on_event(move |e| sink.borrow_mut().push(e.clone()));
+        let err =
+            isolated(|| client.install_app(&app_config, &InstallOptions::default())).unwrap_err();
+        assert!(err.to_string().contains("スクリプトタイプ"), "{}", err);
+
+        let received = received.borrow();
+        let resolved: Vec<SourceType> = received
+            .iter()
+            .filter_map(|e| match e {
+                Event::Resolved { source_type, .. } => Some(*source_type),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(resolved, [SourceType::Flatpak, SourceType::Script]);
+        assert!(received.iter().any(|e| matches!(
+            e,
+            Event::SourceFailed { source_type: SourceType::Flatpak, message, .. }
+                if message.contains("Flatpak ID")
+        )));
+
+        // viaで形式を指定した場合はそのソースのみ
+        let options = InstallOptions {
+            via: Some(SourceType::Flatpak),
+            ..InstallOptions::default()
+        };
+        let err = isolated(|| Client::new().install_app(&app_config, &options)).unwrap_err();
+        assert!(err.to_string().contains("Flatpak ID"), "{}", err);
+    }
+
+    #[test]
+    fn test_install_file_unknown_type() {
+        let err = isolated(|| {
+            Client::new().install_file(Path::new("/tmp/file.xyz"), &InstallOptions::default())
+        })
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AnError>(),
+            Some(AnError::UnknownFileType { .. })
+        ));
+    }
+}
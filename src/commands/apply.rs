@@ -0,0 +1,202 @@
+//! apply / export コマンド実装
+//!
+//! Anfile に記載したあるべき状態になるようアプリをインストール・更新・削除し、
+//! 現在のインストール状態を同じ形式で書き出します。
+
+use crate::db::manifest;
+use crate::db::plan::Action;
+use crate::errors::AnError;
+use crate::ui;
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+/// applyコマンドのエントリーポイント
+///
+/// 計画を表示して確認後に実行します。`dry_run` の場合は計画の表示のみ行います。
+pub fn run(file: &Path, prune: bool, dry_run: bool) -> Result<()> {
+    ui::info(&format!("Anfileを読み込み中: {}", file.display()));
+    let desired = manifest::load(file)?;
+
+    let client = super::client();
+    let plan = client.plan(&desired, prune)?;
+
+    if plan.is_empty() {
+        ui::success(&format!(
+            "変更はありません（{} 件のアプリが記載どおりです）",
+            plan.unchanged.len()
+        ));
+        return Ok(());
+    }
+
+    ui::info(&format!("{} 件の変更があります:", plan.actions.len()));
+    for action in &plan.actions {
+        println!("  {}", format_action(action));
+    }
+    if !plan.unchanged.is_empty() {
+        ui::info(&format!("変更なし: {}", plan.unchanged.join(", ")));
+    }
+
+    if dry_run {
+        return Ok(());
+    }
+    if !ui::confirm("続行しますか?")? {
+        ui::warn("適用をキャンセルしました");
+        return Ok(());
+    }
+
+    let results = client.apply(&plan);
+    println!();
+    let mut failed = 0;
+    for (action, result) in plan.actions.iter().zip(&results) {
+        match result.result {
+            Ok(_) => println!("  ✓ {}", format_action(action)),
+            Err(ref e) => {
+                failed += 1;
+                println!("  ✗ {}: {}", format_action(action), e);
+            }
+        }
+    }
+    println!();
+
+    if failed > 0 {
+        return Err(AnError::BatchInstallFailed {
+            failed,
+            total: results.len(),
+        }
+        .into());
+    }
+    ui::success(&format!("{} 件の変更を適用しました", results.len()));
+    Ok(())
+}
+
+/// exportコマンドのエントリーポイント
+///
+/// ファイルを指定しない場合は標準出力に出力します。
+pub fn export(file: Option<&Path>) -> Result<()> {
+    let content = manifest::to_string(&super::client().export()?)?;
+
+    match file {
+        Some(file) => {
+            fs::write(file, content)?;
+            ui::success(&format!("{} に書き出しました", file.display()));
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+/// 操作を1行で表示（例: `~ obsidian 1.5.0 -> 1.5.3`）
+fn format_action(action: &Action) -> String {
+    let aliases = |aliases: &[String]| {
+        if aliases.is_empty() {
+            String::new()
+        } else {
+            format!("（エイリアス: {}）", aliases.join(", "))
+        }
+    };
+
+    match action {
+        Action::Install { entry } => {
+            let mut line = format!("+ {}", entry.name);
+            if let Some(ref version) = entry.version {
+                line.push_str(&format!(" {}", version));
+            }
+            if let Some(source) = entry.source {
+                line.push_str(&format!(" ({})", source));
+            }
+            line + &aliases(&entry.aliases)
+        }
+        Action::Upgrade {
+            entry,
+            source_type,
+            installed,
+            target,
+        } => {
+            let mut line = format!("~ {}", entry.name);
+            match (installed, target) {
+                (Some(installed), Some(target)) if installed != target => {
+                    line.push_str(&format!(" {} -> {}", installed, target))
+                }
+                (_, Some(target)) => line.push_str(&format!(" -> {}", target)),
+                _ => {}
+            }
+            match entry.source {
+                Some(source) if source != *source_type => {
+                    line.push_str(&format!(" ({} -> {})", source_type, source))
+                }
+                _ => {}
+            }
+            line + &aliases(&entry.aliases)
+        }
+        Action::SetAliases {
+            name,
+            aliases: list,
+        } if list.is_empty() => {
+            format!("= {}（エイリアスを削除）", name)
+        }
+        Action::SetAliases {
+            name,
+            aliases: list,
+        } => format!("= {}{}", name, aliases(list)),
+        Action::Remove { name, source_type } => format!("- {} ({})", name, source_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::app::SourceType;
+    use crate::db::manifest::ManifestEntry;
+
+    #[test]
+    fn test_format_action() {
+        let entry = ManifestEntry {
+            version: Some("1.5.3".to_string()),
+            source: Some(SourceType::AppImage),
+            aliases: vec!["notes".to_string()],
+            ..ManifestEntry::new("obsidian")
+        };
+        assert_eq!(
+            format_action(&Action::Install {
+                entry: entry.clone()
+            }),
+            "+ obsidian 1.5.3 (AppImage)（エイリアス: notes）"
+        );
+        assert_eq!(
+            format_action(&Action::Upgrade {
+                entry: ManifestEntry::new("obsidian"),
+                source_type: SourceType::AppImage,
+                installed: Some("1.5.0".to_string()),
+                target: Some("1.5.3".to_string()),
+            }),
+            "~ obsidian 1.5.0 -> 1.5.3"
+        );
+        assert_eq!(
+            format_action(&Action::Upgrade {
+                entry: ManifestEntry {
+                    source: Some(SourceType::Flatpak),
+                    ..ManifestEntry::new("gimp")
+                },
+                source_type: SourceType::AppImage,
+                installed: Some("2.10".to_string()),
+                target: None,
+            }),
+            "~ gimp (AppImage -> Flatpak)"
+        );
+        assert_eq!(
+            format_action(&Action::SetAliases {
+                name: "code".to_string(),
+                aliases: Vec::new(),
+            }),
+            "= code（エイリアスを削除）"
+        );
+        assert_eq!(
+            format_action(&Action::Remove {
+                name: "tool".to_string(),
+                source_type: SourceType::Deb,
+            }),
+            "- tool (Deb)"
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! config コマンド実装
+//!
+//! `~/.config/an/config.toml` の設定値を表示・変更します。
+
+use crate::config::{self, Config};
+use crate::ui;
+use anyhow::Result;
+use std::path::Path;
+
+/// 設定値を表示
+pub fn get(key: &str) -> Result<()> {
+    println!("{}", config::get().get(key)?);
+    Ok(())
+}
+
+/// 設定値を変更して設定ファイルに保存
+///
+/// `file`（グローバルオプション `--config`）を指定した場合はそのファイルに保存します。
+/// 環境変数やCLIオプションによる上書きは保存されません。
+pub fn set(key: &str, value: &str, file: Option<&Path>) -> Result<()> {
+    let path = file
+        .map(Path::to_path_buf)
+        .unwrap_or_else(config::config_path);
+    let mut file_config = Config::load_file(&path)?;
+    file_config.set(key, value)?;
+    file_config.save_file(&path)?;
+
+    if value.is_empty() {
+        ui::success(&format!("{} をデフォルトに戻しました", key));
+    } else {
+        ui::success(&format!("{} = {}", key, value));
+    }
+    ui::info(&format!("  設定ファイル: {}", path.display()));
+    Ok(())
+}
+
+/// 全設定値を一覧表示
+pub fn list() -> Result<()> {
+    let current = config::get();
+    for (key, _) in config::KEYS {
+        println!("{} = {}", key, current.get(key)?);
+    }
+    Ok(())
+}
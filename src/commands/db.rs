@@ -0,0 +1,62 @@
+//! db コマンド実装
+//!
+//! アプリDBのメンテナンス（旧ツールの一覧の取り込み・アプリ定義の検査）を行います。
+
+use crate::db::legacy::REVIEW_DIR;
+use crate::errors::AnError;
+use crate::ui;
+use anyhow::Result;
+use std::path::Path;
+
+/// 旧ツールのプログラム一覧をアプリ定義に変換
+pub fn import_legacy(legacy_dir: &Path, output: &Path) -> Result<()> {
+    ui::info(&format!(
+        "旧ツールの一覧を変換中: {} -> {}",
+        legacy_dir.display(),
+        output.display()
+    ));
+
+    let summary = super::client().import_legacy(legacy_dir, output)?;
+
+    for item in &summary.needs_review {
+        ui::detail(&format!("{}: {}", item.name, item.reason));
+    }
+
+    ui::success(&format!(
+        "{} 件を変換しました（要確認 {} 件、除外 {} 件）",
+        summary.imported.len(),
+        summary.needs_review.len(),
+        summary.skipped.len()
+    ));
+    if !summary.needs_review.is_empty() {
+        ui::info(&format!(
+            "要確認のエントリは {} に出力しました（理由は各ファイルの先頭、--verbose で一覧表示）",
+            output.join(REVIEW_DIR).display()
+        ));
+    }
+
+    Ok(())
+}
+
+/// アプリ定義を検査し、問題があればエラーで終了
+pub fn lint(path: Option<&Path>, check_urls: bool) -> Result<()> {
+    let reports = super::client().lint(path, check_urls)?;
+
+    let mut files = 0;
+    let mut count = 0;
+    for report in &reports {
+        ui::info(&format!("検査中: {}", report.dir.display()));
+        for issue in &report.issues {
+            println!("  {}: {}", issue.path.display(), issue.message);
+        }
+        files += report.files;
+        count += report.issues.len();
+    }
+
+    if count > 0 {
+        return Err(AnError::LintFailed { count }.into());
+    }
+
+    ui::success(&format!("{} 件のアプリ定義に問題はありません", files));
+    Ok(())
+}
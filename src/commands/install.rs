@@ -1,23 +1,18 @@
 //! install コマンド実装
 //!
 //! ローカルファイル（.deb, .AppImage）またはリモートアプリをインストールします。
+//! 複数のアプリ・マニフェストファイル（`-f`）を指定した場合は順にインストールし、
+//! 最後に成功・失敗の一覧を表示します。
 
-use crate::db;
+use crate::client::{self, BatchResult, Client};
 use crate::db::app::SourceType;
+use crate::db::manifest::{self, ManifestEntry};
 use crate::errors::AnError;
-use crate::handlers::{appimage, deb, flatpak, remote};
-use crate::utils::{fs as fs_utils, ui};
+use crate::handlers::flatpak;
+use crate::ui;
 use anyhow::Result;
 use std::path::Path;
 
-/// ファイルタイプ
-#[derive(Debug, PartialEq)]
-pub enum FileType {
-    Deb,
-    AppImage,
-    Flatpakref,
-}
-
 /// インストールオプション
 #[derive(Default)]
 pub struct InstallOptions {
@@ -27,27 +22,8 @@ pub struct InstallOptions {
     pub desktop: bool,
     /// 元ファイルを削除（移動モード）
     pub move_file: bool,
-}
-
-/// ファイルの拡張子からタイプを判定
-pub fn detect_file_type(path: &str) -> Result<FileType, AnError> {
-    let path = Path::new(path);
-    let extension = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|e| e.to_lowercase());
-
-    match extension.as_deref() {
-        Some("deb") => Ok(FileType::Deb),
-        Some("appimage") => Ok(FileType::AppImage),
-        Some("flatpakref") => Ok(FileType::Flatpakref),
-        Some(ext) => Err(AnError::UnknownFileType {
-            extension: ext.to_string(),
-        }),
-        None => Err(AnError::UnknownFileType {
-            extension: "なし".to_string(),
-        }),
-    }
+    /// 使用するソースの形式（アプリDBからのインストール用）
+    pub via: Option<SourceType>,
 }
 
 /// installコマンドのエントリーポイント
@@ -73,28 +49,7 @@ pub fn run_with_options(target: &str, options: InstallOptions) -> Result<()> {
 fn install_local(path: &str, options: InstallOptions) -> Result<()> {
     ui::info(&format!("ローカルファイルをインストール: {}", path));
 
-    let file_type = detect_file_type(path)?;
-
-    match file_type {
-        FileType::Deb => {
-            ui::info("検出: Debianパッケージ (.deb)");
-            deb::install(Path::new(path))?;
-        }
-        FileType::AppImage => {
-            ui::info("検出: AppImage");
-            let appimage_options = appimage::InstallOptions {
-                name: options.name,
-                desktop_entry: options.desktop,
-                remove_source: options.move_file,
-            };
-            appimage::install_with_options(Path::new(path), appimage_options)?;
-        }
-        FileType::Flatpakref => {
-            ui::info("検出: Flatpakref");
-            flatpak::install_from_ref(Path::new(path))?;
-        }
-    }
-
+    super::client().install_file(Path::new(path), &options.into())?;
     Ok(())
 }
 
@@ -102,42 +57,61 @@ fn install_local(path: &str, options: InstallOptions) -> Result<()> {
 fn install_remote(name: &str, options: InstallOptions) -> Result<()> {
     ui::info(&format!("アプリを検索中: {}", name));
 
+    let client = super::client();
+
     // アプリDBから検索
-    let app_config = db::find_by_name(name)?.ok_or_else(|| AnError::AppNotInDatabase {
-        name: name.to_string(),
-    })?;
-
-    // ソースタイプに応じた処理
-    match app_config.source.source_type {
-        SourceType::Flatpak => {
-            install_flatpak(&app_config)?;
+    let Some(app_config) = client.app(name)? else {
+        let suggestions = client.suggest(name)?;
+        if suggestions.is_empty() {
+            ui::info(&format!(
+                "'an search {}' でアプリを検索してみてください",
+                name
+            ));
+        } else {
+            ui::info(&format!("もしかして: {}", suggestions.join(", ")));
         }
-        SourceType::AppImage | SourceType::Deb => {
-            install_from_url(&app_config, options)?;
+        return Err(AnError::AppNotInDatabase {
+            name: name.to_string(),
         }
-        SourceType::Script => {
-            ui::warn("スクリプトタイプはまだサポートされていません");
+        .into());
+    };
+
+    let candidates = app_config.candidates(
+        options.via,
+        client.config().install_preference(),
+        std::env::consts::ARCH,
+    )?;
+
+    // ソースタイプに応じた確認表示（複数ある場合は試す順に表示）
+    for (i, source) in candidates.iter().enumerate() {
+        let prefix = if candidates.len() > 1 {
+            format!("{}. [{}] ", i + 1, source.source_type)
+        } else {
+            String::new()
+        };
+        match source.source_type {
+            SourceType::Flatpak => {
+                if let Some(ref flatpak_id) = source.flatpak_id {
+                    ui::info(&format!("{}Flatpak ID: {}", prefix, flatpak_id));
+                }
+            }
+            SourceType::AppImage | SourceType::Deb => {
+                ui::info(&format!("{}ソース: {}", prefix, source.url));
+            }
+            SourceType::Script => {
+                if candidates.len() == 1 {
+                    ui::warn("スクリプトタイプはまだサポートされていません");
+                    return Ok(());
+                }
+            }
         }
     }
-
-    Ok(())
-}
-
-/// Flatpakアプリをインストール
-fn install_flatpak(app_config: &db::app::AppConfig) -> Result<()> {
-    let flatpak_id =
-        app_config
-            .source
-            .flatpak_id
-            .as_ref()
-            .ok_or_else(|| AnError::ValidationError {
-                message: "Flatpak IDが指定されていません".to_string(),
-            })?;
-
-    ui::info(&format!("Flatpak ID: {}", flatpak_id));
-
-    // Flatpakがインストールされているか確認
-    if !flatpak::is_installed() {
+    // Flatpakしか候補がない場合は確認前にFlatpakがインストールされているか確認
+    if candidates
+        .iter()
+        .all(|s| s.source_type == SourceType::Flatpak)
+        && !flatpak::is_installed()
+    {
         return Err(AnError::FlatpakNotInstalled.into());
     }
 
@@ -146,118 +120,214 @@ fn install_flatpak(app_config: &db::app::AppConfig) -> Result<()> {
         return Ok(());
     }
 
-    // flatpak installを実行
-    ui::info("Flatpakをインストール中...");
-    let output = std::process::Command::new("flatpak")
-        .args(["install", "-y", "flathub", flatpak_id])
-        .output()?;
+    client.install_app(&app_config, &options.into())?;
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(AnError::FlatpakInstallError {
-            message: stderr.to_string(),
+    ui::success(&format!("{} をインストールしました", app_config.app.name));
+    Ok(())
+}
+
+/// 複数のアプリ・マニフェストのアプリを一括インストール
+///
+/// 途中で失敗しても残りのアプリのインストールを続け、失敗があった場合は
+/// 一覧の表示後に E010 エラーで終了します。
+pub fn run_batch(
+    targets: &[String],
+    manifest: Option<&Path>,
+    options: InstallOptions,
+) -> Result<()> {
+    if options.name.is_some() {
+        return Err(AnError::ValidationError {
+            message: "--name は1つのアプリのインストールでのみ指定できます".to_string(),
         }
         .into());
     }
 
-    // エイリアス作成
-    let alias_name = app_config.app.name.clone();
-    if !flatpak::check_name_conflict(&alias_name) {
-        flatpak::create_wrapper(flatpak_id, &alias_name)?;
-        ui::info(&format!("エイリアス作成: {}", alias_name));
+    let mut entries: Vec<ManifestEntry> = targets.iter().map(|t| ManifestEntry::new(t)).collect();
+    if let Some(path) = manifest {
+        ui::info(&format!("マニフェストを読み込み中: {}", path.display()));
+        entries.extend(manifest::load(path)?.apps);
     }
 
-    ui::success(&format!("{} をインストールしました", app_config.app.name));
-    Ok(())
-}
-
-/// URLからダウンロードしてインストール
-fn install_from_url(app_config: &db::app::AppConfig, options: InstallOptions) -> Result<()> {
-    // URL表示と確認
-    ui::info(&format!("ソース: {}", app_config.source.url));
+    let client = super::client();
+    let options: client::InstallOptions = options.into();
+
+    // インストール前に対象を確認し、アプリDBにない・ソースがないものは失敗として扱う
+    ui::info(&format!(
+        "{} 件のアプリをインストールします:",
+        entries.len()
+    ));
+    let mut rows: Vec<Option<Row>> = Vec::new();
+    let mut planned = Vec::new();
+    for entry in entries {
+        match describe(&client, &entry, &options) {
+            Ok(description) => {
+                println!("  {} ({})", entry.name, description);
+                rows.push(None);
+                planned.push(entry);
+            }
+            Err(e) => {
+                ui::warn(&format!("{}: {}", entry.name, e));
+                rows.push(Some(Row::failed(&entry.name, e.to_string())));
+            }
+        }
+    }
 
-    if !ui::confirm("続行しますか?")? {
-        ui::warn("インストールをキャンセルしました");
-        return Ok(());
+    if !planned.is_empty() {
+        if !ui::confirm("続行しますか?")? {
+            ui::warn("インストールをキャンセルしました");
+            return Ok(());
+        }
+        // 確認で除外しなかった行を記載順に埋める
+        let mut results = client.install_batch(&planned, &options).into_iter();
+        for row in rows.iter_mut().filter(|row| row.is_none()) {
+            *row = results.next().map(Row::from);
+        }
     }
 
-    // URLを展開（バージョンプレースホルダ置換）
-    let url = db::app::expand_url(&app_config.source.url, app_config);
+    let rows: Vec<Row> = rows.into_iter().flatten().collect();
+    print_summary(&rows);
 
-    // ファイル名を決定
-    let filename = match app_config.source.source_type {
-        SourceType::AppImage => format!("{}.AppImage", app_config.app.name),
-        SourceType::Deb => format!("{}.deb", app_config.app.name),
-        _ => app_config.app.name.clone(),
-    };
+    let failed = rows.iter().filter(|row| !row.success).count();
+    if failed > 0 {
+        return Err(AnError::BatchInstallFailed {
+            failed,
+            total: rows.len(),
+        }
+        .into());
+    }
+    ui::success(&format!("{} 件のアプリをインストールしました", rows.len()));
+    Ok(())
+}
 
-    // ダウンロード
-    let downloaded_path = remote::download(&url, &filename)?;
+/// インストール前の確認表示（ソースの形式など）
+fn describe(
+    client: &Client,
+    entry: &ManifestEntry,
+    options: &client::InstallOptions,
+) -> Result<String> {
+    if Path::new(&entry.name).exists() {
+        return Ok("ローカルファイル".to_string());
+    }
 
-    // ファイルタイプに応じた処理
-    match app_config.source.source_type {
-        SourceType::Deb => {
-            deb::install(&downloaded_path)?;
-            fs_utils::remove_file(&downloaded_path)?;
-        }
-        SourceType::AppImage => {
-            let appimage_options = appimage::InstallOptions {
-                name: options.name,
-                desktop_entry: app_config
-                    .metadata
-                    .as_ref()
-                    .map(|m| m.desktop_entry.unwrap_or(false))
-                    .unwrap_or(false)
-                    || options.desktop,
-                remove_source: true,
-            };
-            appimage::install_with_options(&downloaded_path, appimage_options)?;
+    let Some(app_config) = client.app(&entry.name)? else {
+        let suggestions = client.suggest(&entry.name)?;
+        let error = AnError::AppNotInDatabase {
+            name: entry.name.clone(),
+        };
+        if suggestions.is_empty() {
+            return Err(error.into());
         }
-        _ => {}
-    }
+        return Err(anyhow::anyhow!(
+            "{}（もしかして: {}）",
+            error,
+            suggestions.join(", ")
+        ));
+    };
 
-    ui::success(&format!("{} をインストールしました", app_config.app.name));
-    Ok(())
+    let candidates = app_config.candidates(
+        entry.source.or(options.via),
+        client.config().install_preference(),
+        std::env::consts::ARCH,
+    )?;
+    let sources: Vec<String> = candidates
+        .iter()
+        .map(|s| s.source_type.to_string())
+        .collect();
+    let mut description = sources.join(" → ");
+    if let Some(ref version) = entry.version {
+        description.push_str(&format!(", バージョン {}", version));
+    }
+    Ok(description)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// 一括インストールの結果の1行
+struct Row {
+    name: String,
+    success: bool,
+    detail: String,
+}
 
-    #[test]
-    fn test_detect_file_type_deb() {
-        let result = detect_file_type("package.deb");
-        assert_eq!(result.unwrap(), FileType::Deb);
+impl Row {
+    fn failed(name: &str, message: String) -> Self {
+        Self {
+            name: name.to_string(),
+            success: false,
+            detail: message,
+        }
     }
+}
 
-    #[test]
-    fn test_detect_file_type_appimage() {
-        let result = detect_file_type("app.AppImage");
-        assert_eq!(result.unwrap(), FileType::AppImage);
+impl From<BatchResult> for Row {
+    fn from(result: BatchResult) -> Self {
+        match result.result {
+            Ok(Some(record)) => Self {
+                name: result.name,
+                success: true,
+                detail: match record.version {
+                    Some(version) => format!("{} {}", record.source_type, version),
+                    None => record.source_type.to_string(),
+                },
+            },
+            Ok(None) => Self {
+                name: result.name,
+                success: true,
+                detail: String::new(),
+            },
+            Err(e) => Self::failed(&result.name, e.to_string()),
+        }
     }
+}
 
-    #[test]
-    fn test_detect_file_type_appimage_lowercase() {
-        let result = detect_file_type("app.appimage");
-        assert_eq!(result.unwrap(), FileType::AppImage);
+/// 成功・失敗の一覧を表示
+fn print_summary(rows: &[Row]) {
+    let width = rows
+        .iter()
+        .map(|row| row.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    println!();
+    ui::info("=== インストール結果 ===");
+    for row in rows {
+        let padding = " ".repeat(width - row.name.chars().count());
+        let status = if row.success {
+            "✓ 成功"
+        } else {
+            "✗ 失敗"
+        };
+        println!("  {}{}  {}  {}", row.name, padding, status, row.detail)
     }
+    println!();
+}
 
-    #[test]
-    fn test_detect_file_type_unknown() {
-        let result = detect_file_type("file.xyz");
-        assert!(result.is_err());
-        match result {
-            Err(AnError::UnknownFileType { extension }) => {
-                assert_eq!(extension, "xyz");
-            }
-            _ => panic!("Expected UnknownFileType error"),
+impl From<InstallOptions> for client::InstallOptions {
+    fn from(options: InstallOptions) -> Self {
+        Self {
+            name: options.name,
+            desktop_entry: options.desktop,
+            remove_source: options.move_file,
+            via: options.via,
+            version: None,
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
-    fn test_detect_file_type_no_extension() {
-        let result = detect_file_type("file");
-        assert!(result.is_err());
+    fn test_install_options_into_client_options() {
+        let options: client::InstallOptions = InstallOptions {
+            name: Some("tool".to_string()),
+            desktop: true,
+            move_file: true,
+            via: Some(SourceType::Flatpak),
+        }
+        .into();
+        assert_eq!(options.name.as_deref(), Some("tool"));
+        assert!(options.desktop_entry);
+        assert!(options.remove_source);
+        assert_eq!(options.via, Some(SourceType::Flatpak));
     }
 
     #[test]
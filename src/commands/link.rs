@@ -4,7 +4,7 @@
 
 use crate::errors::AnError;
 use crate::handlers::flatpak;
-use crate::utils::ui;
+use crate::ui;
 use anyhow::Result;
 
 /// アプリ名を正規化
@@ -55,6 +55,15 @@ pub fn run() -> Result<()> {
         return Ok(());
     }
 
+    // 旧テンプレートのラッパーを再生成
+    let upgraded = flatpak::upgrade_wrappers()?;
+    if upgraded > 0 {
+        ui::info(&format!(
+            "Upgraded {} wrappers to the latest template",
+            upgraded
+        ));
+    }
+
     let mut created = 0;
     let mut skipped = 0;
 
@@ -71,8 +80,12 @@ pub fn run() -> Result<()> {
             continue;
         }
 
-        // ラッパー作成
-        match flatpak::create_wrapper(&app.id, &name) {
+        // ラッパー作成（既存ラッパーのオプションは引き継ぐ）
+        let options = flatpak::read_wrapper(&name)
+            .filter(|w| w.app_id == app.id)
+            .map(|w| w.options)
+            .unwrap_or_default();
+        match flatpak::create_wrapper_with_options(&app.id, &name, &options) {
             Ok(_) => {
                 ui::info(&format!("  {:<12} → {}", name, app.id));
                 created += 1;
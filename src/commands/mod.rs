@@ -1,11 +1,83 @@
 //! コマンドモジュール
 //!
 //! 各サブコマンドの実装を提供します。
+//! 処理は [`Client`] に委ね、確認プロンプトと結果の表示のみを担当します。
 
+pub mod apply;
+pub mod config;
+pub mod db;
 pub mod install;
 pub mod link;
 pub mod list;
 pub mod remove;
+pub mod repo;
 pub mod search;
 pub mod sync;
 pub mod update;
+
+use crate::client::Client;
+use crate::events::Event;
+use crate::ui;
+
+/// クライアントを作成
+///
+/// イベントはCLI全体で設定された通知先（[`render`]）へ送られます。
+pub fn client() -> Client {
+    Client::new()
+}
+
+/// イベントを表示
+///
+/// ファイル配置やコマンド実行などの詳細は `--verbose` 時のみ表示します。
+pub fn render(event: &Event) {
+    match event {
+        Event::Step { message } => ui::info(message),
+        Event::Resolved { source_type, .. } => ui::info(&format!("検出: {}", source_type)),
+        Event::SourceFailed {
+            source_type,
+            message,
+            ..
+        } => ui::warn(&format!(
+            "{} でのインストールに失敗しました: {}（次のソースを試します）",
+            source_type, message
+        )),
+        Event::DownloadStarted { url } => ui::info(&format!("ダウンロード中: {}", url)),
+        Event::DownloadProgress {
+            downloaded,
+            total: Some(total),
+        } => ui::progress(*downloaded, *total),
+        Event::DownloadFinished { path } => ui::detail(&format!("保存: {}", path.display())),
+        Event::ParallelDownloadStarted { names } => {
+            ui::info(&format!("{} 件を並列にダウンロード中", names.len()));
+            ui::multi_progress_start(names)
+        }
+        Event::ParallelDownloadProgress {
+            index,
+            downloaded,
+            total,
+        } => ui::multi_progress_update(*index, *downloaded, *total),
+        Event::ParallelDownloadFinished { index, success } => {
+            ui::multi_progress_finish(*index, *success)
+        }
+        Event::FilePlaced { path } => ui::detail(&format!("配置: {}", path.display())),
+        Event::SymlinkCreated { link, target } => ui::detail(&format!(
+            "リンク: {} -> {}",
+            link.display(),
+            target.display()
+        )),
+        Event::WrapperWritten { name, app_id } => {
+            ui::detail(&format!("ラッパー: {} -> {}", name, app_id))
+        }
+        Event::FileRemoved { path } => ui::detail(&format!("削除: {}", path.display())),
+        Event::CommandExecuted { command, success } => ui::detail(&format!(
+            "実行: {}{}",
+            command,
+            if *success { "" } else { " (失敗)" }
+        )),
+        Event::RootCommandRequired { command } => ui::root_command(command),
+        Event::Completed { message } => ui::success(message),
+        Event::Warning { message } => ui::warn(message),
+        // 最終結果は各コマンドが表示する
+        _ => {}
+    }
+}
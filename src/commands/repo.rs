@@ -0,0 +1,43 @@
+//! repo コマンド実装
+//!
+//! アプリDBリポジトリ（タップ）の登録・削除・一覧表示を行います。
+
+use crate::ui;
+use anyhow::Result;
+
+/// リポジトリを登録して同期
+pub fn add(name: &str, url: &str, priority: Option<i32>, public_key: Option<&str>) -> Result<()> {
+    ui::info(&format!("リポジトリ '{}' を追加中: {}", name, url));
+
+    let summary = super::client().add_repository(name, url, priority, public_key)?;
+    super::sync::print_summary(&summary);
+
+    Ok(())
+}
+
+/// リポジトリの登録を解除
+pub fn remove(name: &str) -> Result<()> {
+    super::client().remove_repository(name)?;
+    ui::success(&format!("リポジトリ '{}' を削除しました", name));
+    Ok(())
+}
+
+/// 登録済みリポジトリを優先度順に表示
+pub fn list() -> Result<()> {
+    let repositories = super::client().repositories()?;
+
+    println!("{:<16} {:>8}  {:<4}  URL", "名前", "優先度", "署名");
+    for repository in repositories {
+        let signed = if repository.public_key.is_some() {
+            "検証"
+        } else {
+            "-"
+        };
+        println!(
+            "{:<16} {:>8}  {:<4}  {}",
+            repository.name, repository.priority, signed, repository.url
+        );
+    }
+
+    Ok(())
+}
@@ -1,32 +1,24 @@
 //! search コマンド実装
 //!
 //! アプリDBを検索してアプリ情報を表示します。
+//! 各アプリにはインストール状態（インストール済み・更新あり・未インストール）を併記します。
 
-use crate::db;
-use crate::utils::ui;
+use crate::client::{InstallStatus, SearchOptions};
+use crate::db::app::{AppConfig, SourceType};
+use crate::ui;
 use anyhow::Result;
-use std::path::PathBuf;
 
 /// searchコマンドのエントリーポイント
-pub fn run(query: &str) -> Result<()> {
+pub fn run(query: &str, options: &SearchOptions) -> Result<()> {
     ui::info(&format!("「{}」を検索中...\n", query));
 
-    let db_dir = get_db_dir();
-    let apps = db::app::load_all(&db_dir)?;
-
-    if apps.is_empty() {
+    let client = super::client();
+    if client.apps()?.is_empty() {
         ui::warn("アプリDBが空です");
         return Ok(());
     }
 
-    let query_lower = query.to_lowercase();
-    let results: Vec<_> = apps
-        .iter()
-        .filter(|app| {
-            app.app.name.to_lowercase().contains(&query_lower)
-                || app.app.description.to_lowercase().contains(&query_lower)
-        })
-        .collect();
+    let results = client.search(query, options)?;
 
     if results.is_empty() {
         ui::warn(&format!("「{}」に一致するアプリが見つかりません", query));
@@ -35,9 +27,13 @@ pub fn run(query: &str) -> Result<()> {
 
     ui::success(&format!("{} 件のアプリが見つかりました:\n", results.len()));
 
-    for app in results {
+    for app in &results {
         println!("  {} - {}", app.app.name, app.app.description);
-        println!("    タイプ: {:?}", app.source.source_type);
+        println!("    タイプ: {}", source_types(app));
+        println!("    状態: {}", status_label(&client.status(app)?));
+        if let Some(ref repo) = app.repo {
+            println!("    リポジトリ: {}", repo);
+        }
         if let Some(ref homepage) = app.app.homepage {
             println!("    ホームページ: {}", homepage);
         }
@@ -47,34 +43,53 @@ pub fn run(query: &str) -> Result<()> {
     Ok(())
 }
 
-/// アプリDBディレクトリを取得
-fn get_db_dir() -> PathBuf {
-    // 1. 環境変数 AN_DB_DIR
-    // 2. ~/.config/an/apps/
-    // 3. カレントディレクトリの apps/
-    if let Ok(dir) = std::env::var("AN_DB_DIR") {
-        return PathBuf::from(dir);
+/// インストールタイプの表示（代替ソースがある場合は併記）
+fn source_types(app: &AppConfig) -> String {
+    if app.alternatives.is_empty() {
+        return format!("{:?}", app.source.source_type);
     }
+    let alternatives: Vec<String> = app
+        .alternatives
+        .iter()
+        .map(|s| format!("{:?}", s.source_type))
+        .collect();
+    format!(
+        "{:?}（代替: {}）",
+        app.source.source_type,
+        alternatives.join(", ")
+    )
+}
 
-    if let Some(config_dir) = dirs::config_dir() {
-        let an_db = config_dir.join("an").join("apps");
-        if an_db.exists() {
-            return an_db;
-        }
+/// インストール状態の表示（インストール元の形式とバージョン付き）
+pub fn status_label(status: &InstallStatus) -> String {
+    match status {
+        InstallStatus::NotInstalled => "未インストール".to_string(),
+        InstallStatus::Installed(package) => match package.version {
+            Some(ref version) => format!("インストール済み（{} {}）", package.source_type, version),
+            None => format!("インストール済み（{}）", package.source_type),
+        },
+        InstallStatus::Outdated { package, available } => format!(
+            "更新あり（{} {} → {}）",
+            package.source_type,
+            package.version.as_deref().unwrap_or("?"),
+            available
+        ),
     }
-
-    PathBuf::from("apps")
 }
 
 /// アプリ詳細を表示
 pub fn show_details(name: &str) -> Result<()> {
-    let app = db::find_by_name(name)?;
+    let client = super::client();
+    let app = client.app(name)?;
 
     match app {
         Some(config) => {
             println!("=== {} ===", config.app.name);
             println!("説明: {}", config.app.description);
-            println!("タイプ: {:?}", config.source.source_type);
+            println!("タイプ: {}", source_types(&config));
+            if let Some(ref repo) = config.repo {
+                println!("リポジトリ: {}", repo);
+            }
 
             // Flatpakの場合はflatpak_idを表示、それ以外はURL
             if let Some(ref flatpak_id) = config.source.flatpak_id {
@@ -86,6 +101,19 @@ pub fn show_details(name: &str) -> Result<()> {
 
             println!("アーキテクチャ: {}", config.source.architecture.join(", "));
 
+            for source in &config.alternatives {
+                let target = match source.flatpak_id {
+                    Some(ref flatpak_id) => flatpak_id,
+                    None => &source.url,
+                };
+                println!(
+                    "代替ソース: {:?} {} ({})",
+                    source.source_type,
+                    target,
+                    source.architecture.join(", ")
+                );
+            }
+
             if let Some(ref homepage) = config.app.homepage {
                 println!("ホームページ: {}", homepage);
             }
@@ -97,6 +125,26 @@ pub fn show_details(name: &str) -> Result<()> {
                 if let Some(ref categories) = metadata.categories {
                     println!("カテゴリ: {}", categories.join(", "));
                 }
+                if !metadata.keywords.is_empty() {
+                    println!("キーワード: {}", metadata.keywords.join(", "));
+                }
+            }
+
+            println!();
+            println!("状態: {}", status_label(&client.status(&config)?));
+            if let Some(details) = client.details(&config.app.name)? {
+                if let Some(ref location) = details.package.location {
+                    println!("場所: {}", location);
+                }
+                for file in &details.files {
+                    println!("インストール先: {}", file.display());
+                }
+                for wrapper in &details.wrappers {
+                    println!("コマンド: {}", wrapper.display());
+                }
+                if let Some(bytes) = details.disk_usage {
+                    println!("ディスク使用量: {}", ui::format_size(bytes));
+                }
             }
 
             Ok(())
@@ -108,55 +156,38 @@ pub fn show_details(name: &str) -> Result<()> {
     }
 }
 
-/// DB内の全アプリ一覧
-pub fn list_all() -> Result<()> {
-    let db_dir = get_db_dir();
-    let apps = db::app::load_all(&db_dir)?;
+/// DB内の全アプリ一覧（絞り込み条件を満たすもののみ）
+pub fn list_all(options: &SearchOptions) -> Result<()> {
+    let apps = super::client().search("", options)?;
 
     if apps.is_empty() {
-        ui::warn("アプリDBが空です");
+        if options.has_filters() {
+            ui::warn("条件に一致するアプリが見つかりません");
+        } else {
+            ui::warn("アプリDBが空です");
+        }
         return Ok(());
     }
 
     ui::info(&format!("利用可能なアプリ ({} 件):\n", apps.len()));
 
-    // タイプ別に分類
-    let mut appimages: Vec<_> = apps
-        .iter()
-        .filter(|a| a.source.source_type == db::app::SourceType::AppImage)
-        .collect();
-    let mut debs: Vec<_> = apps
-        .iter()
-        .filter(|a| a.source.source_type == db::app::SourceType::Deb)
-        .collect();
-    let mut flatpaks: Vec<_> = apps
-        .iter()
-        .filter(|a| a.source.source_type == db::app::SourceType::Flatpak)
-        .collect();
-
-    appimages.sort_by(|a, b| a.app.name.cmp(&b.app.name));
-    debs.sort_by(|a, b| a.app.name.cmp(&b.app.name));
-    flatpaks.sort_by(|a, b| a.app.name.cmp(&b.app.name));
-
-    if !appimages.is_empty() {
-        println!("=== AppImage ({}) ===", appimages.len());
-        for app in appimages {
-            println!("  {} - {}", app.app.name, app.app.description);
+    // タイプ別に分類（各タイプ内は名前順）
+    for source_type in [
+        SourceType::AppImage,
+        SourceType::Deb,
+        SourceType::Flatpak,
+        SourceType::Script,
+    ] {
+        let group: Vec<_> = apps
+            .iter()
+            .filter(|a| a.source.source_type == source_type)
+            .collect();
+        if group.is_empty() {
+            continue;
         }
-        println!();
-    }
 
-    if !debs.is_empty() {
-        println!("=== Deb ({}) ===", debs.len());
-        for app in debs {
-            println!("  {} - {}", app.app.name, app.app.description);
-        }
-        println!();
-    }
-
-    if !flatpaks.is_empty() {
-        println!("=== Flatpak ({}) ===", flatpaks.len());
-        for app in flatpaks {
+        println!("=== {} ({}) ===", source_type, group.len());
+        for app in group {
             println!("  {} - {}", app.app.name, app.app.description);
         }
         println!();
@@ -165,14 +196,20 @@ pub fn list_all() -> Result<()> {
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// アプリDBのカテゴリ一覧
+pub fn list_categories() -> Result<()> {
+    let categories = super::client().categories()?;
 
-    #[test]
-    fn test_get_db_dir() {
-        let dir = get_db_dir();
-        // デフォルトは "apps"
-        assert!(dir.to_string_lossy().contains("apps"));
+    if categories.is_empty() {
+        ui::warn("カテゴリが設定されたアプリがありません");
+        return Ok(());
+    }
+
+    ui::info(&format!("カテゴリ ({} 件):\n", categories.len()));
+    let width = categories.iter().map(|(c, _)| c.len()).max().unwrap_or(0);
+    for (category, count) in categories {
+        println!("  {:<width$}  {}", category, count, width = width);
     }
+
+    Ok(())
 }
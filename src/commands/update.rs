@@ -4,13 +4,14 @@
 
 use crate::commands::sync;
 use crate::errors::AnError;
-use crate::utils::ui;
+use crate::handlers::flatpak;
+use crate::ui;
+use crate::utils::temp;
 use anyhow::Result;
 use semver::Version;
 use std::fs;
 use std::io::Write;
 use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
 
 /// 現在のバージョン
 const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -90,7 +91,7 @@ fn download_and_install(release: &GitHubRelease) -> Result<()> {
     ui::info(&format!("ダウンロード中: {}", asset.name));
 
     // 一時ファイルにダウンロード
-    let temp_path = std::env::temp_dir().join("an-update");
+    let temp_path = temp::dir()?.join("an-update");
 
     let client = reqwest::blocking::Client::builder()
         .user_agent("AN-Package-Manager")
@@ -146,15 +147,6 @@ fn download_and_install(release: &GitHubRelease) -> Result<()> {
     Ok(())
 }
 
-/// インストール先ディレクトリを取得
-#[allow(dead_code)]
-fn install_dir() -> PathBuf {
-    dirs::home_dir()
-        .expect("ホームディレクトリが見つかりません")
-        .join(".local")
-        .join("bin")
-}
-
 /// updateコマンドのエントリーポイント
 pub fn run() -> Result<()> {
     ui::info("アップデートを確認中...\n");
@@ -167,7 +159,7 @@ pub fn run() -> Result<()> {
             ui::info("アプリDBのみ更新します...\n");
 
             // DB更新のみ実行
-            sync::run()?;
+            sync::run(None, false)?;
             return Ok(());
         }
     };
@@ -196,11 +188,18 @@ pub fn run() -> Result<()> {
         ui::success(&format!("AN: 最新版です (v{})", CURRENT_VERSION));
     }
 
+    // 新しいテンプレートでFlatpakラッパーを再生成
+    match flatpak::upgrade_wrappers() {
+        Ok(0) => {}
+        Ok(n) => ui::info(&format!("Flatpakラッパーを再生成: {} 件", n)),
+        Err(e) => ui::warn(&format!("Flatpakラッパーの再生成に失敗: {}", e)),
+    }
+
     println!();
     ui::info("アプリDB:");
 
     // DB更新 (syncコマンドを呼び出し)
-    sync::run()?;
+    sync::run(None, false)?;
 
     Ok(())
 }
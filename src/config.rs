@@ -0,0 +1,662 @@
+//! ユーザー設定モジュール
+//!
+//! `~/.config/an/config.toml` を起動時に一度だけ読み込み、
+//! インストール先やアプリDBの場所などの設定を提供します。
+//!
+//! 優先順位: デフォルト < 設定ファイル < 環境変数 < CLIオプション
+
+use crate::db::app::SourceType;
+use crate::db::signature;
+use crate::errors::AnError;
+use crate::events;
+use crate::utils::{privilege, xdg};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// デフォルトの同期元リポジトリ
+///
+/// `.github/workflows/db.yml` が公開する tarball（`releases/download/db/apps.tar.gz`）は、
+/// 公開されるまで `sync.repository` で指定した場合のみ使用します。
+pub const DEFAULT_SYNC_REPOSITORY: &str = "https://github.com/clearclown/AN.git";
+
+/// デフォルトのFlatpakリモート
+pub const DEFAULT_FLATPAK_REMOTE: &str = "flathub";
+
+/// 複数のアプリをインストールするときの同時ダウンロード数のデフォルト
+pub const DEFAULT_PARALLEL_DOWNLOADS: usize = 4;
+
+/// 設定キーと対応する環境変数
+pub const KEYS: &[(&str, &str)] = &[
+    ("paths.apps_dir", "AN_APPS_DIR"),
+    ("paths.bin_dir", "AN_BIN_DIR"),
+    ("paths.db_dir", "AN_DB_DIR"),
+    ("paths.state_dir", "AN_STATE_DIR"),
+    ("paths.cache_dir", "AN_CACHE_DIR"),
+    ("sync.repository", "AN_SYNC_REPOSITORY"),
+    ("sync.public_key", "AN_SYNC_PUBLIC_KEY"),
+    ("flatpak.remote", "AN_FLATPAK_REMOTE"),
+    ("privilege.method", "AN_PRIVILEGE_METHOD"),
+    ("install.prefer", "AN_INSTALL_PREFER"),
+    (
+        "install.parallel_downloads",
+        "AN_INSTALL_PARALLEL_DOWNLOADS",
+    ),
+];
+
+/// システム全体モードのAppImage格納ディレクトリ
+pub const SYSTEM_APPS_DIR: &str = "/opt/an";
+
+/// システム全体モードのコマンド配置ディレクトリ
+pub const SYSTEM_BIN_DIR: &str = "/usr/local/bin";
+
+/// システム全体モードのデスクトップエントリ配置先
+pub const SYSTEM_DESKTOP_DIR: &str = "/usr/share/applications";
+
+/// システム全体モードの状態ディレクトリ
+pub const SYSTEM_STATE_DIR: &str = "/var/lib/an";
+
+/// 起動時に読み込まれた設定
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// インストール範囲
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Scope {
+    /// ユーザー単位（ホームディレクトリ配下）
+    #[default]
+    User,
+    /// システム全体（/opt, /usr/local, /var/lib 配下、特権が必要）
+    System,
+}
+
+/// 設定のルート構造体
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    pub paths: PathsConfig,
+    pub sync: SyncConfig,
+    pub flatpak: FlatpakConfig,
+    pub privilege: PrivilegeConfig,
+    pub install: InstallConfig,
+    /// インストール範囲（CLIの `--system` で切り替え、保存はしない）
+    #[serde(skip)]
+    pub scope: Scope,
+}
+
+/// ディレクトリ設定
+///
+/// 未設定（`None`）の場合はデフォルトの場所を使用します。
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PathsConfig {
+    /// AppImage格納ディレクトリ（デフォルト: ~/Applications）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apps_dir: Option<PathBuf>,
+    /// コマンド配置ディレクトリ（デフォルト: $XDG_BIN_HOME または ~/.local/bin）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bin_dir: Option<PathBuf>,
+    /// アプリDBディレクトリ（デフォルト: $XDG_CONFIG_HOME/an/apps）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub db_dir: Option<PathBuf>,
+    /// 状態ディレクトリ（デフォルト: $XDG_STATE_HOME/an）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_dir: Option<PathBuf>,
+    /// キャッシュディレクトリ（デフォルト: $XDG_CACHE_HOME/an）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// 同期設定
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// アプリDBの同期元リポジトリ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    /// 公式DBの署名を検証する公開鍵
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+/// Flatpak設定
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FlatpakConfig {
+    /// インストールに使用するリモート
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+}
+
+/// 特権昇格設定
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PrivilegeConfig {
+    /// 昇格方法（auto | sudo | doas | run0 | pkexec | print）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+}
+
+/// インストール設定
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct InstallConfig {
+    /// 複数のソースがあるアプリで優先する形式の順（例: `["flatpak", "appimage", "deb"]`）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prefer: Vec<SourceType>,
+    /// 複数のアプリをインストールするときの同時ダウンロード数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_downloads: Option<usize>,
+}
+
+/// 設定ファイルのパスを取得
+///
+/// 環境変数 `AN_CONFIG` が設定されていればそれを使用します。
+pub fn config_path() -> PathBuf {
+    if let Ok(path) = std::env::var("AN_CONFIG") {
+        return PathBuf::from(path);
+    }
+
+    xdg::config_home().join("an").join("config.toml")
+}
+
+impl Config {
+    /// 設定ファイルを読み込む（存在しない場合はデフォルト）
+    pub fn load_file(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// 設定ファイルに書き込む
+    pub fn save_file(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let content = toml::to_string_pretty(self).map_err(|e| AnError::ValidationError {
+            message: e.to_string(),
+        })?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// 設定ファイル・環境変数・CLIオプションを合成して読み込む
+    pub fn load(path: Option<&Path>, overrides: &[String]) -> Result<Self> {
+        let path = path.map(Path::to_path_buf).unwrap_or_else(config_path);
+        let mut config = Self::load_file(&path)?;
+
+        // 環境変数による上書き
+        for (key, var) in KEYS {
+            if let Ok(value) = std::env::var(var) {
+                config.set(key, &value)?;
+            }
+        }
+
+        // CLIオプション（KEY=VALUE）による上書き
+        for entry in overrides {
+            let (key, value) = entry
+                .split_once('=')
+                .ok_or_else(|| AnError::ValidationError {
+                    message: format!("KEY=VALUE 形式で指定してください: {}", entry),
+                })?;
+            config.set(key.trim(), value.trim())?;
+        }
+
+        Ok(config)
+    }
+
+    /// キーに対応する設定値を取得（未設定の場合はデフォルト値）
+    pub fn get(&self, key: &str) -> Result<String> {
+        let value = match key {
+            "paths.apps_dir" => self.apps_dir().display().to_string(),
+            "paths.bin_dir" => self.bin_dir().display().to_string(),
+            "paths.db_dir" => self.db_dir().display().to_string(),
+            "paths.state_dir" => self.state_dir().display().to_string(),
+            "paths.cache_dir" => self.cache_dir().display().to_string(),
+            "sync.repository" => self.sync_repository().to_string(),
+            "sync.public_key" => self.sync_public_key().unwrap_or_default().to_string(),
+            "flatpak.remote" => self.flatpak_remote().to_string(),
+            "privilege.method" => self.privilege_method().to_string(),
+            "install.prefer" => self
+                .install_preference()
+                .iter()
+                .map(|t| t.to_string().to_lowercase())
+                .collect::<Vec<_>>()
+                .join(","),
+            "install.parallel_downloads" => self.parallel_downloads().to_string(),
+            _ => {
+                return Err(AnError::UnknownConfigKey {
+                    key: key.to_string(),
+                }
+                .into())
+            }
+        };
+        Ok(value)
+    }
+
+    /// キーに値を設定（空文字列の場合は未設定に戻す）
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = (!value.is_empty()).then(|| value.to_string());
+        match key {
+            "paths.apps_dir" => self.paths.apps_dir = value.map(PathBuf::from),
+            "paths.bin_dir" => self.paths.bin_dir = value.map(PathBuf::from),
+            "paths.db_dir" => self.paths.db_dir = value.map(PathBuf::from),
+            "paths.state_dir" => self.paths.state_dir = value.map(PathBuf::from),
+            "paths.cache_dir" => self.paths.cache_dir = value.map(PathBuf::from),
+            "sync.repository" => self.sync.repository = value,
+            "sync.public_key" => {
+                if let Some(ref key) = value {
+                    signature::validate_public_key(key)?;
+                }
+                self.sync.public_key = value;
+            }
+            "flatpak.remote" => self.flatpak.remote = value,
+            "privilege.method" => {
+                if let Some(ref method) = value {
+                    if !privilege::METHOD_NAMES.contains(&method.as_str()) {
+                        return Err(AnError::ValidationError {
+                            message: format!(
+                                "privilege.method は {} のいずれかを指定してください",
+                                privilege::METHOD_NAMES.join(" | ")
+                            ),
+                        }
+                        .into());
+                    }
+                }
+                self.privilege.method = value;
+            }
+            "install.prefer" => {
+                // カンマ区切り（例: flatpak,appimage,deb）
+                self.install.prefer = value
+                    .iter()
+                    .flat_map(|v| v.split(','))
+                    .map(|t| t.trim().parse::<SourceType>())
+                    .collect::<std::result::Result<_, _>>()?;
+            }
+            "install.parallel_downloads" => {
+                self.install.parallel_downloads = match value {
+                    Some(ref v) => match v.trim().parse::<usize>() {
+                        Ok(n) if n > 0 => Some(n),
+                        _ => {
+                            return Err(AnError::ValidationError {
+                                message:
+                                    "install.parallel_downloads は1以上の整数を指定してください"
+                                        .to_string(),
+                            }
+                            .into())
+                        }
+                    },
+                    None => None,
+                };
+            }
+            _ => {
+                return Err(AnError::UnknownConfigKey {
+                    key: key.to_string(),
+                }
+                .into())
+            }
+        }
+        Ok(())
+    }
+
+    /// システム全体モードか
+    pub fn is_system(&self) -> bool {
+        self.scope == Scope::System
+    }
+
+    /// AppImage格納ディレクトリ
+    pub fn apps_dir(&self) -> PathBuf {
+        if self.is_system() {
+            return PathBuf::from(SYSTEM_APPS_DIR);
+        }
+
+        self.paths
+            .apps_dir
+            .clone()
+            .unwrap_or_else(|| xdg::home_dir().join("Applications"))
+    }
+
+    /// コマンド配置ディレクトリ
+    pub fn bin_dir(&self) -> PathBuf {
+        if self.is_system() {
+            return PathBuf::from(SYSTEM_BIN_DIR);
+        }
+
+        self.paths.bin_dir.clone().unwrap_or_else(xdg::bin_home)
+    }
+
+    /// アプリDBディレクトリ
+    pub fn db_dir(&self) -> PathBuf {
+        self.paths
+            .db_dir
+            .clone()
+            .unwrap_or_else(|| xdg::config_home().join("an").join("apps"))
+    }
+
+    /// 状態ディレクトリ（インストール記録など）
+    pub fn state_dir(&self) -> PathBuf {
+        if self.is_system() {
+            return PathBuf::from(SYSTEM_STATE_DIR);
+        }
+
+        self.paths
+            .state_dir
+            .clone()
+            .unwrap_or_else(|| xdg::state_home().join("an"))
+    }
+
+    /// キャッシュディレクトリ（ダウンロードなど）
+    pub fn cache_dir(&self) -> PathBuf {
+        self.paths
+            .cache_dir
+            .clone()
+            .unwrap_or_else(|| xdg::cache_home().join("an"))
+    }
+
+    /// デスクトップエントリ配置先
+    pub fn desktop_dir(&self) -> PathBuf {
+        if self.is_system() {
+            return PathBuf::from(SYSTEM_DESKTOP_DIR);
+        }
+
+        xdg::data_home().join("applications")
+    }
+
+    /// アプリDBの同期元リポジトリ
+    pub fn sync_repository(&self) -> &str {
+        self.sync
+            .repository
+            .as_deref()
+            .unwrap_or(DEFAULT_SYNC_REPOSITORY)
+    }
+
+    /// 公式DBの署名を検証する公開鍵（未設定の場合は署名を検証しない）
+    pub fn sync_public_key(&self) -> Option<&str> {
+        self.sync.public_key.as_deref()
+    }
+
+    /// Flatpakリモート名
+    pub fn flatpak_remote(&self) -> &str {
+        self.flatpak
+            .remote
+            .as_deref()
+            .unwrap_or(DEFAULT_FLATPAK_REMOTE)
+    }
+
+    /// 特権昇格方法
+    pub fn privilege_method(&self) -> &str {
+        self.privilege.method.as_deref().unwrap_or("auto")
+    }
+
+    /// 複数のソースがあるアプリで優先する形式の順（未設定の場合は空で、アプリDBの定義順）
+    pub fn install_preference(&self) -> &[SourceType] {
+        &self.install.prefer
+    }
+
+    /// 複数のアプリをインストールするときの同時ダウンロード数
+    pub fn parallel_downloads(&self) -> usize {
+        self.install
+            .parallel_downloads
+            .unwrap_or(DEFAULT_PARALLEL_DOWNLOADS)
+    }
+}
+
+/// 読み込んだ設定を登録（起動時に一度だけ呼ぶ）
+///
+/// 設定はプロセス全体で共有されるため、既に異なる設定を使用している場合は E907 になります。
+pub fn init(config: Config) -> Result<()> {
+    init_in(&CONFIG, config)
+}
+
+fn init_in(cell: &OnceLock<Config>, config: Config) -> Result<()> {
+    if *cell.get_or_init(|| config.clone()) != config {
+        return Err(AnError::ConfigAlreadySet.into());
+    }
+    Ok(())
+}
+
+/// 現在の設定を取得
+///
+/// `init` が呼ばれていない場合（ライブラリ利用時など）は
+/// 設定ファイルと環境変数から読み込みます。
+/// 読み込めない場合は警告を通知し、デフォルトの設定を使用します。
+pub fn get() -> &'static Config {
+    #[cfg(test)]
+    if let Some(config) = OVERRIDE.with(std::cell::Cell::get) {
+        return config;
+    }
+    if let Some(config) = CONFIG.get() {
+        return config;
+    }
+    // 通知先から設定を参照できるよう、初期化の外で読み込む
+    let config = load_or_default(None);
+    CONFIG.get_or_init(|| config)
+}
+
+/// 設定を読み込む（読み込めない場合は警告を通知してデフォルトの設定）
+fn load_or_default(path: Option<&Path>) -> Config {
+    Config::load(path, &[]).unwrap_or_else(|e| {
+        events::warn(format!(
+            "設定ファイルを読み込めないため、デフォルトの設定を使用します: {:#}",
+            e
+        ));
+        Config::default()
+    })
+}
+
+#[cfg(test)]
+thread_local! {
+    /// テスト中に差し替えた設定（スレッドごと）
+    static OVERRIDE: std::cell::Cell<Option<&'static Config>> = const { std::cell::Cell::new(None) };
+}
+
+/// 設定を差し替えて処理を実行（テスト用）
+///
+/// システム全体モード・表示モードなど、起動時にしか決まらない設定を扱う処理のテストに使用します。
+/// 差し替えは現在のスレッドのみに作用し、`f` の終了後に元に戻ります。
+#[cfg(test)]
+pub(crate) fn with_config<T>(config: Config, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<&'static Config>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            OVERRIDE.with(|o| o.set(self.0));
+        }
+    }
+
+    // テストごとに少量のため解放しない
+    let config: &'static Config = Box::leak(Box::new(config));
+    let _restore = Restore(OVERRIDE.with(|o| o.replace(Some(config))));
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_defaults() {
+        let config = Config::default();
+        assert!(config.apps_dir().ends_with("Applications"));
+        assert!(config.bin_dir().ends_with(".local/bin"));
+        assert!(config.db_dir().ends_with("an/apps"));
+        assert!(config.state_dir().ends_with("an"));
+        assert!(config.cache_dir().ends_with("an"));
+        assert!(config.desktop_dir().ends_with("applications"));
+        assert_eq!(config.sync_repository(), DEFAULT_SYNC_REPOSITORY);
+        assert_eq!(config.flatpak_remote(), "flathub");
+        assert_eq!(config.sync_public_key(), None);
+    }
+
+    #[test]
+    fn test_sync_public_key() {
+        let key = signature::test_key::public_key(1);
+        let mut config = Config::default();
+        config.set("sync.public_key", &key).unwrap();
+        assert_eq!(config.sync_public_key(), Some(key.as_str()));
+
+        // 不正な公開鍵は設定できない
+        assert!(config.set("sync.public_key", "not-a-key").is_err());
+    }
+
+    #[test]
+    fn test_load_or_default_warns_on_broken_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[paths\napps_dir = 1").unwrap();
+
+        let warnings = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let observed = warnings.clone();
+        let config = events::with_observer(
+            std::rc::Rc::new(move |event: &events::Event| {
+                if let events::Event::Warning { message } = event {
+                    observed.borrow_mut().push(message.clone());
+                }
+            }),
+            || load_or_default(Some(&path)),
+        );
+
+        assert_eq!(config.sync_repository(), DEFAULT_SYNC_REPOSITORY);
+        let warnings = warnings.borrow();
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].contains("設定ファイルを読み込めない"),
+            "{}",
+            warnings[0]
+        );
+    }
+
+    #[test]
+    fn test_init_rejects_different_config() {
+        let cell = OnceLock::new();
+        init_in(&cell, Config::default()).unwrap();
+        // 同じ設定は再度登録できる
+        init_in(&cell, Config::default()).unwrap();
+
+        let mut other = Config::default();
+        other.set("flatpak.remote", "other").unwrap();
+        let err = init_in(&cell, other).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AnError>(),
+            Some(AnError::ConfigAlreadySet)
+        ));
+        assert_eq!(cell.get().unwrap().flatpak_remote(), "flathub");
+    }
+
+    #[test]
+    fn test_system_scope_paths() {
+        let mut config = Config::default();
+        config.set("paths.apps_dir", "/home/user/Apps").unwrap();
+        config.scope = Scope::System;
+        assert_eq!(config.apps_dir(), PathBuf::from("/opt/an"));
+        assert_eq!(config.bin_dir(), PathBuf::from("/usr/local/bin"));
+        assert_eq!(
+            config.desktop_dir(),
+            PathBuf::from("/usr/share/applications")
+        );
+        assert_eq!(config.state_dir(), PathBuf::from("/var/lib/an"));
+    }
+
+    #[test]
+    fn test_set_and_get() {
+        let mut config = Config::default();
+        config.set("paths.apps_dir", "/opt/apps").unwrap();
+        config.set("flatpak.remote", "flathub-beta").unwrap();
+        assert_eq!(config.get("paths.apps_dir").unwrap(), "/opt/apps");
+        assert_eq!(config.get("flatpak.remote").unwrap(), "flathub-beta");
+
+        // 空文字列でデフォルトに戻る
+        config.set("flatpak.remote", "").unwrap();
+        assert_eq!(config.get("flatpak.remote").unwrap(), "flathub");
+    }
+
+    #[test]
+    fn test_privilege_method_validation() {
+        let mut config = Config::default();
+        assert_eq!(config.privilege_method(), "auto");
+        config.set("privilege.method", "doas").unwrap();
+        assert_eq!(config.privilege_method(), "doas");
+        assert!(config.set("privilege.method", "su").is_err());
+    }
+
+    #[test]
+    fn test_install_prefer() {
+        let mut config = Config::default();
+        assert_eq!(config.get("install.prefer").unwrap(), "");
+        config
+            .set("install.prefer", "flatpak, appimage,deb")
+            .unwrap();
+        assert_eq!(
+            config.install_preference(),
+            [SourceType::Flatpak, SourceType::AppImage, SourceType::Deb]
+        );
+        assert_eq!(
+            config.get("install.prefer").unwrap(),
+            "flatpak,appimage,deb"
+        );
+        assert!(config.set("install.prefer", "flatpak,snap").is_err());
+
+        let content = toml::to_string(&config).unwrap();
+        assert!(content.contains("prefer = [\"flatpak\", \"appimage\", \"deb\"]"));
+        config.set("install.prefer", "").unwrap();
+        assert!(config.install_preference().is_empty());
+    }
+
+    #[test]
+    fn test_parallel_downloads() {
+        let mut config = Config::default();
+        assert_eq!(config.parallel_downloads(), DEFAULT_PARALLEL_DOWNLOADS);
+        config.set("install.parallel_downloads", "8").unwrap();
+        assert_eq!(config.get("install.parallel_downloads").unwrap(), "8");
+        assert!(config.set("install.parallel_downloads", "0").is_err());
+        assert!(config.set("install.parallel_downloads", "many").is_err());
+        config.set("install.parallel_downloads", "").unwrap();
+        assert_eq!(config.parallel_downloads(), DEFAULT_PARALLEL_DOWNLOADS);
+    }
+
+    #[test]
+    fn test_unknown_key() {
+        let mut config = Config::default();
+        assert!(config.get("paths.unknown").is_err());
+        assert!(config.set("unknown", "value").is_err());
+    }
+
+    #[test]
+    fn test_save_and_load_file() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("an").join("config.toml");
+
+        let mut config = Config::default();
+        config
+            .set("sync.repository", "https://example.com/db.git")
+            .unwrap();
+        config.save_file(&path).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("[sync]"));
+        assert!(!content.contains("apps_dir"));
+        assert_eq!(Config::load_file(&path).unwrap(), config);
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let config = Config::load_file(Path::new("/nonexistent/config.toml")).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn test_load_with_cli_overrides() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("config.toml");
+        std::fs::write(&path, "[flatpak]\nremote = \"from-file\"\n").unwrap();
+
+        let config = Config::load(Some(&path), &["flatpak.remote=from-cli".to_string()]).unwrap();
+        assert_eq!(config.flatpak_remote(), "from-cli");
+
+        assert!(Config::load(Some(&path), &["no-equals".to_string()]).is_err());
+    }
+}
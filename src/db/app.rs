@@ -2,18 +2,87 @@
 //!
 //! TOMLファイルからアプリ設定を読み込み、検索する機能を提供します。
 
+use crate::config;
+use crate::db::index;
 use crate::errors::AnError;
+use crate::events;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::Path;
 
 /// アプリ設定のルート構造体
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AppConfig {
     pub app: AppInfo,
+    /// 既定のソース
     pub source: SourceInfo,
+    /// 追加のソース（`[[alternatives]]`、同じアプリの別形式での配布）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<SourceInfo>,
     #[serde(default)]
     pub metadata: Option<Metadata>,
+    /// 読み込み元のリポジトリ名（[`all`] / [`find_by_name`] で設定）
+    #[serde(skip)]
+    pub repo: Option<String>,
+}
+
+impl AppConfig {
+    /// 既定のソースと追加のソース（定義順）
+    pub fn sources(&self) -> impl Iterator<Item = &SourceInfo> {
+        std::iter::once(&self.source).chain(&self.alternatives)
+    }
+
+    /// 指定したソースを既定のソースにしたコピー（追加のソースは含まない）
+    pub fn with_source(&self, source: &SourceInfo) -> AppConfig {
+        AppConfig {
+            source: source.clone(),
+            alternatives: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// インストールを試すソース（試す順）
+    ///
+    /// `via` を指定した場合はその形式のソースのみを対象とし、
+    /// それ以外は `prefer` の順（含まれない形式は定義順で後ろ）に並べます。
+    /// `arch` に対応しないソースは除きます。
+    pub fn candidates(
+        &self,
+        via: Option<SourceType>,
+        prefer: &[SourceType],
+        arch: &str,
+    ) -> Result<Vec<&SourceInfo>> {
+        let mut sources: Vec<&SourceInfo> = self.sources().collect();
+        if let Some(via) = via {
+            sources.retain(|s| s.source_type == via);
+            if sources.is_empty() {
+                return Err(AnError::NoSuitableSource {
+                    name: self.app.name.clone(),
+                    message: format!("{} のソースがありません", via),
+                }
+                .into());
+            }
+        }
+
+        sources.retain(|s| s.architecture.iter().any(|a| a == arch));
+        if sources.is_empty() {
+            return Err(AnError::NoSuitableSource {
+                name: self.app.name.clone(),
+                message: format!("{} に対応するソースがありません", arch),
+            }
+            .into());
+        }
+
+        // 安定ソートのため、同じ優先度のソースは定義順のまま
+        sources.sort_by_key(|s| {
+            prefer
+                .iter()
+                .position(|t| *t == s.source_type)
+                .unwrap_or(prefer.len())
+        });
+        Ok(sources)
+    }
 }
 
 /// アプリ基本情報
@@ -21,6 +90,9 @@ pub struct AppConfig {
 pub struct AppInfo {
     /// アプリ名（コマンド名）
     pub name: String,
+    /// 別名（`an install` / `an info` で名前の代わりに使用可能）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
     /// 説明
     pub description: String,
     /// 公式サイトURL
@@ -38,12 +110,24 @@ pub struct SourceInfo {
     pub url: String,
     /// Flatpak ID（Flatpak用）
     pub flatpak_id: Option<String>,
+    /// Flatpakブランチ（Flatpak用、ラッパーの `--branch=`）
+    #[serde(default)]
+    pub flatpak_branch: Option<String>,
+    /// 実行コマンド（Flatpak用、ラッパーの `--command=`）
+    #[serde(default)]
+    pub flatpak_command: Option<String>,
+    /// サンドボックスに渡す環境変数（Flatpak用）
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub flatpak_env: BTreeMap<String, String>,
+    /// ファイル引数を転送するか（Flatpak用、デフォルト: true）
+    #[serde(default)]
+    pub flatpak_file_forwarding: Option<bool>,
     /// 対応アーキテクチャ
     pub architecture: Vec<String>,
 }
 
 /// インストールタイプ列挙
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum SourceType {
     AppImage,
@@ -52,29 +136,62 @@ pub enum SourceType {
     Script,
 }
 
+impl std::fmt::Display for SourceType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceType::AppImage => write!(f, "AppImage"),
+            SourceType::Deb => write!(f, "Deb"),
+            SourceType::Flatpak => write!(f, "Flatpak"),
+            SourceType::Script => write!(f, "Script"),
+        }
+    }
+}
+
+impl std::str::FromStr for SourceType {
+    type Err = AnError;
+
+    /// `appimage` / `deb` / `flatpak` / `script`（大文字小文字は区別しない）
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "appimage" => Ok(SourceType::AppImage),
+            "deb" => Ok(SourceType::Deb),
+            "flatpak" => Ok(SourceType::Flatpak),
+            "script" => Ok(SourceType::Script),
+            _ => Err(AnError::ValidationError {
+                message: format!(
+                    "不明なインストールタイプです: {}（appimage / deb / flatpak / script）",
+                    s
+                ),
+            }),
+        }
+    }
+}
+
 /// メタデータ（オプション）
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Metadata {
     pub categories: Option<Vec<String>>,
+    /// 検索用のキーワード（`tags` も可）
+    #[serde(default, alias = "tags", skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
     pub desktop_entry: Option<bool>,
     pub version: Option<String>,
     pub maintainer: Option<String>,
 }
 
 /// アプリDBディレクトリを取得
-fn db_dir() -> std::path::PathBuf {
-    // 1. 環境変数 AN_DB_DIR
+pub fn db_dir() -> std::path::PathBuf {
+    // 1. 設定 paths.db_dir（環境変数 AN_DB_DIR を含む）
     // 2. ~/.config/an/apps/
     // 3. カレントディレクトリの apps/
-    if let Ok(dir) = std::env::var("AN_DB_DIR") {
-        return std::path::PathBuf::from(dir);
+    let config = config::get();
+    if let Some(ref dir) = config.paths.db_dir {
+        return dir.clone();
     }
 
-    if let Some(config_dir) = dirs::config_dir() {
-        let an_db = config_dir.join("an").join("apps");
-        if an_db.exists() {
-            return an_db;
-        }
+    let an_db = config.db_dir();
+    if an_db.exists() {
+        return an_db;
     }
 
     std::path::PathBuf::from("apps")
@@ -103,7 +220,7 @@ pub fn load_all(dir: &Path) -> Result<Vec<AppConfig>> {
             match load(&path) {
                 Ok(config) => apps.push(config),
                 Err(e) => {
-                    eprintln!("Warning: Failed to load {:?}: {}", path, e);
+                    events::warn(format!("{} を読み込めません: {}", path.display(), e));
                 }
             }
         }
@@ -112,23 +229,34 @@ pub fn load_all(dir: &Path) -> Result<Vec<AppConfig>> {
     Ok(apps)
 }
 
-/// アプリ名で検索
-pub fn find_by_name(name: &str) -> Result<Option<AppConfig>> {
-    let dir = db_dir();
+/// 全リポジトリのアプリを読み込む
+///
+/// 同じ名前のアプリが複数のリポジトリにある場合は優先度の高いものだけを返します。
+pub fn all() -> Result<Vec<AppConfig>> {
+    all_in(&db_dir())
+}
 
-    // まず直接ファイル名で検索
-    let direct_path = dir.join(format!("{}.toml", name));
-    if direct_path.exists() {
-        return Ok(Some(load(&direct_path)?));
-    }
+fn all_in(db_dir: &Path) -> Result<Vec<AppConfig>> {
+    index::load(db_dir)?
+        .entries
+        .iter()
+        .map(|entry| entry.load())
+        .collect()
+}
+
+/// アプリ名（またはエイリアス）で検索（優先度の高いリポジトリから順に）
+pub fn find_by_name(name: &str) -> Result<Option<AppConfig>> {
+    find_by_name_in(&db_dir(), name)
+}
 
-    // 全ファイルをスキャンして検索
-    let apps = load_all(&dir)?;
-    Ok(apps.into_iter().find(|app| app.app.name == name))
+fn find_by_name_in(db_dir: &Path, name: &str) -> Result<Option<AppConfig>> {
+    index::load(db_dir)?
+        .find(name)
+        .map(|entry| entry.load())
+        .transpose()
 }
 
 /// AppConfigのバリデーション
-#[allow(dead_code)]
 pub fn validate(config: &AppConfig) -> Result<()> {
     // name は空でない
     if config.app.name.is_empty() {
@@ -146,11 +274,19 @@ pub fn validate(config: &AppConfig) -> Result<()> {
         .into());
     }
 
+    for source in config.sources() {
+        validate_source(source)?;
+    }
+
+    Ok(())
+}
+
+/// ソース1件のバリデーション
+fn validate_source(source: &SourceInfo) -> Result<()> {
     // Flatpak以外はURLが必要
-    if config.source.source_type != SourceType::Flatpak {
-        if config.source.url.is_empty()
-            || (!config.source.url.starts_with("http://")
-                && !config.source.url.starts_with("https://"))
+    if source.source_type != SourceType::Flatpak {
+        if source.url.is_empty()
+            || (!source.url.starts_with("http://") && !source.url.starts_with("https://"))
         {
             return Err(AnError::ValidationError {
                 message: "url must be a valid HTTP(S) URL".to_string(),
@@ -159,7 +295,7 @@ pub fn validate(config: &AppConfig) -> Result<()> {
         }
     } else {
         // Flatpakはflatpak_idが必要
-        if config.source.flatpak_id.is_none() {
+        if source.flatpak_id.is_none() {
             return Err(AnError::ValidationError {
                 message: "flatpak_id is required for Flatpak apps".to_string(),
             }
@@ -168,7 +304,7 @@ pub fn validate(config: &AppConfig) -> Result<()> {
     }
 
     // architecture は1つ以上
-    if config.source.architecture.is_empty() {
+    if source.architecture.is_empty() {
         return Err(AnError::ValidationError {
             message: "architecture must have at least one entry".to_string(),
         }
@@ -184,7 +320,10 @@ pub fn filter_by_architecture(apps: Vec<AppConfig>) -> Vec<AppConfig> {
     let current_arch = std::env::consts::ARCH;
 
     apps.into_iter()
-        .filter(|app| app.source.architecture.iter().any(|a| a == current_arch))
+        .filter(|app| {
+            app.sources()
+                .any(|s| s.architecture.iter().any(|a| a == current_arch))
+        })
         .collect()
 }
 
@@ -208,11 +347,13 @@ pub fn expand_url(url: &str, config: &AppConfig) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::repo;
 
     fn sample_config() -> AppConfig {
         AppConfig {
             app: AppInfo {
                 name: "testapp".to_string(),
+                aliases: Vec::new(),
                 description: "Test Application".to_string(),
                 homepage: Some("https://example.com".to_string()),
             },
@@ -220,14 +361,21 @@ mod tests {
                 source_type: SourceType::AppImage,
                 url: "https://example.com/app-{version}-{arch}.AppImage".to_string(),
                 flatpak_id: None,
+                flatpak_branch: None,
+                flatpak_command: None,
+                flatpak_env: BTreeMap::new(),
+                flatpak_file_forwarding: None,
                 architecture: vec!["x86_64".to_string()],
             },
+            alternatives: Vec::new(),
             metadata: Some(Metadata {
                 categories: Some(vec!["Utility".to_string()]),
+                keywords: Vec::new(),
                 desktop_entry: Some(true),
                 version: Some("1.0.0".to_string()),
                 maintainer: None,
             }),
+            repo: None,
         }
     }
 
@@ -263,6 +411,147 @@ architecture = ["x86_64"]
         assert_eq!(config.source.source_type, SourceType::Deb);
     }
 
+    #[test]
+    fn test_source_type_from_str() {
+        assert_eq!(
+            "flatpak".parse::<SourceType>().unwrap(),
+            SourceType::Flatpak
+        );
+        assert_eq!(
+            "AppImage".parse::<SourceType>().unwrap(),
+            SourceType::AppImage
+        );
+        assert!("snap".parse::<SourceType>().is_err());
+    }
+
+    #[test]
+    fn test_metadata_keywords() {
+        let toml_str = r#"
+[app]
+name = "test"
+description = "Test app"
+
+[source]
+type = "deb"
+url = "https://example.com/app.deb"
+architecture = ["x86_64"]
+
+[metadata]
+tags = ["password", "vault"]
+"#;
+        let config: AppConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.metadata.unwrap().keywords, vec!["password", "vault"]);
+    }
+
+    #[test]
+    fn test_flatpak_wrapper_fields() {
+        let toml_str = r#"
+[app]
+name = "gimp"
+description = "GNU Image Manipulation Program"
+
+[source]
+type = "flatpak"
+flatpak_id = "org.gimp.GIMP"
+flatpak_branch = "beta"
+flatpak_file_forwarding = false
+flatpak_env = { GTK_THEME = "Adwaita:dark" }
+architecture = ["x86_64"]
+"#;
+        let config: AppConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.source.flatpak_branch.as_deref(), Some("beta"));
+        assert!(config.source.flatpak_command.is_none());
+        assert_eq!(config.source.flatpak_file_forwarding, Some(false));
+        assert_eq!(
+            config
+                .source
+                .flatpak_env
+                .get("GTK_THEME")
+                .map(String::as_str),
+            Some("Adwaita:dark")
+        );
+    }
+
+    #[test]
+    fn test_alternatives() {
+        let toml_str = r#"
+[app]
+name = "gimp"
+description = "GNU Image Manipulation Program"
+
+[source]
+type = "appimage"
+url = "https://example.com/GIMP-x86_64.AppImage"
+architecture = ["x86_64"]
+
+[[alternatives]]
+type = "flatpak"
+flatpak_id = "org.gimp.GIMP"
+architecture = ["x86_64", "aarch64"]
+
+[[alternatives]]
+type = "deb"
+url = "https://example.com/gimp.deb"
+architecture = ["x86_64"]
+"#;
+        let config: AppConfig = toml::from_str(toml_str).unwrap();
+        assert!(validate(&config).is_ok());
+        let types = |sources: Vec<&SourceInfo>| -> Vec<SourceType> {
+            sources.iter().map(|s| s.source_type).collect()
+        };
+
+        // 優先順の指定がなければ定義順
+        assert_eq!(
+            types(config.candidates(None, &[], "x86_64").unwrap()),
+            [SourceType::AppImage, SourceType::Flatpak, SourceType::Deb]
+        );
+        // 優先順に含まれない形式は後ろ
+        assert_eq!(
+            types(
+                config
+                    .candidates(None, &[SourceType::Deb, SourceType::Flatpak], "x86_64")
+                    .unwrap()
+            ),
+            [SourceType::Deb, SourceType::Flatpak, SourceType::AppImage]
+        );
+        // アーキテクチャで絞り込む
+        assert_eq!(
+            types(config.candidates(None, &[], "aarch64").unwrap()),
+            [SourceType::Flatpak]
+        );
+        // viaで形式を指定
+        assert_eq!(
+            types(
+                config
+                    .candidates(Some(SourceType::Deb), &[SourceType::Flatpak], "x86_64")
+                    .unwrap()
+            ),
+            [SourceType::Deb]
+        );
+
+        let err = config
+            .candidates(Some(SourceType::Script), &[], "x86_64")
+            .unwrap_err();
+        assert!(err.to_string().starts_with("E009"), "{}", err);
+        let err = config
+            .candidates(Some(SourceType::Deb), &[], "aarch64")
+            .unwrap_err();
+        assert!(err.to_string().contains("aarch64"), "{}", err);
+
+        let flatpak = config.with_source(&config.alternatives[0]);
+        assert_eq!(flatpak.source.source_type, SourceType::Flatpak);
+        assert!(flatpak.alternatives.is_empty());
+    }
+
+    #[test]
+    fn test_validate_alternatives() {
+        let mut config = sample_config();
+        let mut alternative = config.source.clone();
+        alternative.url = "not-a-url".to_string();
+        config.alternatives.push(alternative);
+        assert!(validate(&config).is_err());
+    }
+
     #[test]
     fn test_validate_valid_config() {
         let config = sample_config();
@@ -297,6 +586,49 @@ architecture = ["x86_64"]
         assert!(validate(&config).is_err());
     }
 
+    #[test]
+    fn test_db_dir() {
+        let dir = db_dir();
+        // デフォルトは "apps"
+        assert!(dir.to_string_lossy().contains("apps"));
+    }
+
+    fn write_app(dir: &Path, name: &str, description: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        let content = format!(
+            "[app]\nname = \"{}\"\ndescription = \"{}\"\n\n[source]\ntype = \"flatpak\"\nflatpak_id = \"org.example.App\"\narchitecture = [\"x86_64\"]\n",
+            name, description
+        );
+        std::fs::write(dir.join(format!("{}.toml", name)), content).unwrap();
+    }
+
+    #[test]
+    fn test_repository_priority() {
+        let db = tempfile::tempdir().unwrap();
+        let repos = db.path().join("repos");
+        std::fs::write(
+            db.path().join("repos.toml"),
+            "[[repo]]\nname = \"team\"\nurl = \"/srv/team\"\npriority = 10\n",
+        )
+        .unwrap();
+        write_app(&repos.join("main"), "firefox", "upstream");
+        write_app(&repos.join("main"), "gimp", "upstream");
+        write_app(&repos.join("team"), "firefox", "team");
+        write_app(db.path(), "gimp", "local");
+
+        let firefox = find_by_name_in(db.path(), "firefox").unwrap().unwrap();
+        assert_eq!(firefox.app.description, "team");
+        assert_eq!(firefox.repo.as_deref(), Some("team"));
+
+        let gimp = find_by_name_in(db.path(), "gimp").unwrap().unwrap();
+        assert_eq!(gimp.repo.as_deref(), Some(repo::LOCAL_REPO));
+
+        // 重複は優先度の高いものだけ
+        let apps = all_in(db.path()).unwrap();
+        assert_eq!(apps.len(), 2);
+        assert!(find_by_name_in(db.path(), "missing").unwrap().is_none());
+    }
+
     #[test]
     fn test_expand_url() {
         let config = sample_config();
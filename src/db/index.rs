@@ -0,0 +1,315 @@
+//! アプリDBインデックスモジュール
+//!
+//! 検索・詳細表示・インストールのたびに全TOMLファイルを読み込まないよう、
+//! 名前・エイリアス・説明・カテゴリ・キーワード・形式・アーキテクチャ・ファイルパスを
+//! DBディレクトリの `.index.json` にまとめて保存します。
+//!
+//! インデックスには作成時の各ファイルの更新日時とサイズから求めたフィンガープリントを保存し、
+//! TOMLファイルやリポジトリの登録が変わった場合は読み込み時に自動で作り直します。
+
+use crate::db::app::{self, AppConfig, SourceType};
+use crate::db::repo;
+use crate::events;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// インデックスファイル名（`*.toml` として読み込まれないよう拡張子は `.json`）
+pub const INDEX_FILE: &str = ".index.json";
+
+/// インデックスの形式のバージョン（変更した場合は作り直す）
+const INDEX_VERSION: u32 = 3;
+
+/// インデックスのエントリ（アプリ1件）
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct IndexEntry {
+    /// アプリ名
+    pub name: String,
+    /// 別名
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// 説明
+    pub description: String,
+    /// カテゴリ
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    /// 検索用のキーワード
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// インストールタイプ
+    pub source_type: SourceType,
+    /// 代替ソースのインストールタイプ
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<SourceType>,
+    /// 対応アーキテクチャ（全ソースの和集合）
+    pub architecture: Vec<String>,
+    /// 読み込み元のリポジトリ名
+    pub repo: String,
+    /// TOMLファイルのパス
+    pub path: PathBuf,
+}
+
+impl IndexEntry {
+    fn new(config: &AppConfig, repo: &str, path: &Path) -> Self {
+        Self {
+            name: config.app.name.clone(),
+            aliases: config.app.aliases.clone(),
+            description: config.app.description.clone(),
+            categories: config
+                .metadata
+                .as_ref()
+                .and_then(|m| m.categories.clone())
+                .unwrap_or_default(),
+            keywords: config
+                .metadata
+                .as_ref()
+                .map(|m| m.keywords.clone())
+                .unwrap_or_default(),
+            source_type: config.source.source_type,
+            alternatives: config.alternatives.iter().map(|s| s.source_type).collect(),
+            architecture: config.sources().fold(Vec::new(), |mut archs, source| {
+                for arch in &source.architecture {
+                    if !archs.contains(arch) {
+                        archs.push(arch.clone());
+                    }
+                }
+                archs
+            }),
+            repo: repo.to_string(),
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// いずれかのソースが指定のインストールタイプか
+    pub fn has_source_type(&self, source_type: SourceType) -> bool {
+        self.source_type == source_type || self.alternatives.contains(&source_type)
+    }
+
+    /// アプリ名またはエイリアスが一致するか
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.name == name || self.aliases.iter().any(|a| a == name)
+    }
+
+    /// エントリのTOMLファイルを読み込む
+    pub fn load(&self) -> Result<AppConfig> {
+        let mut config = app::load(&self.path)?;
+        config.repo = Some(self.repo.clone());
+        Ok(config)
+    }
+}
+
+/// アプリDBインデックス
+///
+/// エントリは優先度順に並び、同じ名前のアプリは優先度の高いものだけを含みます。
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Index {
+    version: u32,
+    fingerprint: u64,
+    /// アプリ一覧（優先度順）
+    pub entries: Vec<IndexEntry>,
+}
+
+impl Index {
+    /// アプリ名で検索し、なければエイリアスで検索
+    pub fn find(&self, name: &str) -> Option<&IndexEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.name == name)
+            .or_else(|| self.entries.iter().find(|e| e.matches_name(name)))
+    }
+}
+
+/// インデックスを読み込む（古い場合は作り直して保存）
+pub fn load(db_dir: &Path) -> Result<Index> {
+    let dirs = repo::search_dirs(db_dir)?;
+    let fingerprint = fingerprint(&dirs);
+
+    let cached = fs::read_to_string(db_dir.join(INDEX_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Index>(&content).ok())
+        .filter(|index| index.version == INDEX_VERSION && index.fingerprint == fingerprint);
+    if let Some(index) = cached {
+        return Ok(index);
+    }
+
+    let index = build(&dirs, fingerprint);
+    // 書き込めない場合（読み取り専用のDBなど）は保存せずに使用する
+    if db_dir.is_dir() {
+        let _ = save(db_dir, &index);
+    }
+    Ok(index)
+}
+
+/// インデックスを保存
+///
+/// ロックを取得しないコマンド（search など）も保存するため、一時ファイルに書き込んでから
+/// `rename` で置き換え、同時に読み書きする AN に書き込み途中の内容を見せないようにします。
+fn save(db_dir: &Path, index: &Index) -> Result<()> {
+    let mut temp = tempfile::Builder::new()
+        .prefix(&format!("{}.", INDEX_FILE))
+        .tempfile_in(db_dir)?;
+    temp.write_all(serde_json::to_string(index)?.as_bytes())?;
+    temp.persist(db_dir.join(INDEX_FILE))?;
+    Ok(())
+}
+
+/// インデックスを作り直して保存
+pub fn rebuild(db_dir: &Path) -> Result<Index> {
+    let _ = fs::remove_file(db_dir.join(INDEX_FILE));
+    load(db_dir)
+}
+
+/// 全リポジトリのTOMLファイルからインデックスを作成
+fn build(dirs: &[(String, PathBuf)], fingerprint: u64) -> Index {
+    let mut entries = Vec::new();
+    let mut seen = HashSet::new();
+
+    for (repo, dir) in dirs {
+        for path in toml_files(dir) {
+            match app::load(&path) {
+                Ok(config) => {
+                    if seen.insert(config.app.name.clone()) {
+                        entries.push(IndexEntry::new(&config, repo, &path));
+                    }
+                }
+                Err(e) => events::warn(format!("{} を読み込めません: {}", path.display(), e)),
+            }
+        }
+    }
+
+    Index {
+        version: INDEX_VERSION,
+        fingerprint,
+        entries,
+    }
+}
+
+/// リポジトリの構成と各TOMLファイルの更新日時・サイズから求めたフィンガープリント
+fn fingerprint(dirs: &[(String, PathBuf)]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for (repo, dir) in dirs {
+        repo.hash(&mut hasher);
+        dir.hash(&mut hasher);
+        for path in toml_files(dir) {
+            path.hash(&mut hasher);
+            if let Ok(metadata) = fs::metadata(&path) {
+                metadata.len().hash(&mut hasher);
+                metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .hash(&mut hasher);
+            }
+        }
+    }
+    hasher.finish()
+}
+
+/// ディレクトリ直下の `*.toml`（名前順、存在しない場合は空）
+fn toml_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && p.extension().map(|e| e == "toml").unwrap_or(false))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_app(dir: &Path, file: &str, name: &str, extra: &str) {
+        fs::create_dir_all(dir).unwrap();
+        let content = format!(
+            "[app]\nname = \"{}\"\ndescription = \"{} app\"\n{}\n[source]\ntype = \"flatpak\"\nflatpak_id = \"org.example.App\"\narchitecture = [\"x86_64\"]\n",
+            name, name, extra
+        );
+        fs::write(dir.join(format!("{}.toml", file)), content).unwrap();
+    }
+
+    #[test]
+    fn test_build_and_cache() {
+        let db = tempfile::tempdir().unwrap();
+        let main = db.path().join("repos").join("main");
+        write_app(&main, "firefox", "firefox", "aliases = [\"ff\"]");
+        write_app(&main, "gimp", "gimp", "");
+        write_app(db.path(), "gimp", "gimp", "");
+
+        let index = load(db.path()).unwrap();
+        assert!(db.path().join(INDEX_FILE).exists());
+        // 書き込みに使用した一時ファイルは残らない
+        let leftovers = fs::read_dir(db.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                name.starts_with(INDEX_FILE) && name != INDEX_FILE
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+        assert_eq!(index.entries.len(), 2);
+        // ローカルのエントリが優先
+        assert_eq!(index.find("gimp").unwrap().repo, repo::LOCAL_REPO);
+        assert_eq!(index.find("ff").unwrap().name, "firefox");
+        assert!(index.find("missing").is_none());
+
+        // 保存したインデックスを再利用する
+        let cached = load(db.path()).unwrap();
+        assert_eq!(cached.fingerprint, index.fingerprint);
+        assert_eq!(cached.entries, index.entries);
+    }
+
+    #[test]
+    fn test_rebuilt_when_files_change() {
+        let db = tempfile::tempdir().unwrap();
+        let main = db.path().join("repos").join("main");
+        write_app(&main, "firefox", "firefox", "");
+        assert_eq!(load(db.path()).unwrap().entries.len(), 1);
+
+        write_app(&main, "gimp", "gimp", "");
+        assert_eq!(load(db.path()).unwrap().entries.len(), 2);
+
+        fs::remove_file(main.join("firefox.toml")).unwrap();
+        let index = load(db.path()).unwrap();
+        assert_eq!(index.entries.len(), 1);
+        assert_eq!(index.entries[0].name, "gimp");
+    }
+
+    #[test]
+    fn test_stale_or_broken_index_is_ignored() {
+        let db = tempfile::tempdir().unwrap();
+        write_app(db.path(), "tool", "tool", "");
+        fs::write(db.path().join(INDEX_FILE), "not json").unwrap();
+
+        let index = load(db.path()).unwrap();
+        assert_eq!(index.entries.len(), 1);
+        // ファイル名ではなくエントリのパスを保持する
+        write_app(db.path(), "renamed", "other", "");
+        let index = rebuild(db.path()).unwrap();
+        let other = index.find("other").unwrap();
+        assert_eq!(other.path, db.path().join("renamed.toml"));
+        assert_eq!(other.load().unwrap().app.name, "other");
+    }
+
+    #[test]
+    fn test_broken_entries_are_skipped() {
+        let db = tempfile::tempdir().unwrap();
+        write_app(db.path(), "tool", "tool", "");
+        fs::write(db.path().join("broken.toml"), "[app").unwrap();
+
+        let index = load(db.path()).unwrap();
+        assert_eq!(index.entries.len(), 1);
+    }
+}
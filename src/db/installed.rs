@@ -0,0 +1,173 @@
+//! インストール記録モジュール
+//!
+//! ANがインストールしたアプリを状態ディレクトリに記録します。
+//! - ユーザー単位: $XDG_STATE_HOME/an/installed/<name>.toml
+//! - システム全体: /var/lib/an/installed/<name>.toml
+
+use crate::config;
+use crate::db::app::SourceType;
+use crate::events;
+use crate::utils::fs as fs_utils;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// インストール記録
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct InstallRecord {
+    /// アプリ名（コマンド名）
+    pub name: String,
+    /// インストール形式
+    pub source_type: SourceType,
+    /// 形式ごとの識別子（パッケージ名、Flatpak ID、コマンド名）
+    pub identifier: String,
+    /// インストールしたバージョン（判明している場合）
+    #[serde(default)]
+    pub version: Option<String>,
+    /// 配置したファイル
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+    /// システム全体にインストールしたか
+    #[serde(default)]
+    pub system: bool,
+    /// 追加のコマンド名（`an apply` で作成した、コマンドへのシンボリックリンク）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+impl InstallRecord {
+    /// 現在のインストール範囲で記録を作成
+    pub fn new(name: &str, source_type: SourceType, identifier: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            source_type,
+            identifier: identifier.to_string(),
+            version: None,
+            files: Vec::new(),
+            system: config::get().is_system(),
+            aliases: Vec::new(),
+        }
+    }
+}
+
+/// 記録ディレクトリを取得
+pub fn records_dir() -> PathBuf {
+    config::get().state_dir().join("installed")
+}
+
+fn record_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.toml", name))
+}
+
+/// 記録を保存
+pub fn save(record: &InstallRecord) -> Result<()> {
+    save_in(&records_dir(), record)
+}
+
+fn save_in(dir: &Path, record: &InstallRecord) -> Result<()> {
+    let content = toml::to_string_pretty(record)?;
+    fs_utils::place_content(&record_path(dir, &record.name), &content, 0o644)
+}
+
+/// 記録を読み込む
+pub fn load(name: &str) -> Result<Option<InstallRecord>> {
+    load_in(&records_dir(), name)
+}
+
+fn load_in(dir: &Path, name: &str) -> Result<Option<InstallRecord>> {
+    let path = record_path(dir, name);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(path)?;
+    Ok(Some(toml::from_str(&content)?))
+}
+
+/// 全ての記録を読み込む
+pub fn load_all() -> Result<Vec<InstallRecord>> {
+    load_all_in(&records_dir())
+}
+
+fn load_all_in(dir: &Path) -> Result<Vec<InstallRecord>> {
+    let mut records = Vec::new();
+
+    if !dir.exists() {
+        return Ok(records);
+    }
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().map(|e| e == "toml").unwrap_or(false) {
+            let content = std::fs::read_to_string(&path)?;
+            match toml::from_str(&content) {
+                Ok(record) => records.push(record),
+                Err(e) => events::warn(format!(
+                    "インストール記録 {} を読み込めません: {}",
+                    path.display(),
+                    e
+                )),
+            }
+        }
+    }
+
+    records.sort_by(|a: &InstallRecord, b| a.name.cmp(&b.name));
+    Ok(records)
+}
+
+/// 記録を削除（存在しなければ何もしない）
+pub fn remove(name: &str) -> Result<()> {
+    fs_utils::unplace(&record_path(&records_dir(), name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn sample_record() -> InstallRecord {
+        InstallRecord {
+            name: "firefox".to_string(),
+            source_type: SourceType::AppImage,
+            identifier: "firefox".to_string(),
+            version: Some("120.0".to_string()),
+            files: vec![PathBuf::from("/opt/an/firefox/firefox.AppImage")],
+            system: true,
+            aliases: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp = tempdir().unwrap();
+        let record = sample_record();
+
+        save_in(temp.path(), &record).unwrap();
+        assert_eq!(load_in(temp.path(), "firefox").unwrap(), Some(record));
+        assert_eq!(load_in(temp.path(), "missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_load_all_sorted() {
+        let temp = tempdir().unwrap();
+        let mut second = sample_record();
+        second.name = "anytype".to_string();
+
+        save_in(temp.path(), &sample_record()).unwrap();
+        save_in(temp.path(), &second).unwrap();
+
+        let names: Vec<_> = load_all_in(temp.path())
+            .unwrap()
+            .into_iter()
+            .map(|r| r.name)
+            .collect();
+        assert_eq!(names, vec!["anytype", "firefox"]);
+    }
+
+    #[test]
+    fn test_load_all_missing_dir() {
+        assert!(load_all_in(Path::new("/nonexistent/an/installed"))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,373 @@
+//! 旧ツール（AM）のプログラム一覧の取り込みモジュール
+//!
+//! `_legacy/programs/` の一覧（`<arch>-apps` / `<arch>-appimages`）と
+//! インストールスクリプト（`<arch>/<name>`）から [`AppConfig`] を作成します。
+//!
+//! 旧スクリプトの多くはGitHub APIで最新版のURLを都度調べるため、固定のダウンロードURLが
+//! 分からないエントリや [`app::validate`] を通らないエントリは `needs-review/` に出力します。
+
+use crate::db::app::{self, AppConfig, AppInfo, SourceInfo, SourceType};
+use crate::errors::AnError;
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// 旧ツールが対応していたアーキテクチャ
+const ARCHITECTURES: [&str; 3] = ["x86_64", "aarch64", "i686"];
+
+/// 確認が必要なエントリの出力先（出力ディレクトリからの相対パス）
+pub const REVIEW_DIR: &str = "needs-review";
+
+/// 確認が必要なエントリ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewItem {
+    /// アプリ名
+    pub name: String,
+    /// 理由
+    pub reason: String,
+}
+
+/// 取り込み結果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// 検証を通ったエントリ
+    pub imported: Vec<String>,
+    /// 確認が必要なエントリ（`needs-review/` に出力）
+    pub needs_review: Vec<ReviewItem>,
+    /// アプリDBに既にある・名前をファイル名に使えないため出力しなかったエントリ
+    pub skipped: Vec<String>,
+}
+
+/// 一覧の1件
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ListedApp {
+    description: String,
+    architectures: BTreeSet<String>,
+    appimage: bool,
+}
+
+/// インストールスクリプトから読み取った情報
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct LegacyScript {
+    /// 公式サイト（`SITE=`）
+    homepage: Option<String>,
+    /// 固定のダウンロードURL
+    url: Option<String>,
+    /// 最新版を調べていたGitHubリポジトリ（`owner/repo`）
+    github: Option<String>,
+}
+
+/// 一覧ファイルの行（`◆ name : description`）を読み取る
+fn parse_list(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix('◆'))
+        .filter_map(|line| line.split_once(" : "))
+        .map(|(name, description)| (name.trim().to_string(), description.trim().to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+/// インストールスクリプトから公式サイトとダウンロード元を読み取る
+fn parse_script(content: &str) -> LegacyScript {
+    let mut script = LegacyScript::default();
+
+    for line in content.lines().map(str::trim) {
+        if let Some(site) = line.strip_prefix("SITE=") {
+            let site = site.trim_matches('"');
+            if script.homepage.is_none() && !site.is_empty() {
+                script.homepage = Some(if site.starts_with("http") {
+                    site.to_string()
+                } else {
+                    format!("https://github.com/{}", site)
+                });
+            }
+        } else if let Some(rest) = line.split("api.github.com/repos/").nth(1) {
+            if script.github.is_none() {
+                let repo: Vec<&str> = rest.split('/').take(2).collect();
+                if let [owner, name] = repo.as_slice() {
+                    script.github = Some(format!("{}/{}", owner, name));
+                }
+            }
+        } else if let Some(rest) = line.strip_prefix("wget ") {
+            // アイコンなどではなく本体のダウンロード（`|| exit 1`）で、変数を含まないもの
+            let Some(url) = rest.strip_suffix("|| exit 1") else {
+                continue;
+            };
+            let url = url.trim().trim_matches('"');
+            if script.url.is_none() && url.starts_with("http") && !url.contains(['$', ' ']) {
+                script.url = Some(url.to_string());
+            }
+        }
+    }
+
+    script
+}
+
+/// 一覧とスクリプトの情報からアプリ定義を作成
+///
+/// 確認が必要な場合は理由を返します。
+fn convert(name: &str, listed: &ListedApp, script: &LegacyScript) -> (AppConfig, Option<String>) {
+    let source_type = if listed.appimage {
+        SourceType::AppImage
+    } else {
+        SourceType::Script
+    };
+    let config = AppConfig {
+        app: AppInfo {
+            name: name.to_string(),
+            aliases: Vec::new(),
+            description: listed.description.clone(),
+            homepage: script.homepage.clone(),
+        },
+        source: SourceInfo {
+            source_type,
+            url: script.url.clone().unwrap_or_default(),
+            flatpak_id: None,
+            flatpak_branch: None,
+            flatpak_command: None,
+            flatpak_env: BTreeMap::new(),
+            flatpak_file_forwarding: None,
+            architecture: listed.architectures.iter().cloned().collect(),
+        },
+        alternatives: Vec::new(),
+        metadata: None,
+        repo: None,
+    };
+
+    let reason = if source_type == SourceType::Script {
+        Some("AppImage以外（アーカイブ・バイナリ）の形式は未対応です".to_string())
+    } else if script.url.is_none() {
+        Some(match script.github {
+            Some(ref github) => format!(
+                "ダウンロードURLを設定してください（https://github.com/{}/releases）",
+                github
+            ),
+            None => "ダウンロードURLを設定してください".to_string(),
+        })
+    } else {
+        app::validate(&config).err().map(|e| e.to_string())
+    };
+
+    (config, reason)
+}
+
+/// 旧ツールの一覧を読み込む（アーキテクチャをまとめる）
+fn load_lists(legacy_dir: &Path) -> BTreeMap<String, ListedApp> {
+    let mut apps: BTreeMap<String, ListedApp> = BTreeMap::new();
+
+    for arch in ARCHITECTURES {
+        let appimages: BTreeSet<String> =
+            fs::read_to_string(legacy_dir.join(format!("{}-appimages", arch)))
+                .map(|content| parse_list(&content).into_iter().map(|(n, _)| n).collect())
+                .unwrap_or_default();
+        let Ok(content) = fs::read_to_string(legacy_dir.join(format!("{}-apps", arch))) else {
+            continue;
+        };
+
+        for (name, description) in parse_list(&content) {
+            let appimage = appimages.contains(&name);
+            let entry = apps.entry(name).or_insert_with(|| ListedApp {
+                description,
+                architectures: BTreeSet::new(),
+                appimage: false,
+            });
+            entry.architectures.insert(arch.to_string());
+            entry.appimage |= appimage;
+        }
+    }
+
+    apps
+}
+
+/// 対応アーキテクチャのうち最初に見つかったインストールスクリプト
+fn load_script(legacy_dir: &Path, name: &str, listed: &ListedApp) -> LegacyScript {
+    ARCHITECTURES
+        .iter()
+        .filter(|arch| listed.architectures.contains(**arch))
+        .find_map(|arch| fs::read_to_string(legacy_dir.join(arch).join(name)).ok())
+        .map(|content| parse_script(&content))
+        .unwrap_or_default()
+}
+
+/// 旧ツールの一覧をアプリ定義に変換して出力
+///
+/// `exists` がtrueを返すアプリ（アプリDBに既にあるもの）は出力しません。
+pub fn import(
+    legacy_dir: &Path,
+    output: &Path,
+    exists: impl Fn(&str) -> bool,
+) -> Result<ImportSummary> {
+    let apps = load_lists(legacy_dir);
+    if apps.is_empty() {
+        return Err(AnError::ValidationError {
+            message: format!(
+                "旧ツールの一覧（<arch>-apps）が見つかりません: {}",
+                legacy_dir.display()
+            ),
+        }
+        .into());
+    }
+
+    let review_dir = output.join(REVIEW_DIR);
+    fs::create_dir_all(&review_dir)?;
+
+    let mut summary = ImportSummary::default();
+    for (name, listed) in &apps {
+        // ファイル名として使用できない名前は取り込まない
+        if exists(name) || name.contains(['/', '\\']) || name.starts_with('.') {
+            summary.skipped.push(name.clone());
+            continue;
+        }
+
+        let script = load_script(legacy_dir, name, listed);
+        let (config, reason) = convert(name, listed, &script);
+        let content = toml::to_string_pretty(&config)?;
+        let file = format!("{}.toml", name);
+
+        match reason {
+            None => {
+                fs::write(output.join(file), content)?;
+                summary.imported.push(name.clone());
+            }
+            Some(reason) => {
+                fs::write(
+                    review_dir.join(file),
+                    format!("# 要確認: {}\n\n{}", reason, content),
+                )?;
+                summary.needs_review.push(ReviewItem {
+                    name: name.clone(),
+                    reason,
+                });
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"#!/bin/sh
+APP=tool
+SITE="team/tool"
+version=$(curl -Ls https://api.github.com/repos/team/tool-appimage/releases/latest | grep -oi "https.*mage$" | head -1)
+wget "$version" || exit 1
+wget https://example.com/icon.png -O ./icons/"$APP" 2> /dev/null
+"#;
+
+    fn listed(appimage: bool) -> ListedApp {
+        ListedApp {
+            description: "Tool".to_string(),
+            architectures: ["x86_64".to_string()].into_iter().collect(),
+            appimage,
+        }
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let list =
+            "◆ 0ad : Unofficial. FOSS RTS game.\n◆ tool : A tool : with colon.\nbroken line\n";
+        assert_eq!(
+            parse_list(list),
+            vec![
+                ("0ad".to_string(), "Unofficial. FOSS RTS game.".to_string()),
+                ("tool".to_string(), "A tool : with colon.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_script() {
+        let script = parse_script(SCRIPT);
+        assert_eq!(
+            script.homepage.as_deref(),
+            Some("https://github.com/team/tool")
+        );
+        assert_eq!(script.github.as_deref(), Some("team/tool-appimage"));
+        // 変数を含むダウンロードとアイコンのダウンロードは使用しない
+        assert_eq!(script.url, None);
+
+        let direct = parse_script(
+            "SITE=\"https://example.com\"\nwget \"https://example.com/Tool-x86_64.AppImage\" || exit 1\n",
+        );
+        assert_eq!(direct.homepage.as_deref(), Some("https://example.com"));
+        assert_eq!(
+            direct.url.as_deref(),
+            Some("https://example.com/Tool-x86_64.AppImage")
+        );
+    }
+
+    #[test]
+    fn test_convert_flags_missing_url_and_portable() {
+        let (config, reason) = convert("tool", &listed(true), &parse_script(SCRIPT));
+        assert_eq!(config.source.source_type, SourceType::AppImage);
+        assert!(reason.unwrap().contains("team/tool-appimage"));
+
+        let (_, reason) = convert("tool", &listed(false), &LegacyScript::default());
+        assert!(reason.unwrap().contains("未対応"));
+
+        let script = LegacyScript {
+            url: Some("https://example.com/Tool.AppImage".to_string()),
+            ..LegacyScript::default()
+        };
+        let (config, reason) = convert("tool", &listed(true), &script);
+        assert_eq!(reason, None);
+        app::validate(&config).unwrap();
+    }
+
+    #[test]
+    fn test_import() {
+        let legacy = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        let dir = legacy.path();
+        fs::write(
+            dir.join("x86_64-apps"),
+            "◆ direct : Direct\n◆ dynamic : Dynamic\n◆ existing : Existing\n◆ portable : Portable\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("x86_64-appimages"),
+            "◆ direct : Direct\n◆ dynamic : Dynamic\n",
+        )
+        .unwrap();
+        fs::write(dir.join("aarch64-apps"), "◆ direct : Direct\n").unwrap();
+        fs::write(dir.join("aarch64-appimages"), "◆ direct : Direct\n").unwrap();
+        fs::create_dir_all(dir.join("x86_64")).unwrap();
+        fs::write(
+            dir.join("x86_64").join("direct"),
+            "wget \"https://example.com/Direct.AppImage\" || exit 1\n",
+        )
+        .unwrap();
+        fs::write(dir.join("x86_64").join("dynamic"), SCRIPT).unwrap();
+
+        let summary = import(dir, output.path(), |name| name == "existing").unwrap();
+        assert_eq!(summary.imported, vec!["direct"]);
+        assert_eq!(summary.skipped, vec!["existing"]);
+        let review: Vec<_> = summary
+            .needs_review
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect();
+        assert_eq!(review, vec!["dynamic", "portable"]);
+
+        // 出力したエントリはアプリDBとして読み込める
+        let direct = app::load(&output.path().join("direct.toml")).unwrap();
+        assert_eq!(direct.source.architecture, vec!["aarch64", "x86_64"]);
+        app::validate(&direct).unwrap();
+        let dynamic = output.path().join(REVIEW_DIR).join("dynamic.toml");
+        let content = fs::read_to_string(&dynamic).unwrap();
+        assert!(content.starts_with("# 要確認:"));
+        assert!(app::load(&dynamic).is_ok());
+    }
+
+    #[test]
+    fn test_import_requires_lists() {
+        let legacy = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        assert!(import(legacy.path(), output.path(), |_| false).is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! アプリ定義の検査モジュール
+//!
+//! アプリDBのディレクトリ内の全TOMLファイルを検査します（`an db lint`）。
+//!
+//! - 読み込めること、[`app::validate`] を通ること
+//! - 名前・エイリアスが重複していないこと
+//! - カテゴリが既知のもの（freedesktop.org のカテゴリとANの追加分）であること
+//! - URLのプレースホルダーが展開できること（`{version}` には `metadata.version` が必要）
+//! - 任意でURLにアクセスできること
+
+use crate::db::app::{self, AppConfig, SourceType};
+use crate::handlers::remote;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 既知のカテゴリ（freedesktop.org Desktop Menu Specification のメイン・追加カテゴリ）
+#[rustfmt::skip]
+pub const KNOWN_CATEGORIES: &[&str] = &[
+    // メインカテゴリ
+    "AudioVideo", "Audio", "Video", "Development", "Education", "Game", "Graphics", "Network",
+    "Office", "Science", "Settings", "System", "Utility",
+    // 追加カテゴリ
+    "Building", "Debugger", "IDE", "GUIDesigner", "Profiling", "RevisionControl", "Translation",
+    "Calendar", "ContactManagement", "Database", "Dictionary", "Chart", "Email", "Finance",
+    "FlowChart", "PDA", "ProjectManagement", "Presentation", "Spreadsheet", "WordProcessor",
+    "2DGraphics", "VectorGraphics", "RasterGraphics", "3DGraphics", "Scanning", "OCR",
+    "Photography", "Publishing", "Viewer", "TextTools", "DesktopSettings", "HardwareSettings",
+    "Printing", "PackageManager", "Dialup", "InstantMessaging", "Chat", "IRCClient", "Feed",
+    "FileTransfer", "HamRadio", "News", "P2P", "RemoteAccess", "Telephony", "TelephonyTools",
+    "VideoConference", "WebBrowser", "WebDevelopment", "Midi", "Mixer", "Sequencer", "Tuner",
+    "TV", "AudioVideoEditing", "Player", "Recorder", "DiscBurning", "ActionGame",
+    "AdventureGame", "ArcadeGame", "BoardGame", "BlocksGame", "CardGame", "KidsGame",
+    "LogicGame", "RolePlaying", "Shooter", "Simulation", "SportsGame", "StrategyGame", "Art",
+    "Construction", "Music", "Languages", "ArtificialIntelligence", "Astronomy", "Biology",
+    "Chemistry", "ComputerScience", "DataVisualization", "Economy", "Electricity", "Geography",
+    "Geology", "Geoscience", "History", "Humanities", "ImageProcessing", "Literature", "Maps",
+    "Math", "NumericalAnalysis", "MedicalSoftware", "Physics", "Robotics", "Spirituality",
+    "Sports", "ParallelComputing", "Amusement", "Archiving", "Compression", "Electronics",
+    "Emulator", "Engineering", "FileTools", "FileManager", "TerminalEmulator", "Filesystem",
+    "Monitor", "Security", "Accessibility", "Calculator", "Clock", "TextEditor", "Documentation",
+    "Adult", "Core", "ConsoleOnly",
+    // ANの追加分
+    "NoteTaking",
+];
+
+/// URLで使用できるプレースホルダー
+const PLACEHOLDERS: [&str; 2] = ["{version}", "{arch}"];
+
+/// 検出した問題
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    /// TOMLファイルのパス
+    pub path: PathBuf,
+    /// 内容
+    pub message: String,
+}
+
+/// ディレクトリの検査結果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintReport {
+    /// 検査したディレクトリ
+    pub dir: PathBuf,
+    /// 検査したファイル数
+    pub files: usize,
+    /// 検出した問題
+    pub issues: Vec<LintIssue>,
+}
+
+/// ディレクトリ直下の全TOMLファイルを検査
+///
+/// `check_urls` の場合はダウンロードURLにアクセスできるかも確認します。
+pub fn lint_dir(dir: &Path, check_urls: bool) -> Result<LintReport> {
+    let mut report = LintReport {
+        dir: dir.to_path_buf(),
+        ..LintReport::default()
+    };
+    let mut issue = |path: &Path, message: String| {
+        report.issues.push(LintIssue {
+            path: path.to_path_buf(),
+            message,
+        })
+    };
+
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().map(|e| e == "toml").unwrap_or(false))
+        .collect();
+    files.sort();
+
+    // 名前・エイリアス → 最初に定義したファイル
+    let mut names: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for path in &files {
+        let config = match app::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                issue(path, format!("読み込めません: {}", e));
+                continue;
+            }
+        };
+
+        if let Err(e) = app::validate(&config) {
+            issue(path, e.to_string());
+        }
+
+        for name in std::iter::once(&config.app.name).chain(&config.app.aliases) {
+            match names.get(name) {
+                Some(other) => issue(
+                    path,
+                    format!(
+                        "名前 '{}' が {} と重複しています",
+                        name,
+                        other.file_name().unwrap().to_string_lossy()
+                    ),
+                ),
+                None => {
+                    names.insert(name.clone(), path.clone());
+                }
+            }
+        }
+
+        for message in check_categories(&config)
+            .into_iter()
+            .chain(check_placeholders(&config))
+        {
+            issue(path, message);
+        }
+
+        if check_urls {
+            for url in download_urls(&config) {
+                if let Err(e) = remote::check_url(&url) {
+                    issue(path, format!("URLにアクセスできません: {}", e));
+                }
+            }
+        }
+    }
+
+    report.files = files.len();
+    Ok(report)
+}
+
+/// 未知のカテゴリ
+fn check_categories(config: &AppConfig) -> Vec<String> {
+    config
+        .metadata
+        .as_ref()
+        .and_then(|m| m.categories.as_ref())
+        .into_iter()
+        .flatten()
+        .filter(|c| !KNOWN_CATEGORIES.contains(&c.as_str()))
+        .map(|c| format!("不明なカテゴリです: {}", c))
+        .collect()
+}
+
+/// 展開できないプレースホルダー（全ソースのURL）
+fn check_placeholders(config: &AppConfig) -> Vec<String> {
+    let has_version = config
+        .metadata
+        .as_ref()
+        .and_then(|m| m.version.as_ref())
+        .is_some();
+    let mut messages = Vec::new();
+
+    for source in config.sources() {
+        let url = &source.url;
+        if url.contains("{version}") && !has_version {
+            messages.push("URLに {version} がありますが metadata.version がありません".to_string());
+        }
+
+        let mut rest = url.as_str();
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            let placeholder = &rest[start..start + len + 1];
+            if !PLACEHOLDERS.contains(&placeholder) {
+                messages.push(format!("不明なプレースホルダーです: {}", placeholder));
+            }
+            rest = &rest[start + len + 1..];
+        }
+    }
+
+    messages
+}
+
+/// 全ソースの対応アーキテクチャごとに展開したダウンロードURL（Flatpakは対象外）
+fn download_urls(config: &AppConfig) -> Vec<String> {
+    let version = config.metadata.as_ref().and_then(|m| m.version.as_deref());
+    let mut urls: Vec<String> = config
+        .sources()
+        .filter(|s| s.source_type != SourceType::Flatpak && !s.url.is_empty())
+        .flat_map(|source| {
+            source.architecture.iter().map(move |arch| {
+                let url = source.url.replace("{arch}", arch);
+                match version {
+                    Some(version) => url.replace("{version}", version),
+                    None => url,
+                }
+            })
+        })
+        .collect();
+    urls.dedup();
+    urls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::remote::test_server::serve;
+
+    fn appimage(name: &str, url: &str, extra: &str) -> String {
+        format!(
+            "[app]\nname = \"{}\"\ndescription = \"{}\"\n{}\n[source]\ntype = \"appimage\"\nurl = \"{}\"\narchitecture = [\"x86_64\"]\n",
+            name, name, extra, url
+        )
+    }
+
+    fn messages(report: &LintReport) -> Vec<String> {
+        report
+            .issues
+            .iter()
+            .map(|i| {
+                format!(
+                    "{}: {}",
+                    i.path.file_name().unwrap().to_string_lossy(),
+                    i.message
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bundled_db_is_clean() {
+        let report = lint_dir(Path::new("apps"), false).unwrap();
+        assert!(report.files > 0);
+        assert_eq!(messages(&report), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_detects_problems() {
+        let dir = tempfile::tempdir().unwrap();
+        let write =
+            |file: &str, content: String| fs::write(dir.path().join(file), content).unwrap();
+        write(
+            "a.toml",
+            appimage("tool", "https://example.com/a", "aliases = [\"t\"]"),
+        );
+        write("b.toml", appimage("t", "https://example.com/b", ""));
+        write("broken.toml", "[app".to_string());
+        write("empty-url.toml", appimage("empty", "", ""));
+        write(
+            "placeholder.toml",
+            appimage("ph", "https://example.com/{version}/{os}", ""),
+        );
+        write(
+            "category.toml",
+            format!(
+                "{}\n[metadata]\ncategories = [\"Graphics\", \"Gfx\"]\n",
+                appimage("cat", "https://example.com/c", "")
+            ),
+        );
+
+        let report = lint_dir(dir.path(), false).unwrap();
+        assert_eq!(report.files, 6);
+        let messages = messages(&report);
+        assert_eq!(messages.len(), 6, "{:?}", messages);
+        assert!(messages[0].starts_with("b.toml: 名前 't' が a.toml と重複"));
+        assert!(messages[1].starts_with("broken.toml: 読み込めません"));
+        assert_eq!(messages[2], "category.toml: 不明なカテゴリです: Gfx");
+        assert!(messages[3].starts_with("empty-url.toml: E903"));
+        assert!(messages[4].contains("metadata.version"));
+        assert_eq!(
+            messages[5],
+            "placeholder.toml: 不明なプレースホルダーです: {os}"
+        );
+    }
+
+    #[test]
+    fn test_check_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = serve(vec![("/ok-1.0-x86_64.appimage", b"app".to_vec())], 2);
+        fs::write(
+            dir.path().join("ok.toml"),
+            format!(
+                "{}\n[metadata]\nversion = \"1.0\"\n",
+                appimage(
+                    "ok",
+                    &format!("{}/ok-{{version}}-{{arch}}.appimage", base),
+                    ""
+                )
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("missing.toml"),
+            appimage("missing", &format!("{}/missing.AppImage", base), ""),
+        )
+        .unwrap();
+
+        let report = lint_dir(dir.path(), true).unwrap();
+        let messages = messages(&report);
+        assert_eq!(messages.len(), 1, "{:?}", messages);
+        assert!(messages[0].starts_with("missing.toml: URLにアクセスできません"));
+    }
+}
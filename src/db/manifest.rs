@@ -0,0 +1,185 @@
+//! インストールマニフェストモジュール
+//!
+//! `an install -f team-apps.toml` で一括インストールするアプリの一覧を読み込みます。
+//! 同じ形式のファイル（`Anfile`）を `an apply` ではマシンのあるべき状態として使用し、
+//! `an export` で現在の状態を書き出します。
+//!
+//! ```toml
+//! [[apps]]
+//! name = "firefox"
+//!
+//! [[apps]]
+//! name = "gimp"
+//! source = "flatpak"    # 使用するソースの形式（省略時は install.prefer の順）
+//!
+//! [[apps]]
+//! name = "obsidian"
+//! version = "1.5.3"     # URLの {version} に使用するバージョン（省略時はアプリDBの版）
+//! aliases = ["notes"]   # 追加のコマンド名（an apply のみ）
+//! ```
+
+use crate::db::app::SourceType;
+use crate::errors::AnError;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// `an apply` / `an export` の既定のファイル名
+pub const DEFAULT_FILE: &str = "Anfile";
+
+/// インストールマニフェスト
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Manifest {
+    /// インストールするアプリ（記載順）
+    #[serde(default)]
+    pub apps: Vec<ManifestEntry>,
+}
+
+/// マニフェストのアプリ1件
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    /// アプリ名（エイリアスも可）
+    pub name: String,
+    /// バージョン
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// 使用するソースの形式
+    #[serde(default, alias = "via", skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceType>,
+    /// 追加のコマンド名
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+impl ManifestEntry {
+    /// 名前のみのエントリ
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: None,
+            source: None,
+            aliases: Vec::new(),
+        }
+    }
+}
+
+/// マニフェストファイルを読み込む
+pub fn load(path: &Path) -> Result<Manifest> {
+    if !path.exists() {
+        return Err(AnError::FileNotFound {
+            path: path.display().to_string(),
+        }
+        .into());
+    }
+    parse(&fs::read_to_string(path)?)
+}
+
+/// マニフェストの内容を解析
+pub fn parse(content: &str) -> Result<Manifest> {
+    let manifest: Manifest = toml::from_str(content).map_err(AnError::from)?;
+
+    if manifest.apps.is_empty() {
+        return Err(AnError::ValidationError {
+            message: "マニフェストにアプリがありません".to_string(),
+        }
+        .into());
+    }
+    if let Some(i) = manifest.apps.iter().position(|e| e.name.trim().is_empty()) {
+        return Err(AnError::ValidationError {
+            message: format!("マニフェストの {} 件目のアプリ名が空です", i + 1),
+        }
+        .into());
+    }
+    for entry in &manifest.apps {
+        if let Some(alias) = entry.aliases.iter().find(|a| !is_command_name(a)) {
+            return Err(AnError::ValidationError {
+                message: format!(
+                    "{} のエイリアス '{}' はコマンド名に使えません",
+                    entry.name, alias
+                ),
+            }
+            .into());
+        }
+    }
+    Ok(manifest)
+}
+
+/// マニフェストをTOMLに変換
+pub fn to_string(manifest: &Manifest) -> Result<String> {
+    Ok(toml::to_string_pretty(manifest)?)
+}
+
+/// コマンド名として使える名前か（パス区切りや空白を含まない）
+fn is_command_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(|c: char| c == '/' || c.is_whitespace())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let manifest = parse(
+            r#"
+[[apps]]
+name = "firefox"
+
+[[apps]]
+name = "gimp"
+source = "flatpak"
+
+[[apps]]
+name = "obsidian"
+version = "1.5.3"
+via = "appimage"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.apps.len(), 3);
+        assert_eq!(manifest.apps[0], ManifestEntry::new("firefox"));
+        assert_eq!(manifest.apps[1].source, Some(SourceType::Flatpak));
+        assert_eq!(manifest.apps[2].version.as_deref(), Some("1.5.3"));
+        assert_eq!(manifest.apps[2].source, Some(SourceType::AppImage));
+    }
+
+    #[test]
+    fn test_aliases_roundtrip() {
+        let manifest = Manifest {
+            apps: vec![
+                ManifestEntry {
+                    source: Some(SourceType::AppImage),
+                    version: Some("1.5.3".to_string()),
+                    aliases: vec!["notes".to_string()],
+                    ..ManifestEntry::new("obsidian")
+                },
+                ManifestEntry::new("firefox"),
+            ],
+        };
+        let content = to_string(&manifest).unwrap();
+        assert!(content.contains("aliases = [\"notes\"]"), "{}", content);
+        assert!(!content.contains("firefox\"\nversion"));
+        assert_eq!(parse(&content).unwrap(), manifest);
+
+        assert!(parse("[[apps]]\nname = \"gimp\"\naliases = [\"../gimp\"]\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("").is_err());
+        assert!(parse("[[apps]]\nname = \"\"\n").is_err());
+        assert!(parse("[[apps]]\nname = \"gimp\"\nsource = \"snap\"\n").is_err());
+        assert!(parse("[[apps]\n").is_err());
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let err = load(Path::new("/nonexistent/team-apps.toml")).unwrap_err();
+        assert!(err.to_string().starts_with("E001"), "{}", err);
+    }
+}
@@ -3,5 +3,15 @@
 //! TOMLアプリデータベースの管理を提供します。
 
 pub mod app;
+pub mod index;
+pub mod installed;
+pub mod legacy;
+pub mod lint;
+pub mod manifest;
+pub mod plan;
+pub mod repo;
+pub mod search;
+pub mod signature;
+pub mod sync;
 
 pub use app::find_by_name;
@@ -0,0 +1,255 @@
+//! 適用計画モジュール
+//!
+//! `an apply` で、Anfile（[`Manifest`]）に記載したあるべき状態とインストール記録を比較し、
+//! 実行する操作の一覧を作成します。
+//!
+//! - 未インストールのアプリ → インストール
+//! - 固定したバージョン・ソースの形式が異なる、またはアプリDBに新しい版があるアプリ → 更新
+//! - エイリアスのみ異なるアプリ → エイリアスの設定
+//! - 記載のないANのインストール記録（`prune` 時のみ） → 削除
+
+use crate::db::app::SourceType;
+use crate::db::installed::InstallRecord;
+use crate::db::manifest::{Manifest, ManifestEntry};
+
+/// 適用する操作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// 未インストールのアプリをインストール
+    Install { entry: ManifestEntry },
+    /// インストール済みのアプリを記載のバージョン・形式で入れ替え
+    Upgrade {
+        entry: ManifestEntry,
+        /// インストール済みの形式
+        source_type: SourceType,
+        /// インストール済みのバージョン
+        installed: Option<String>,
+        /// インストールするバージョン（アプリDBの版の場合は `None`）
+        target: Option<String>,
+    },
+    /// エイリアスのみ設定
+    SetAliases { name: String, aliases: Vec<String> },
+    /// 記載のないアプリを削除
+    Remove {
+        name: String,
+        source_type: SourceType,
+    },
+}
+
+impl Action {
+    /// 対象のアプリ名
+    pub fn name(&self) -> &str {
+        match self {
+            Action::Install { entry } | Action::Upgrade { entry, .. } => &entry.name,
+            Action::SetAliases { name, .. } | Action::Remove { name, .. } => name,
+        }
+    }
+}
+
+/// 適用計画
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plan {
+    /// 実行する操作（インストール・更新・エイリアス・削除の順）
+    pub actions: Vec<Action>,
+    /// 変更のないアプリ
+    pub unchanged: Vec<String>,
+}
+
+impl Plan {
+    /// 実行する操作がないか
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+}
+
+/// あるべき状態とインストール記録から適用計画を作成
+///
+/// `desired` のアプリ名はアプリDBの名前（エイリアスではなく）に揃えておくこと。
+/// `outdated` はバージョンを固定していないアプリについて、アプリDBにより新しい版が
+/// あればそのバージョンを返します。
+pub fn compute(
+    desired: &Manifest,
+    records: &[InstallRecord],
+    outdated: impl Fn(&InstallRecord) -> Option<String>,
+    prune: bool,
+) -> Plan {
+    let mut installs = Vec::new();
+    let mut upgrades = Vec::new();
+    let mut aliases = Vec::new();
+    let mut plan = Plan::default();
+
+    for entry in &desired.apps {
+        let Some(record) = records.iter().find(|r| r.name == entry.name) else {
+            installs.push(Action::Install {
+                entry: entry.clone(),
+            });
+            continue;
+        };
+
+        let source_changed = entry.source.is_some_and(|s| s != record.source_type);
+        let target = match entry.version {
+            Some(ref version) if record.version.as_ref() != Some(version) => Some(version.clone()),
+            Some(_) => None,
+            None => outdated(record),
+        };
+        if source_changed || target.is_some() {
+            upgrades.push(Action::Upgrade {
+                entry: entry.clone(),
+                source_type: record.source_type,
+                installed: record.version.clone(),
+                target: target.or_else(|| entry.version.clone()),
+            });
+        } else if !same_aliases(&entry.aliases, &record.aliases) {
+            aliases.push(Action::SetAliases {
+                name: entry.name.clone(),
+                aliases: entry.aliases.clone(),
+            });
+        } else {
+            plan.unchanged.push(entry.name.clone());
+        }
+    }
+
+    let removes = records
+        .iter()
+        .filter(|_| prune)
+        .filter(|r| !desired.apps.iter().any(|e| e.name == r.name))
+        .map(|r| Action::Remove {
+            name: r.name.clone(),
+            source_type: r.source_type,
+        });
+
+    plan.actions = installs
+        .into_iter()
+        .chain(upgrades)
+        .chain(aliases)
+        .chain(removes)
+        .collect();
+    plan
+}
+
+/// 順序を問わずエイリアスが同じか
+fn same_aliases(a: &[String], b: &[String]) -> bool {
+    let mut a = a.to_vec();
+    let mut b = b.to_vec();
+    a.sort();
+    a.dedup();
+    b.sort();
+    b.dedup();
+    a == b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(name: &str, source_type: SourceType, version: Option<&str>) -> InstallRecord {
+        InstallRecord {
+            name: name.to_string(),
+            source_type,
+            identifier: name.to_string(),
+            version: version.map(str::to_string),
+            files: Vec::new(),
+            system: false,
+            aliases: Vec::new(),
+        }
+    }
+
+    fn entry(name: &str) -> ManifestEntry {
+        ManifestEntry::new(name)
+    }
+
+    fn names(plan: &Plan) -> Vec<&str> {
+        plan.actions.iter().map(Action::name).collect()
+    }
+
+    #[test]
+    fn test_compute() {
+        let desired = Manifest {
+            apps: vec![
+                entry("firefox"),
+                ManifestEntry {
+                    version: Some("1.5.3".to_string()),
+                    ..entry("obsidian")
+                },
+                ManifestEntry {
+                    source: Some(SourceType::Flatpak),
+                    ..entry("gimp")
+                },
+                ManifestEntry {
+                    aliases: vec!["code".to_string()],
+                    ..entry("vscode")
+                },
+                entry("vlc"),
+                entry("blender"),
+            ],
+        };
+        let mut vscode = record("vscode", SourceType::Deb, Some("1.95.3"));
+        vscode.aliases = vec!["vsc".to_string()];
+        let records = vec![
+            record("obsidian", SourceType::AppImage, Some("1.5.0")),
+            record("gimp", SourceType::AppImage, Some("2.10")),
+            vscode,
+            record("vlc", SourceType::AppImage, Some("3.0")),
+            record("blender", SourceType::Flatpak, Some("4.0")),
+            record("tool", SourceType::AppImage, None),
+        ];
+        let outdated = |r: &InstallRecord| (r.name == "vlc").then(|| "3.1".to_string());
+
+        let plan = compute(&desired, &records, outdated, false);
+        assert_eq!(
+            names(&plan),
+            ["firefox", "obsidian", "gimp", "vlc", "vscode"]
+        );
+        assert_eq!(plan.unchanged, ["blender"]);
+        assert_eq!(
+            plan.actions[1],
+            Action::Upgrade {
+                entry: desired.apps[1].clone(),
+                source_type: SourceType::AppImage,
+                installed: Some("1.5.0".to_string()),
+                target: Some("1.5.3".to_string()),
+            }
+        );
+        // 形式のみ異なる場合はアプリDBの版
+        assert!(matches!(
+            plan.actions[2],
+            Action::Upgrade { target: None, .. }
+        ));
+        assert!(matches!(
+            plan.actions[3],
+            Action::Upgrade { ref target, .. } if target.as_deref() == Some("3.1")
+        ));
+        assert_eq!(
+            plan.actions[4],
+            Action::SetAliases {
+                name: "vscode".to_string(),
+                aliases: vec!["code".to_string()],
+            }
+        );
+
+        // 記載のないアプリは prune の場合のみ削除
+        let plan = compute(&desired, &records, outdated, true);
+        assert_eq!(
+            plan.actions.last(),
+            Some(&Action::Remove {
+                name: "tool".to_string(),
+                source_type: SourceType::AppImage,
+            })
+        );
+    }
+
+    #[test]
+    fn test_compute_pinned_version_is_not_upgraded() {
+        let desired = Manifest {
+            apps: vec![ManifestEntry {
+                version: Some("1.5.0".to_string()),
+                ..entry("obsidian")
+            }],
+        };
+        let records = vec![record("obsidian", SourceType::AppImage, Some("1.5.0"))];
+        // アプリDBに新しい版があっても固定したバージョンを優先する
+        let plan = compute(&desired, &records, |_| Some("1.5.3".to_string()), true);
+        assert!(plan.is_empty());
+        assert_eq!(plan.unchanged, ["obsidian"]);
+    }
+}
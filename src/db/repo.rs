@@ -0,0 +1,357 @@
+//! アプリDBリポジトリ（タップ）管理モジュール
+//!
+//! 公式DB（`main`）に加えて、チーム内のアプリ定義などを別リポジトリとして登録できます。
+//!
+//! DBディレクトリの構成:
+//!
+//! ```text
+//! <db_dir>/
+//! ├── *.toml            # ローカルのエントリ（同期で変更されない、最優先）
+//! ├── repos.toml        # 登録済みリポジトリ
+//! └── repos/<name>/     # リポジトリごとの同期先
+//! ```
+//!
+//! 同じ名前のアプリが複数のリポジトリにある場合は優先度の高いものを使用します。
+
+use crate::config;
+use crate::db::signature;
+use crate::errors::AnError;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 公式DBのリポジトリ名
+pub const MAIN_REPO: &str = "main";
+
+/// DBディレクトリ直下のエントリを表すリポジトリ名
+pub const LOCAL_REPO: &str = "local";
+
+/// `repo add` で優先度を省略した場合の値（公式DBより優先）
+pub const DEFAULT_PRIORITY: i32 = 10;
+
+/// 登録済みリポジトリ一覧のファイル名
+const REGISTRY_FILE: &str = "repos.toml";
+
+/// リポジトリ
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Repository {
+    /// リポジトリ名（ディレクトリ名）
+    pub name: String,
+    /// 取得元（git URL・ローカルパス・HTTPSのtarball）
+    pub url: String,
+    /// 優先度（大きいほど優先）
+    #[serde(default)]
+    pub priority: i32,
+    /// 署名を検証する公開鍵（minisign形式、設定時は署名が必須）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+/// リポジトリの取得元の種類
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoSource {
+    /// gitリポジトリ
+    Git(String),
+    /// ローカルディレクトリ
+    Path(PathBuf),
+    /// HTTP(S)で配布される tar.gz
+    Tarball(String),
+}
+
+impl Repository {
+    /// 取得元の種類を判定
+    pub fn source(&self) -> RepoSource {
+        let url = self.url.as_str();
+        let is_http = url.starts_with("https://") || url.starts_with("http://");
+
+        if is_http && [".tar.gz", ".tgz"].iter().any(|ext| url.ends_with(ext)) {
+            RepoSource::Tarball(self.url.clone())
+        } else if let Some(path) = url.strip_prefix("file://") {
+            RepoSource::Path(PathBuf::from(path))
+        } else if url.starts_with('/') || url.starts_with('.') {
+            RepoSource::Path(PathBuf::from(url))
+        } else {
+            RepoSource::Git(self.url.clone())
+        }
+    }
+
+    /// 同期先ディレクトリ
+    pub fn dir_in(&self, db_dir: &Path) -> PathBuf {
+        db_dir.join("repos").join(&self.name)
+    }
+}
+
+/// 登録ファイルの内容
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Registry {
+    #[serde(default, rename = "repo")]
+    repos: Vec<Repository>,
+}
+
+impl Registry {
+    fn load(db_dir: &Path) -> Result<Self> {
+        let path = db_dir.join(REGISTRY_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    fn save(&self, db_dir: &Path) -> Result<()> {
+        fs::create_dir_all(db_dir)?;
+        fs::write(db_dir.join(REGISTRY_FILE), toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// 公式DBのリポジトリ
+fn main_repo() -> Repository {
+    Repository {
+        name: MAIN_REPO.to_string(),
+        url: config::get().sync_repository().to_string(),
+        priority: 0,
+        public_key: config::get().sync_public_key().map(str::to_string),
+    }
+}
+
+/// 登録済みリポジトリを優先度順に取得
+pub fn list() -> Result<Vec<Repository>> {
+    list_in(&config::get().db_dir())
+}
+
+fn list_in(db_dir: &Path) -> Result<Vec<Repository>> {
+    let registry = Registry::load(db_dir)?;
+
+    // 公式DBは常に含める（登録ファイルで優先度を変更可能）
+    let mut repos = Vec::new();
+    if !registry.repos.iter().any(|r| r.name == MAIN_REPO) {
+        repos.push(main_repo());
+    }
+    repos.extend(registry.repos);
+
+    // 同じ優先度では登録順
+    repos.sort_by_key(|r| std::cmp::Reverse(r.priority));
+    Ok(repos)
+}
+
+/// 名前でリポジトリを取得
+pub fn get(name: &str) -> Result<Repository> {
+    list()?.into_iter().find(|r| r.name == name).ok_or_else(|| {
+        AnError::RepositoryNotFound {
+            name: name.to_string(),
+        }
+        .into()
+    })
+}
+
+/// リポジトリを登録
+pub fn add(
+    name: &str,
+    url: &str,
+    priority: Option<i32>,
+    public_key: Option<&str>,
+) -> Result<Repository> {
+    add_in(&config::get().db_dir(), name, url, priority, public_key)
+}
+
+fn add_in(
+    db_dir: &Path,
+    name: &str,
+    url: &str,
+    priority: Option<i32>,
+    public_key: Option<&str>,
+) -> Result<Repository> {
+    validate_name(name)?;
+    if let Some(key) = public_key {
+        signature::validate_public_key(key)?;
+    }
+
+    let mut registry = Registry::load(db_dir)?;
+    if name == MAIN_REPO || registry.repos.iter().any(|r| r.name == name) {
+        return Err(AnError::RepositoryExists {
+            name: name.to_string(),
+        }
+        .into());
+    }
+
+    let mut repo = Repository {
+        name: name.to_string(),
+        url: url.to_string(),
+        priority: priority.unwrap_or(DEFAULT_PRIORITY),
+        public_key: public_key.map(str::to_string),
+    };
+
+    // 相対パスは登録時の作業ディレクトリ基準で絶対パスにする
+    if let RepoSource::Path(path) = repo.source() {
+        if let Ok(absolute) = fs::canonicalize(path) {
+            repo.url = absolute.display().to_string();
+        }
+    }
+
+    registry.repos.push(repo.clone());
+    registry.save(db_dir)?;
+    Ok(repo)
+}
+
+/// リポジトリの登録を解除し、同期済みのエントリを削除
+pub fn remove(name: &str) -> Result<()> {
+    remove_in(&config::get().db_dir(), name)
+}
+
+fn remove_in(db_dir: &Path, name: &str) -> Result<()> {
+    if name == MAIN_REPO {
+        return Err(AnError::ValidationError {
+            message: format!("リポジトリ '{}' は削除できません", MAIN_REPO),
+        }
+        .into());
+    }
+
+    let mut registry = Registry::load(db_dir)?;
+    let Some(index) = registry.repos.iter().position(|r| r.name == name) else {
+        return Err(AnError::RepositoryNotFound {
+            name: name.to_string(),
+        }
+        .into());
+    };
+
+    let repo = registry.repos.remove(index);
+    registry.save(db_dir)?;
+
+    let dir = repo.dir_in(db_dir);
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+/// リポジトリ名の検証（ディレクトリ名として使用するため）
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name != LOCAL_REPO
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+
+    if !valid {
+        return Err(AnError::ValidationError {
+            message: format!(
+                "リポジトリ名 '{}' は使用できません（英小文字・数字・'-'・'_' のみ、'{}' は予約済み）",
+                name, LOCAL_REPO
+            ),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// エントリを読み込むディレクトリを優先度順に取得
+///
+/// 先頭はDBディレクトリ直下のローカルエントリです。
+pub fn search_dirs(db_dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut dirs = vec![(LOCAL_REPO.to_string(), db_dir.to_path_buf())];
+    for repo in list_in(db_dir)? {
+        let dir = repo.dir_in(db_dir);
+        dirs.push((repo.name, dir));
+    }
+    Ok(dirs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(url: &str) -> Repository {
+        Repository {
+            name: "team".to_string(),
+            url: url.to_string(),
+            priority: 0,
+            public_key: None,
+        }
+    }
+
+    #[test]
+    fn test_source_detection() {
+        assert_eq!(
+            repo("https://github.com/team/apps.git").source(),
+            RepoSource::Git("https://github.com/team/apps.git".to_string())
+        );
+        assert_eq!(
+            repo("git@example.com:team/apps.git").source(),
+            RepoSource::Git("git@example.com:team/apps.git".to_string())
+        );
+        assert_eq!(
+            repo("/srv/an-apps").source(),
+            RepoSource::Path(PathBuf::from("/srv/an-apps"))
+        );
+        assert_eq!(
+            repo("file:///srv/an-apps").source(),
+            RepoSource::Path(PathBuf::from("/srv/an-apps"))
+        );
+        assert_eq!(
+            repo("https://example.com/apps.tar.gz").source(),
+            RepoSource::Tarball("https://example.com/apps.tar.gz".to_string())
+        );
+    }
+
+    #[test]
+    fn test_add_list_remove() {
+        let db = tempfile::tempdir().unwrap();
+
+        add_in(db.path(), "team", "/srv/team", None, None).unwrap();
+        add_in(db.path(), "low", "/srv/low", Some(-5), None).unwrap();
+
+        let names: Vec<_> = list_in(db.path())
+            .unwrap()
+            .into_iter()
+            .map(|r| r.name)
+            .collect();
+        assert_eq!(names, vec!["team", "main", "low"]);
+
+        // 同期済みディレクトリも削除される
+        let dir = db.path().join("repos").join("team");
+        fs::create_dir_all(&dir).unwrap();
+        remove_in(db.path(), "team").unwrap();
+        assert!(!dir.exists());
+        assert_eq!(list_in(db.path()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_add_rejects_duplicates_and_bad_names() {
+        let db = tempfile::tempdir().unwrap();
+        add_in(db.path(), "team", "/srv/team", None, None).unwrap();
+
+        let err = add_in(db.path(), "team", "/srv/other", None, None).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AnError>(),
+            Some(AnError::RepositoryExists { .. })
+        ));
+        assert!(add_in(db.path(), "main", "/srv/other", None, None).is_err());
+        assert!(add_in(db.path(), "local", "/srv/other", None, None).is_err());
+        assert!(add_in(db.path(), "../etc", "/srv/other", None, None).is_err());
+    }
+
+    #[test]
+    fn test_remove_errors() {
+        let db = tempfile::tempdir().unwrap();
+        assert!(remove_in(db.path(), MAIN_REPO).is_err());
+
+        let err = remove_in(db.path(), "missing").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AnError>(),
+            Some(AnError::RepositoryNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_search_dirs_order() {
+        let db = tempfile::tempdir().unwrap();
+        add_in(db.path(), "team", "/srv/team", Some(5), None).unwrap();
+
+        let dirs = search_dirs(db.path()).unwrap();
+        assert_eq!(dirs[0], (LOCAL_REPO.to_string(), db.path().to_path_buf()));
+        assert_eq!(dirs[1].0, "team");
+        assert_eq!(dirs[1].1, db.path().join("repos").join("team"));
+        assert_eq!(dirs[2].0, MAIN_REPO);
+    }
+}
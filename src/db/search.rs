@@ -0,0 +1,390 @@
+//! アプリDB検索モジュール
+//!
+//! インデックスのエントリをクエリとの一致度で順位付けします。
+//!
+//! 順位は「名前の完全一致 > 名前の前方一致 > 別名の一致 > 名前の部分一致 >
+//! 名前のあいまい一致 > 説明の一致」で、同じ順位の中では名前との編集距離が小さいもの、
+//! 名前順の順に並べます。あいまい一致は入力ミス（1〜3文字の脱字・誤字・入れ替え）を許容します。
+//! 説明・キーワードは、クエリの各単語がいずれかに含まれていれば一致とします。
+
+use crate::db::app::SourceType;
+use crate::db::index::IndexEntry;
+use std::collections::BTreeMap;
+
+/// 検索オプション
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// 表示する最大件数（`None` の場合は全件）
+    pub limit: Option<usize>,
+    /// カテゴリで絞り込む（大文字小文字は区別しない）
+    pub category: Option<String>,
+    /// インストールタイプで絞り込む
+    pub source_type: Option<SourceType>,
+    /// 対応アーキテクチャで絞り込む
+    pub arch: Option<String>,
+}
+
+impl SearchOptions {
+    /// 絞り込み条件が指定されているか
+    pub fn has_filters(&self) -> bool {
+        self.category.is_some() || self.source_type.is_some() || self.arch.is_some()
+    }
+
+    /// エントリが絞り込み条件を満たすか
+    pub fn matches(&self, entry: &IndexEntry) -> bool {
+        self.category.as_ref().map_or(true, |category| {
+            entry
+                .categories
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(category))
+        }) && self
+            .source_type
+            .map_or(true, |source_type| entry.has_source_type(source_type))
+            && self
+                .arch
+                .as_ref()
+                .map_or(true, |arch| entry.architecture.contains(arch))
+    }
+}
+
+/// クエリとの一致の種類（上にあるほど優先）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    /// 名前の完全一致
+    Exact,
+    /// 名前の前方一致
+    Prefix,
+    /// 別名の完全一致・前方一致
+    Alias,
+    /// 名前・別名の部分一致
+    Substring,
+    /// 名前・別名のあいまい一致
+    Fuzzy,
+    /// 説明・キーワードの一致
+    Description,
+}
+
+/// エントリをクエリとの一致度順に検索（大文字小文字は区別しない）
+///
+/// 絞り込み条件を満たすエントリのみを対象とし、最大 `options.limit` 件を返します。
+/// クエリが空の場合は条件を満たす全エントリを名前順に返します。
+pub fn search<'a>(
+    entries: &'a [IndexEntry],
+    query: &str,
+    options: &SearchOptions,
+) -> Vec<&'a IndexEntry> {
+    let limit = options.limit.unwrap_or(usize::MAX);
+    let entries = entries.iter().filter(|entry| options.matches(entry));
+
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        let mut all: Vec<&IndexEntry> = entries.collect();
+        all.sort_by(|a, b| a.name.cmp(&b.name));
+        all.truncate(limit);
+        return all;
+    }
+
+    let mut matches: Vec<(MatchKind, usize, &IndexEntry)> = entries
+        .filter_map(|entry| {
+            let kind = match_kind(entry, &query)?;
+            // 説明の一致は名前との近さに関係しない
+            let distance = match kind {
+                MatchKind::Description => 0,
+                _ => names(entry)
+                    .map(|n| distance(&query, &n))
+                    .min()
+                    .unwrap_or(0),
+            };
+            Some((kind, distance, entry))
+        })
+        .collect();
+    matches.sort_by(|a, b| (a.0, a.1, &a.2.name).cmp(&(b.0, b.1, &b.2.name)));
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, _, entry)| entry)
+        .collect()
+}
+
+/// 見つからなかった名前に近いアプリ名の候補（最大 `limit` 件）
+///
+/// 説明だけに一致するエントリは候補にしません。
+pub fn suggest(entries: &[IndexEntry], name: &str, limit: usize) -> Vec<String> {
+    let query = name.to_lowercase();
+    search(entries, name, &SearchOptions::default())
+        .into_iter()
+        .filter(|entry| match_kind(entry, &query) < Some(MatchKind::Description))
+        .take(limit)
+        .map(|entry| entry.name.clone())
+        .collect()
+}
+
+/// カテゴリごとのアプリ数（カテゴリ名順）
+pub fn categories(entries: &[IndexEntry]) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for category in entries.iter().flat_map(|e| &e.categories) {
+        *counts.entry(category).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(category, count)| (category.to_string(), count))
+        .collect()
+}
+
+/// エントリとクエリ（小文字）の一致の種類
+fn match_kind(entry: &IndexEntry, query: &str) -> Option<MatchKind> {
+    let name = entry.name.to_lowercase();
+    let aliases: Vec<String> = names(entry).skip(1).collect();
+
+    if name == query {
+        Some(MatchKind::Exact)
+    } else if name.starts_with(query) {
+        Some(MatchKind::Prefix)
+    } else if aliases.iter().any(|a| a.starts_with(query)) {
+        Some(MatchKind::Alias)
+    } else if names(entry).any(|n| n.contains(query)) {
+        Some(MatchKind::Substring)
+    } else if names(entry).any(|n| distance(query, &n) <= max_distance(query)) {
+        Some(MatchKind::Fuzzy)
+    } else if matches_text(entry, query) {
+        Some(MatchKind::Description)
+    } else {
+        None
+    }
+}
+
+/// クエリの各単語が説明またはキーワードに含まれるか
+fn matches_text(entry: &IndexEntry, query: &str) -> bool {
+    let description = entry.description.to_lowercase();
+    let keywords: Vec<String> = entry.keywords.iter().map(|k| k.to_lowercase()).collect();
+    query
+        .split_whitespace()
+        .all(|word| description.contains(word) || keywords.iter().any(|k| k.contains(word)))
+}
+
+/// 名前と別名（小文字）
+fn names(entry: &IndexEntry) -> impl Iterator<Item = String> + '_ {
+    std::iter::once(&entry.name)
+        .chain(&entry.aliases)
+        .map(|n| n.to_lowercase())
+}
+
+/// あいまい一致とみなす編集距離の上限（クエリが短いほど厳しくする）
+fn max_distance(query: &str) -> usize {
+    match query.chars().count() {
+        0..=2 => 0,
+        3..=4 => 1,
+        5..=8 => 2,
+        _ => 3,
+    }
+}
+
+/// 編集距離（挿入・削除・置換・隣接文字の入れ替えをそれぞれ1とする）
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // d[i][j] = a[..i] と b[..j] の距離
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn entry(name: &str, aliases: &[&str], description: &str) -> IndexEntry {
+        IndexEntry {
+            name: name.to_string(),
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            description: description.to_string(),
+            categories: Vec::new(),
+            keywords: Vec::new(),
+            source_type: SourceType::AppImage,
+            alternatives: Vec::new(),
+            architecture: vec!["x86_64".to_string()],
+            repo: "main".to_string(),
+            path: PathBuf::from(format!("{}.toml", name)),
+        }
+    }
+
+    fn find<'a>(entries: &'a [IndexEntry], query: &str) -> Vec<&'a str> {
+        find_with(entries, query, &SearchOptions::default())
+    }
+
+    fn find_with<'a>(
+        entries: &'a [IndexEntry],
+        query: &str,
+        options: &SearchOptions,
+    ) -> Vec<&'a str> {
+        search(entries, query, options)
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect()
+    }
+
+    fn entries() -> Vec<IndexEntry> {
+        vec![
+            entry("code", &["vscode"], "Visual Studio Code"),
+            entry("codeblocks", &[], "C++ IDE"),
+            entry("firefox", &["ff"], "Mozilla Firefox Web Browser"),
+            entry("librewolf", &[], "Firefox-based privacy browser"),
+            entry("obs-studio", &["obs"], "Streaming and recording"),
+            entry("vscodium", &[], "Free build of VS Code"),
+        ]
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("firefox", "firefox"), 0);
+        assert_eq!(distance("firefx", "firefox"), 1);
+        assert_eq!(distance("fierfox", "firefox"), 1);
+        assert_eq!(distance("firfeox", "firefox"), 1);
+        assert_eq!(distance("gimp", "inkscape"), 7);
+        assert_eq!(distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_ranking() {
+        let entries = entries();
+        // 完全一致 > 前方一致 > 別名 > 部分一致 > 説明
+        assert_eq!(
+            find(&entries, "code"),
+            vec!["code", "codeblocks", "vscodium"]
+        );
+        assert_eq!(find(&entries, "Firefox"), vec!["firefox", "librewolf"]);
+        assert_eq!(find(&entries, "obs"), vec!["obs-studio"]);
+        assert_eq!(find(&entries, "ff"), vec!["firefox"]);
+        assert_eq!(find(&entries, "browser"), vec!["firefox", "librewolf"]);
+    }
+
+    #[test]
+    fn test_typo_tolerance() {
+        let entries = entries();
+        assert_eq!(find(&entries, "firefx"), vec!["firefox"]);
+        assert_eq!(find(&entries, "fierfox"), vec!["firefox"]);
+        // 別名とのあいまい一致（vscode）より名前に近いものを優先
+        assert_eq!(find(&entries, "vscodim"), vec!["vscodium", "code"]);
+        // 短いクエリでは誤字を許容しない
+        assert!(find(&entries, "fx").is_empty());
+    }
+
+    #[test]
+    fn test_suggest() {
+        let entries = entries();
+        assert_eq!(suggest(&entries, "firefx", 3), vec!["firefox"]);
+        assert_eq!(suggest(&entries, "cod", 1), vec!["code"]);
+        // 説明だけの一致は候補にしない
+        assert!(suggest(&entries, "browser", 3).is_empty());
+        assert!(suggest(&entries, "zzzzzz", 3).is_empty());
+    }
+
+    /// 絞り込み用のエントリ
+    fn catalog() -> Vec<IndexEntry> {
+        let mut bitwarden = entry("bitwarden", &[], "Bitwarden");
+        bitwarden.categories = vec!["Utility".to_string(), "Security".to_string()];
+        bitwarden.keywords = vec!["password".to_string(), "manager".to_string()];
+        let mut keepassxc = entry("keepassxc", &[], "KeePassXC - Password Manager");
+        keepassxc.categories = vec!["Utility".to_string(), "Security".to_string()];
+        keepassxc.architecture.push("aarch64".to_string());
+        keepassxc.alternatives = vec![SourceType::Flatpak];
+        let mut gimp = entry("gimp", &[], "GNU Image Manipulation Program");
+        gimp.categories = vec!["Graphics".to_string()];
+        gimp.source_type = SourceType::Flatpak;
+        gimp.architecture.push("aarch64".to_string());
+        vec![bitwarden, keepassxc, gimp]
+    }
+
+    #[test]
+    fn test_keywords() {
+        let entries = catalog();
+        assert_eq!(
+            find(&entries, "password manager"),
+            vec!["bitwarden", "keepassxc"]
+        );
+        assert!(find(&entries, "password browser").is_empty());
+    }
+
+    #[test]
+    fn test_filters() {
+        let entries = catalog();
+        let options = |category: Option<&str>, source_type, arch: Option<&str>| SearchOptions {
+            category: category.map(String::from),
+            source_type,
+            arch: arch.map(String::from),
+            ..SearchOptions::default()
+        };
+
+        assert_eq!(
+            find_with(&entries, "", &options(Some("security"), None, None)),
+            vec!["bitwarden", "keepassxc"]
+        );
+        assert_eq!(
+            find_with(&entries, "", &options(None, None, Some("aarch64"))),
+            vec!["gimp", "keepassxc"]
+        );
+        assert_eq!(
+            find_with(
+                &entries,
+                "",
+                &options(Some("Graphics"), Some(SourceType::Flatpak), Some("aarch64"))
+            ),
+            vec!["gimp"]
+        );
+        // 代替ソースの形式でも絞り込める
+        assert_eq!(
+            find_with(
+                &entries,
+                "",
+                &options(None, Some(SourceType::Flatpak), None)
+            ),
+            vec!["gimp", "keepassxc"]
+        );
+        assert!(find_with(
+            &entries,
+            "gimp",
+            &options(None, Some(SourceType::Deb), None)
+        )
+        .is_empty());
+        assert_eq!(
+            find_with(
+                &entries,
+                "password",
+                &SearchOptions {
+                    limit: Some(1),
+                    ..SearchOptions::default()
+                }
+            ),
+            vec!["bitwarden"]
+        );
+    }
+
+    #[test]
+    fn test_categories() {
+        assert_eq!(
+            categories(&catalog()),
+            vec![
+                ("Graphics".to_string(), 1),
+                ("Security".to_string(), 2),
+                ("Utility".to_string(), 2),
+            ]
+        );
+    }
+}
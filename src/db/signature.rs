@@ -0,0 +1,176 @@
+//! アプリDBの署名検証モジュール
+//!
+//! アプリDBはダウンロード・実行するURLを決めるため、公開されるtarballは
+//! minisign 形式（Ed25519）で署名されます。同期時に取得元と同じURLに
+//! `.minisig` を付けた署名ファイルを取得し、リポジトリの公開鍵で検証します。
+//!
+//! 署名は `examples/sign_db.rs` または `minisign -S` で作成できます。
+
+use crate::errors::AnError;
+use anyhow::Result;
+use minisign_verify::{PublicKey, Signature};
+
+/// 署名ファイルの拡張子
+pub const SIGNATURE_EXTENSION: &str = ".minisig";
+
+/// 取得元に対応する署名ファイルのURL
+pub fn signature_url(url: &str) -> String {
+    format!("{}{}", url, SIGNATURE_EXTENSION)
+}
+
+/// 公開鍵を読み込む
+///
+/// base64の1行（`RW...`）と、`minisign.pub` ファイルの内容（コメント行付き）の両方に対応します。
+fn parse_public_key(public_key: &str) -> Result<PublicKey, minisign_verify::Error> {
+    let public_key = public_key.trim();
+    if public_key.lines().count() > 1 {
+        PublicKey::decode(public_key)
+    } else {
+        PublicKey::from_base64(public_key)
+    }
+}
+
+/// 公開鍵の形式を検証
+pub fn validate_public_key(public_key: &str) -> Result<()> {
+    parse_public_key(public_key).map_err(|e| AnError::ValidationError {
+        message: format!("公開鍵を読み込めません: {}", e),
+    })?;
+    Ok(())
+}
+
+/// データの署名を検証
+///
+/// 署名が壊れている・鍵が一致しない・内容が改ざんされている場合は
+/// [`AnError::SignatureInvalid`] を返します。
+pub fn verify(repo: &str, data: &[u8], signature: &str, public_key: &str) -> Result<()> {
+    let invalid = |message: String| AnError::SignatureInvalid {
+        name: repo.to_string(),
+        message,
+    };
+
+    let public_key = parse_public_key(public_key)
+        .map_err(|e| invalid(format!("公開鍵を読み込めません: {}", e)))?;
+    let signature = Signature::decode(signature)
+        .map_err(|e| invalid(format!("署名を読み込めません: {}", e)))?;
+
+    public_key
+        .verify(data, &signature, true)
+        .map_err(|e| invalid(format!("署名が一致しません: {}", e)))?;
+    Ok(())
+}
+
+/// テスト用の署名鍵
+#[cfg(test)]
+pub(crate) mod test_key {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use ed25519_compact::{KeyPair, Seed};
+
+    const KEY_ID: [u8; 8] = *b"an-tests";
+
+    fn key_pair(seed: u8) -> KeyPair {
+        KeyPair::from_seed(Seed::new([seed; Seed::BYTES]))
+    }
+
+    /// 公開鍵（minisign形式のbase64）
+    pub fn public_key(seed: u8) -> String {
+        let mut bin = b"Ed".to_vec();
+        bin.extend_from_slice(&KEY_ID);
+        bin.extend_from_slice(&key_pair(seed).pk[..]);
+        STANDARD.encode(bin)
+    }
+
+    /// minisign形式の署名
+    pub fn sign(seed: u8, data: &[u8]) -> String {
+        let key_pair = key_pair(seed);
+        let signature = key_pair.sk.sign(data, None);
+        let trusted_comment = "timestamp:0\tfile:apps.tar.gz";
+
+        let mut global = signature.to_vec();
+        global.extend_from_slice(trusted_comment.as_bytes());
+        let global_signature = key_pair.sk.sign(&global, None);
+
+        let mut line = b"Ed".to_vec();
+        line.extend_from_slice(&KEY_ID);
+        line.extend_from_slice(&signature[..]);
+
+        format!(
+            "untrusted comment: test\n{}\ntrusted comment: {}\n{}\n",
+            STANDARD.encode(line),
+            trusted_comment,
+            STANDARD.encode(&global_signature[..])
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_invalid(result: Result<()>) {
+        let err = result.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AnError>(),
+            Some(AnError::SignatureInvalid { .. })
+        ));
+    }
+
+    #[test]
+    fn test_verify_valid_signature() {
+        let signature = test_key::sign(1, b"apps");
+        verify("main", b"apps", &signature, &test_key::public_key(1)).unwrap();
+
+        // minisign.pub 形式の公開鍵
+        let file = format!(
+            "untrusted comment: minisign public key\n{}\n",
+            test_key::public_key(1)
+        );
+        verify("main", b"apps", &signature, &file).unwrap();
+    }
+
+    #[test]
+    fn test_verify_rejects_tampered_data() {
+        let signature = test_key::sign(1, b"apps");
+        assert_invalid(verify(
+            "main",
+            b"evil",
+            &signature,
+            &test_key::public_key(1),
+        ));
+    }
+
+    #[test]
+    fn test_verify_rejects_other_key() {
+        let signature = test_key::sign(2, b"apps");
+        assert_invalid(verify(
+            "main",
+            b"apps",
+            &signature,
+            &test_key::public_key(1),
+        ));
+    }
+
+    #[test]
+    fn test_verify_rejects_malformed_signature() {
+        assert_invalid(verify(
+            "main",
+            b"apps",
+            "not a signature",
+            &test_key::public_key(1),
+        ));
+    }
+
+    #[test]
+    fn test_validate_public_key() {
+        validate_public_key(&test_key::public_key(1)).unwrap();
+        assert!(validate_public_key("RWnotakey").is_err());
+    }
+
+    #[test]
+    fn test_signature_url() {
+        assert_eq!(
+            signature_url("https://example.com/apps.tar.gz"),
+            "https://example.com/apps.tar.gz.minisig"
+        );
+    }
+}
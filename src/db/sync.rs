@@ -0,0 +1,865 @@
+//! アプリDB同期モジュール
+//!
+//! 登録済みのリポジトリ（[`repo`]）からアプリDB（`apps/*.toml`）を取得し、
+//! リポジトリごとの同期先ディレクトリへ反映します。
+//! 取得元はHTTPSのtarball・gitリポジトリ（公式DBのデフォルト）・ローカルディレクトリに対応します。
+//!
+//! 取得した内容は同期先と同じファイルシステム上の一時ディレクトリに展開して検証し、
+//! 全エントリを読み込めた場合のみ同期先と入れ替えます。
+//! tarballは `ETag` / `Last-Modified` を保存し、変更がなければ再ダウンロードしません。
+//!
+//! 同期先 `repos/<name>/` の内容はすべて同期が管理し、取得元から削除されたエントリも削除します。
+//! DBディレクトリ直下のローカルのエントリは変更しません。
+
+use crate::config;
+use crate::db::app;
+use crate::db::index;
+use crate::db::repo::{self, RepoSource, Repository};
+use crate::db::signature;
+use crate::errors::AnError;
+use crate::handlers::remote::{self, Validators};
+use crate::utils::{fs as fs_utils, process};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 同期状態ファイル（`*.toml` として読み込まれないよう拡張子なし）
+const STATE_FILE: &str = ".sync-state";
+
+/// アプリ定義の変更の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// 新規追加
+    Added,
+    /// 内容の更新
+    Updated,
+    /// 取得元から削除
+    Removed,
+}
+
+/// アプリ定義ごとの変更
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AppChange {
+    /// アプリ名（読み込めない場合はファイル名）
+    pub name: String,
+    /// 変更の種類
+    pub kind: ChangeKind,
+    /// 同期前のバージョン（`metadata.version`）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_version: Option<String>,
+    /// 同期後のバージョン（`metadata.version`）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_version: Option<String>,
+}
+
+/// 同期結果（リポジトリごと）
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SyncSummary {
+    /// リポジトリ名
+    pub repo: String,
+    /// 新規追加したアプリ数
+    pub added: usize,
+    /// 更新したアプリ数
+    pub updated: usize,
+    /// 取得元から削除されたアプリ数
+    pub removed: usize,
+    /// 同期後のアプリ数
+    pub total: usize,
+    /// 前回の同期から変更がなかった（ダウンロードしていない）
+    pub not_modified: bool,
+    /// アプリごとの変更（名前順）
+    pub changes: Vec<AppChange>,
+}
+
+/// リポジトリごとの同期結果
+#[derive(Debug)]
+pub struct SyncResult {
+    /// リポジトリ名
+    pub repo: String,
+    /// 同期結果
+    pub result: Result<SyncSummary>,
+}
+
+/// 前回の同期の状態（tarballの条件付きリクエスト用）
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+struct SyncState {
+    /// 取得元（変更された場合は検証子を使わない）
+    url: String,
+    /// 検証に使用した公開鍵（変更された場合は検証子を使わない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    public_key: Option<String>,
+    #[serde(flatten)]
+    validators: Validators,
+}
+
+impl SyncState {
+    fn load(dir: &Path, repository: &Repository) -> Validators {
+        fs::read_to_string(dir.join(STATE_FILE))
+            .ok()
+            .and_then(|content| toml::from_str::<SyncState>(&content).ok())
+            .filter(|state| {
+                state.url == repository.url && state.public_key == repository.public_key
+            })
+            .map(|state| state.validators)
+            .unwrap_or_default()
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        // 書き込み途中で中断しても壊れた状態を残さないよう、一時ファイルから置き換える
+        let mut temp = tempfile::Builder::new()
+            .prefix(&format!("{}.", STATE_FILE))
+            .tempfile_in(dir)?;
+        temp.write_all(toml::to_string(self)?.as_bytes())?;
+        temp.persist(dir.join(STATE_FILE))?;
+        Ok(())
+    }
+}
+
+/// 取得した内容
+struct Fetched {
+    /// アプリ定義のあるディレクトリ
+    root: PathBuf,
+    /// 保存する同期状態
+    state: Option<SyncState>,
+}
+
+/// 登録済みの全リポジトリを同期
+///
+/// 途中のリポジトリで失敗しても残りのリポジトリの同期を続け、リポジトリごとの結果を
+/// 優先度順で返します。
+pub fn sync() -> Result<Vec<SyncResult>> {
+    Ok(repo::list()?
+        .iter()
+        .map(|repository| SyncResult {
+            repo: repository.name.clone(),
+            result: sync_repo(repository),
+        })
+        .collect())
+}
+
+/// リポジトリを同期
+pub fn sync_repo(repository: &Repository) -> Result<SyncSummary> {
+    sync_repo_in(&config::get().db_dir(), repository)
+}
+
+fn sync_repo_in(db_dir: &Path, repository: &Repository) -> Result<SyncSummary> {
+    let dest = repository.dir_in(db_dir);
+    let repos_dir = dest.parent().ok_or_else(|| AnError::ValidationError {
+        message: format!("同期先のディレクトリが不正です: {}", dest.display()),
+    })?;
+    fs::create_dir_all(repos_dir)?;
+
+    // 入れ替えをrenameで行うため、同期先と同じディレクトリに作業領域を作る
+    let staging = tempfile::Builder::new()
+        .prefix(".staging-")
+        .tempdir_in(repos_dir)?;
+
+    let failed = |message: String| AnError::RepositorySyncFailed {
+        name: repository.name.clone(),
+        message,
+    };
+
+    let source = repository.source();
+    if repository.public_key.is_some() && !matches!(source, RepoSource::Tarball(_)) {
+        return Err(AnError::SignatureInvalid {
+            name: repository.name.clone(),
+            message: "署名を検証できるのはHTTP(S)のtarballの取得元のみです".to_string(),
+        }
+        .into());
+    }
+
+    let fetched = match source {
+        RepoSource::Tarball(url) => fetch_tarball(repository, &url, &dest, staging.path()),
+        RepoSource::Git(url) => fetch_git(repository, &url, staging.path()).map(Some),
+        RepoSource::Path(path) => {
+            if !path.is_dir() {
+                return Err(failed(format!("ディレクトリがありません: {}", path.display())).into());
+            }
+            Ok(Some(Fetched {
+                root: apps_root(&path),
+                state: None,
+            }))
+        }
+    }
+    .map_err(|e| match e.downcast::<AnError>() {
+        Ok(e @ (AnError::RepositorySyncFailed { .. } | AnError::SignatureInvalid { .. })) => e,
+        Ok(e) => failed(e.to_string()),
+        Err(e) => failed(e.to_string()),
+    })?;
+
+    let mut summary = match fetched {
+        Some(fetched) => {
+            let new_dir = staging.path().join("new");
+            stage_apps(&fetched.root, &new_dir).map_err(|e| failed(e.to_string()))?;
+            if let Some(state) = fetched.state {
+                state.save(&new_dir)?;
+            }
+
+            let summary = diff(&dest, &new_dir)?;
+            replace_dir(&new_dir, &dest, staging.path())?;
+            summary
+        }
+        None => SyncSummary {
+            not_modified: true,
+            ..SyncSummary::default()
+        },
+    };
+
+    if repository.name == repo::MAIN_REPO {
+        remove_legacy_copies(db_dir, &dest)?;
+    }
+
+    // 次回の検索に備えてインデックスを更新
+    index::load(db_dir)?;
+
+    summary.repo = repository.name.clone();
+    summary.total = count_apps(&dest);
+    Ok(summary)
+}
+
+/// tar.gz を取得して展開（前回から変更がなければ `None`）
+///
+/// 公開鍵が設定されている場合は、展開する前に署名を検証します。
+fn fetch_tarball(
+    repository: &Repository,
+    url: &str,
+    dest: &Path,
+    staging: &Path,
+) -> Result<Option<Fetched>> {
+    let archive = staging.join("index.tar.gz");
+    let previous = SyncState::load(dest, repository);
+    let Some(validators) = remote::download_if_modified(url, &archive, &previous)? else {
+        return Ok(None);
+    };
+
+    if let Some(ref public_key) = repository.public_key {
+        let signature_url = signature::signature_url(url);
+        let signature =
+            remote::fetch_text(&signature_url).map_err(|_| AnError::SignatureInvalid {
+                name: repository.name.clone(),
+                message: format!("署名ファイルを取得できません: {}", signature_url),
+            })?;
+        signature::verify(
+            &repository.name,
+            &fs::read(&archive)?,
+            &signature,
+            public_key,
+        )?;
+    }
+
+    let unpacked = staging.join("unpacked");
+    fs::create_dir_all(&unpacked)?;
+    tar::Archive::new(flate2::read::GzDecoder::new(fs::File::open(&archive)?))
+        .unpack(&unpacked)
+        .map_err(|e| AnError::ValidationError {
+            message: format!("アーカイブを展開できません: {}", e),
+        })?;
+
+    Ok(Some(Fetched {
+        root: apps_root(&unpacked),
+        state: Some(SyncState {
+            url: url.to_string(),
+            public_key: repository.public_key.clone(),
+            validators,
+        }),
+    }))
+}
+
+/// gitリポジトリをクローン
+fn fetch_git(repository: &Repository, url: &str, staging: &Path) -> Result<Fetched> {
+    let is_main = repository.name == repo::MAIN_REPO;
+    let clone_dir = staging.join("clone");
+    let clone_path = fs_utils::path_str(&clone_dir)?;
+
+    // 公式DBはsparse checkoutでappsディレクトリのみ取得
+    let mut args = vec!["clone", "--depth=1"];
+    if is_main {
+        args.extend(["--filter=blob:none", "--sparse"]);
+    }
+    args.extend([url, clone_path]);
+
+    let output = process::output("git", &args).map_err(|e| AnError::RepositorySyncFailed {
+        name: repository.name.clone(),
+        message: format!("gitが利用できません: {}", e),
+    })?;
+    if !output.status.success() {
+        return Err(AnError::RepositorySyncFailed {
+            name: repository.name.clone(),
+            message: format!(
+                "git clone失敗: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        }
+        .into());
+    }
+
+    if is_main {
+        // sparse-checkoutでappsのみ展開
+        let _ = process::output("git", &["-C", clone_path, "sparse-checkout", "set", "apps"]);
+    }
+
+    Ok(Fetched {
+        root: apps_root(&clone_dir),
+        state: None,
+    })
+}
+
+/// 取得したリポジトリ内のアプリ定義ディレクトリ
+///
+/// `apps/` があればそれを、なければリポジトリ直下を使用します。
+/// tarballのように最上位ディレクトリが1つだけの場合はその中を探します。
+fn apps_root(root: &Path) -> PathBuf {
+    if root.join("apps").is_dir() {
+        return root.join("apps");
+    }
+
+    let entries: Vec<PathBuf> = fs::read_dir(root)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    match entries.as_slice() {
+        [single] if single.is_dir() => apps_root(single),
+        _ => root.to_path_buf(),
+    }
+}
+
+/// アプリ定義を検証して作業ディレクトリへコピー
+///
+/// 読み込めないエントリが1つでもある場合や、エントリがない場合は失敗します。
+fn stage_apps(source: &Path, new_dir: &Path) -> Result<()> {
+    fs::create_dir_all(new_dir)?;
+
+    let mut count = 0;
+    for path in toml_files(source)? {
+        let file_name = fs_utils::file_name(&path)?;
+        app::load(&path).map_err(|e| AnError::ValidationError {
+            message: format!("{}: {}", file_name.to_string_lossy(), e),
+        })?;
+        fs::copy(&path, new_dir.join(file_name))?;
+        count += 1;
+    }
+
+    if count == 0 {
+        return Err(AnError::ValidationError {
+            message: format!("アプリ定義が見つかりません: {}", source.display()),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// 同期前後の差分を集計
+fn diff(old_dir: &Path, new_dir: &Path) -> Result<SyncSummary> {
+    let mut summary = SyncSummary::default();
+
+    for path in toml_files(new_dir)? {
+        let old = old_dir.join(fs_utils::file_name(&path)?);
+        let kind = if !old.exists() {
+            ChangeKind::Added
+        } else if fs::read(&old)? != fs::read(&path)? {
+            ChangeKind::Updated
+        } else {
+            continue;
+        };
+
+        let (name, new_version) = describe(&path);
+        let old_version = match kind {
+            ChangeKind::Updated => describe(&old).1,
+            _ => None,
+        };
+        summary.changes.push(AppChange {
+            name,
+            kind,
+            old_version,
+            new_version,
+        });
+    }
+
+    for path in toml_files(old_dir)? {
+        if !new_dir.join(fs_utils::file_name(&path)?).exists() {
+            let (name, old_version) = describe(&path);
+            summary.changes.push(AppChange {
+                name,
+                kind: ChangeKind::Removed,
+                old_version,
+                new_version: None,
+            });
+        }
+    }
+
+    summary.changes.sort_by(|a, b| a.name.cmp(&b.name));
+    let count = |kind| summary.changes.iter().filter(|c| c.kind == kind).count();
+    summary.added = count(ChangeKind::Added);
+    summary.updated = count(ChangeKind::Updated);
+    summary.removed = count(ChangeKind::Removed);
+    Ok(summary)
+}
+
+/// アプリ名とバージョン（読み込めない場合はファイル名）
+fn describe(path: &Path) -> (String, Option<String>) {
+    match app::load(path) {
+        Ok(config) => (
+            config.app.name,
+            config.metadata.and_then(|metadata| metadata.version),
+        ),
+        Err(_) => (
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            None,
+        ),
+    }
+}
+
+/// 同期先を新しい内容と入れ替え
+///
+/// 古い内容は作業ディレクトリへ退避し、入れ替えに失敗した場合は元に戻します。
+fn replace_dir(new_dir: &Path, dest: &Path, staging: &Path) -> Result<()> {
+    if !dest.exists() {
+        fs::rename(new_dir, dest)?;
+        return Ok(());
+    }
+
+    let old = staging.join("old");
+    fs::rename(dest, &old)?;
+    if let Err(e) = fs::rename(new_dir, dest) {
+        fs::rename(&old, dest)?;
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// 旧レイアウト（DBディレクトリ直下）に同期されていた公式DBのコピーを削除
+///
+/// 内容が同期結果と同じファイルのみ削除し、ローカルで編集したエントリは残します。
+fn remove_legacy_copies(db_dir: &Path, main_dir: &Path) -> Result<()> {
+    for path in toml_files(main_dir)? {
+        let legacy = db_dir.join(fs_utils::file_name(&path)?);
+        if legacy.is_file() && fs::read(&legacy)? == fs::read(&path)? {
+            fs::remove_file(legacy)?;
+        }
+    }
+    Ok(())
+}
+
+/// ディレクトリ直下の `*.toml`（存在しない場合は空）
+fn toml_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().map(|e| e == "toml").unwrap_or(false) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// DBディレクトリ内のアプリ数をカウント
+pub fn count_apps(db_dir: &Path) -> usize {
+    if !db_dir.exists() {
+        return 0;
+    }
+
+    fs::read_dir(db_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| {
+                    e.path()
+                        .extension()
+                        .map(|ext| ext == "toml")
+                        .unwrap_or(false)
+                })
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::remote::test_server::serve;
+    use crate::utils::process::fake::{FakeRunner, Reply};
+    use crate::utils::process::with_runner;
+    use std::sync::Arc;
+
+    fn app_toml(name: &str, description: &str) -> String {
+        format!(
+            "[app]\nname = \"{}\"\ndescription = \"{}\"\n\n[source]\ntype = \"flatpak\"\nflatpak_id = \"org.example.App\"\narchitecture = [\"x86_64\"]\n",
+            name, description
+        )
+    }
+
+    fn repository(name: &str, url: &str) -> Repository {
+        Repository {
+            name: name.to_string(),
+            url: url.to_string(),
+            priority: 0,
+            public_key: None,
+        }
+    }
+
+    /// `apps/` を含む tar.gz を作成
+    fn tarball(apps: &[(&str, &str)]) -> Vec<u8> {
+        let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (name, description) in apps {
+            let content = app_toml(name, description);
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(
+                    &mut header,
+                    format!("an-db/apps/{}.toml", name),
+                    content.as_bytes(),
+                )
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn signed_repository(url: &str, seed: u8) -> Repository {
+        Repository {
+            public_key: Some(signature::test_key::public_key(seed)),
+            ..repository(repo::MAIN_REPO, url)
+        }
+    }
+
+    fn assert_signature_invalid(result: Result<SyncSummary>) {
+        let err = result.unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<AnError>(),
+                Some(AnError::SignatureInvalid { .. })
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_count_apps_nonexistent() {
+        let path = PathBuf::from("/nonexistent/path");
+        assert_eq!(count_apps(&path), 0);
+    }
+
+    #[test]
+    fn test_apps_root() {
+        let root = tempfile::tempdir().unwrap();
+        assert_eq!(apps_root(root.path()), root.path());
+
+        // tarballの最上位ディレクトリ配下の apps/
+        let apps = root.path().join("team-db-main").join("apps");
+        fs::create_dir_all(&apps).unwrap();
+        assert_eq!(apps_root(root.path()), apps);
+    }
+
+    #[test]
+    fn test_stage_apps_rejects_invalid_entries() {
+        let source = tempfile::tempdir().unwrap();
+        let staging = tempfile::tempdir().unwrap();
+
+        // エントリがない
+        assert!(stage_apps(source.path(), &staging.path().join("a")).is_err());
+
+        fs::write(source.path().join("ok.toml"), app_toml("ok", "OK")).unwrap();
+        stage_apps(source.path(), &staging.path().join("b")).unwrap();
+        assert!(staging.path().join("b").join("ok.toml").exists());
+
+        // 読み込めないエントリが1つでもあれば失敗
+        fs::write(source.path().join("broken.toml"), "[app").unwrap();
+        let err = stage_apps(source.path(), &staging.path().join("c")).unwrap_err();
+        assert!(err.to_string().contains("broken.toml"));
+    }
+
+    #[test]
+    fn test_diff_counts_changes() {
+        let old = tempfile::tempdir().unwrap();
+        let new = tempfile::tempdir().unwrap();
+        fs::write(new.path().join("new.toml"), "a").unwrap();
+        fs::write(new.path().join("changed.toml"), "b").unwrap();
+        fs::write(new.path().join("same.toml"), "c").unwrap();
+        fs::write(old.path().join("changed.toml"), "old").unwrap();
+        fs::write(old.path().join("same.toml"), "c").unwrap();
+        fs::write(old.path().join("gone.toml"), "d").unwrap();
+
+        let summary = diff(old.path(), new.path()).unwrap();
+        assert_eq!((summary.added, summary.updated, summary.removed), (1, 1, 1));
+        let names: Vec<_> = summary.changes.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["changed", "gone", "new"]);
+    }
+
+    #[test]
+    fn test_diff_reports_versions() {
+        let old = tempfile::tempdir().unwrap();
+        let new = tempfile::tempdir().unwrap();
+        let versioned = |name: &str, version: &str| {
+            format!(
+                "{}\n[metadata]\nversion = \"{}\"\n",
+                app_toml(name, name),
+                version
+            )
+        };
+        fs::write(old.path().join("gimp.toml"), versioned("gimp", "2.10")).unwrap();
+        fs::write(new.path().join("gimp.toml"), versioned("gimp", "3.0")).unwrap();
+        fs::write(old.path().join("legacy.toml"), versioned("old-tool", "1.0")).unwrap();
+
+        let summary = diff(old.path(), new.path()).unwrap();
+        assert_eq!(
+            summary.changes,
+            vec![
+                AppChange {
+                    name: "gimp".to_string(),
+                    kind: ChangeKind::Updated,
+                    old_version: Some("2.10".to_string()),
+                    new_version: Some("3.0".to_string()),
+                },
+                AppChange {
+                    // ファイル名ではなくエントリの app.name
+                    name: "old-tool".to_string(),
+                    kind: ChangeKind::Removed,
+                    old_version: Some("1.0".to_string()),
+                    new_version: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sync_path_repo_replaces_contents() {
+        let db = tempfile::tempdir().unwrap();
+        let source = tempfile::tempdir().unwrap();
+        let apps = source.path().join("apps");
+        fs::create_dir_all(&apps).unwrap();
+        fs::write(apps.join("tool.toml"), app_toml("tool", "Tool")).unwrap();
+        fs::write(apps.join("old.toml"), app_toml("old", "Old")).unwrap();
+
+        // ローカルのエントリ
+        fs::write(db.path().join("old.toml"), app_toml("old", "Local")).unwrap();
+
+        let team = repository("team", source.path().to_str().unwrap());
+        let summary = sync_repo_in(db.path(), &team).unwrap();
+        assert_eq!((summary.added, summary.total), (2, 2));
+
+        // 取得元から削除されたエントリは同期先からも消える
+        fs::remove_file(apps.join("old.toml")).unwrap();
+        let summary = sync_repo_in(db.path(), &team).unwrap();
+        assert_eq!((summary.removed, summary.total), (1, 1));
+        assert_eq!(summary.changes[0].kind, ChangeKind::Removed);
+        assert!(!team.dir_in(db.path()).join("old.toml").exists());
+
+        // ローカルのエントリは残る
+        assert!(db.path().join("old.toml").exists());
+
+        // 作業ディレクトリは残らない
+        let leftovers = fs::read_dir(db.path().join("repos")).unwrap().count();
+        assert_eq!(leftovers, 1);
+    }
+
+    #[test]
+    fn test_sync_keeps_previous_db_on_invalid_source() {
+        let db = tempfile::tempdir().unwrap();
+        let source = tempfile::tempdir().unwrap();
+        fs::write(source.path().join("tool.toml"), app_toml("tool", "Tool")).unwrap();
+        let team = repository("team", source.path().to_str().unwrap());
+        sync_repo_in(db.path(), &team).unwrap();
+
+        fs::write(source.path().join("broken.toml"), "not toml").unwrap();
+        let err = sync_repo_in(db.path(), &team).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AnError>(),
+            Some(AnError::RepositorySyncFailed { .. })
+        ));
+        assert_eq!(count_apps(&team.dir_in(db.path())), 1);
+    }
+
+    #[test]
+    fn test_sync_continues_after_failed_repository() {
+        let db = tempfile::tempdir().unwrap();
+        let source = tempfile::tempdir().unwrap();
+        fs::write(source.path().join("tool.toml"), app_toml("tool", "Tool")).unwrap();
+
+        let mut config = crate::config::Config::default();
+        config
+            .set("paths.db_dir", db.path().to_str().unwrap())
+            .unwrap();
+        // 公式DBの取得元がないため失敗する
+        config
+            .set(
+                "sync.repository",
+                source.path().join("missing").to_str().unwrap(),
+            )
+            .unwrap();
+
+        let results = crate::config::with_config(config, || {
+            // 公式DBより後に同期する
+            repo::add("team", source.path().to_str().unwrap(), Some(-1), None).unwrap();
+            sync().unwrap()
+        });
+        let repos: Vec<&str> = results.iter().map(|r| r.repo.as_str()).collect();
+        assert_eq!(repos, [repo::MAIN_REPO, "team"]);
+        assert!(matches!(
+            results[0]
+                .result
+                .as_ref()
+                .unwrap_err()
+                .downcast_ref::<AnError>(),
+            Some(AnError::RepositorySyncFailed { .. })
+        ));
+        assert_eq!(results[1].result.as_ref().unwrap().total, 1);
+    }
+
+    #[test]
+    fn test_sync_tarball_with_etag() {
+        let db = tempfile::tempdir().unwrap();
+        let base = serve(
+            vec![(
+                "/apps.tar.gz",
+                tarball(&[("firefox", "Firefox"), ("gimp", "GIMP")]),
+            )],
+            2,
+        );
+        let main = repository(repo::MAIN_REPO, &format!("{}/apps.tar.gz", base));
+
+        let summary = sync_repo_in(db.path(), &main).unwrap();
+        assert_eq!((summary.added, summary.total), (2, 2));
+        assert!(!summary.not_modified);
+        assert!(main.dir_in(db.path()).join(STATE_FILE).exists());
+        // 書き込みに使用した一時ファイルは残らない
+        let leftovers = fs::read_dir(main.dir_in(db.path()))
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                name.starts_with(STATE_FILE) && name != STATE_FILE
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+
+        // 2回目は If-None-Match で 304 になり、内容は変わらない
+        let summary = sync_repo_in(db.path(), &main).unwrap();
+        assert!(summary.not_modified);
+        assert_eq!(summary.total, 2);
+    }
+
+    #[test]
+    fn test_sync_signed_tarball() {
+        let db = tempfile::tempdir().unwrap();
+        let archive = tarball(&[("firefox", "Firefox")]);
+        let signature = signature::test_key::sign(1, &archive).into_bytes();
+        let base = serve(
+            vec![
+                ("/apps.tar.gz", archive),
+                ("/apps.tar.gz.minisig", signature),
+            ],
+            2,
+        );
+        let main = signed_repository(&format!("{}/apps.tar.gz", base), 1);
+
+        let summary = sync_repo_in(db.path(), &main).unwrap();
+        assert_eq!(summary.total, 1);
+    }
+
+    #[test]
+    fn test_sync_rejects_bad_signature() {
+        let db = tempfile::tempdir().unwrap();
+        let archive = tarball(&[("firefox", "Firefox")]);
+        // 別の鍵で署名
+        let signature = signature::test_key::sign(2, &archive).into_bytes();
+        let base = serve(
+            vec![
+                ("/apps.tar.gz", archive),
+                ("/apps.tar.gz.minisig", signature),
+            ],
+            2,
+        );
+        let main = signed_repository(&format!("{}/apps.tar.gz", base), 1);
+
+        assert_signature_invalid(sync_repo_in(db.path(), &main));
+        assert_eq!(count_apps(&main.dir_in(db.path())), 0);
+    }
+
+    #[test]
+    fn test_sync_rejects_missing_signature() {
+        let db = tempfile::tempdir().unwrap();
+        let base = serve(
+            vec![("/apps.tar.gz", tarball(&[("firefox", "Firefox")]))],
+            2,
+        );
+        let main = signed_repository(&format!("{}/apps.tar.gz", base), 1);
+
+        assert_signature_invalid(sync_repo_in(db.path(), &main));
+    }
+
+    #[test]
+    fn test_signed_repository_requires_tarball() {
+        let db = tempfile::tempdir().unwrap();
+        let source = tempfile::tempdir().unwrap();
+        let team = signed_repository(source.path().to_str().unwrap(), 1);
+
+        assert_signature_invalid(sync_repo_in(db.path(), &team));
+    }
+
+    #[test]
+    fn test_state_ignored_when_source_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = repository(repo::MAIN_REPO, "https://a.example/apps.tar.gz");
+        let state = SyncState {
+            url: main.url.clone(),
+            public_key: None,
+            validators: Validators {
+                etag: Some("\"v1\"".to_string()),
+                last_modified: None,
+            },
+        };
+        state.save(dir.path()).unwrap();
+
+        assert_eq!(SyncState::load(dir.path(), &main), state.validators);
+
+        let moved = repository(repo::MAIN_REPO, "https://b.example/apps.tar.gz");
+        assert_eq!(SyncState::load(dir.path(), &moved), Validators::default());
+
+        // 公開鍵を設定した場合は再取得して検証する
+        let signed = signed_repository(&main.url, 1);
+        assert_eq!(SyncState::load(dir.path(), &signed), Validators::default());
+    }
+
+    #[test]
+    fn test_git_failure() {
+        let staging = tempfile::tempdir().unwrap();
+        let fake = Arc::new(FakeRunner::new().on(&["clone"], Reply::fail(128, "not found")));
+        let team = repository("team", "https://example.com/team.git");
+
+        let err = with_runner(fake.clone(), || {
+            fetch_git(&team, "https://example.com/team.git", staging.path())
+        })
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("not found"));
+        assert!(!fake.called(&["--sparse"]));
+    }
+
+    #[test]
+    fn test_remove_legacy_copies() {
+        let db = tempfile::tempdir().unwrap();
+        let main = db.path().join("repos").join("main");
+        fs::create_dir_all(&main).unwrap();
+        fs::write(main.join("firefox.toml"), "synced").unwrap();
+        fs::write(main.join("gimp.toml"), "synced").unwrap();
+        fs::write(db.path().join("firefox.toml"), "synced").unwrap();
+        fs::write(db.path().join("gimp.toml"), "edited").unwrap();
+
+        remove_legacy_copies(db.path(), &main).unwrap();
+        assert!(!db.path().join("firefox.toml").exists());
+        assert!(db.path().join("gimp.toml").exists());
+    }
+}
@@ -36,6 +36,18 @@ pub enum AnError {
     #[error("E006: flatpak installに失敗しました: {message}")]
     FlatpakInstallError { message: String },
 
+    #[error("E007: 特権コマンドの実行に失敗しました: {command}: {message}")]
+    PrivilegedCommandFailed { command: String, message: String },
+
+    #[error("E008: 特権昇格の方法が見つかりません（sudo/doas/run0/pkexec をインストールするか、privilege.method = \"print\" を設定してください）")]
+    NoPrivilegeMethod,
+
+    #[error("E009: {name} をインストールできるソースがありません: {message}")]
+    NoSuitableSource { name: String, message: String },
+
+    #[error("E010: {total} 件中 {failed} 件のインストールに失敗しました")]
+    BatchInstallFailed { failed: usize, total: usize },
+
     // リンク関連 (E201-E299)
     #[error("E201: Flatpakがインストールされていません")]
     FlatpakNotInstalled,
@@ -49,6 +61,22 @@ pub enum AnError {
     #[allow(dead_code)]
     UpdateCheckFailed,
 
+    // リポジトリ関連 (E401-E499)
+    #[error("E401: リポジトリが見つかりません: {name}")]
+    RepositoryNotFound { name: String },
+
+    #[error("E402: リポジトリは登録済みです: {name}")]
+    RepositoryExists { name: String },
+
+    #[error("E403: リポジトリ '{name}' の同期に失敗しました: {message}")]
+    RepositorySyncFailed { name: String, message: String },
+
+    #[error("E404: リポジトリ '{name}' の署名を検証できません: {message}")]
+    SignatureInvalid { name: String, message: String },
+
+    #[error("E405: {total} 件中 {failed} 件のリポジトリの同期に失敗しました")]
+    BatchSyncFailed { failed: usize, total: usize },
+
     // 一般 (E901-E999)
     #[error("E901: I/Oエラー: {0}")]
     IoError(#[from] std::io::Error),
@@ -58,6 +86,18 @@ pub enum AnError {
 
     #[error("E903: バリデーションエラー: {message}")]
     ValidationError { message: String },
+
+    #[error("E904: 不明な設定キーです: {key}")]
+    UnknownConfigKey { key: String },
+
+    #[error("E905: アプリ定義に {count} 件の問題があります")]
+    LintFailed { count: usize },
+
+    #[error("E906: 別の an が実行中です（PID {pid}）。終了してから再実行してください")]
+    AlreadyRunning { pid: String },
+
+    #[error("E907: 設定は既に読み込まれています（プロセス内で使用できる設定は1つです）")]
+    ConfigAlreadySet,
 }
 
 #[cfg(test)]
@@ -0,0 +1,188 @@
+//! イベント通知
+//!
+//! 処理の進行状況を構造化されたイベントとして通知します。
+//! ハンドラは表示を行わず [`emit`] でイベントを送り、通知先が表示・記録を担当します。
+//! CLI（`an` コマンド）はイベントを表示し、ライブラリ利用者は
+//! [`Client::on_event`](crate::Client::on_event) で独自に受け取れます。
+
+use crate::db::app::SourceType;
+use crate::db::installed::InstallRecord;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// 処理の進行状況
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Event {
+    /// 処理の段階（「依存関係を解決中」など）
+    Step { message: String },
+    /// 対象のアプリとインストール形式を特定した
+    Resolved {
+        name: String,
+        source_type: SourceType,
+    },
+    /// ソースでのインストールに失敗し、次のソースを試す
+    SourceFailed {
+        name: String,
+        source_type: SourceType,
+        message: String,
+    },
+    /// ダウンロードを開始した
+    DownloadStarted { url: String },
+    /// ダウンロードの進捗（サイズ不明の場合 `total` は `None`）
+    DownloadProgress { downloaded: u64, total: Option<u64> },
+    /// ダウンロードが完了した
+    DownloadFinished { path: PathBuf },
+    /// 複数のファイルの並列ダウンロードを開始した（`names` の順に番号を振る）
+    ParallelDownloadStarted { names: Vec<String> },
+    /// 並列ダウンロードの進捗（`index` は `names` の番号、サイズ不明の場合 `total` は `None`）
+    ParallelDownloadProgress {
+        index: usize,
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    /// 並列ダウンロードの1件が終了した
+    ParallelDownloadFinished { index: usize, success: bool },
+    /// ファイルを配置した
+    FilePlaced { path: PathBuf },
+    /// シンボリックリンクを作成した
+    SymlinkCreated { link: PathBuf, target: PathBuf },
+    /// Flatpakラッパーを書き込んだ
+    WrapperWritten { name: String, app_id: String },
+    /// ファイルを削除した
+    FileRemoved { path: PathBuf },
+    /// 外部コマンドを実行した
+    CommandExecuted { command: String, success: bool },
+    /// 表示モードのため、root権限で実行する必要があるコマンド（シェルに貼り付け可能な形式）
+    RootCommandRequired { command: String },
+    /// ハンドラの処理が完了した
+    Completed { message: String },
+    /// インストールが完了した
+    Installed { record: InstallRecord },
+    /// 削除が完了した
+    Removed {
+        name: String,
+        source_type: SourceType,
+    },
+    /// 処理は続行できるが注意が必要
+    Warning { message: String },
+}
+
+/// イベントの通知先
+pub type Observer = dyn Fn(&Event);
+
+thread_local! {
+    /// 現在の通知先（スレッドごと）
+    static OBSERVER: RefCell<Option<Rc<Observer>>> = const { RefCell::new(None) };
+}
+
+/// 通知先を元に戻すガード（パニック時も復元する）
+struct Restore(Option<Rc<Observer>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        OBSERVER.with(|o| *o.borrow_mut() = previous);
+    }
+}
+
+/// 通知先を設定して処理を実行
+///
+/// 設定は現在のスレッドのみに作用し、`f` の終了後に元に戻ります。
+pub fn with_observer<T>(observer: Rc<Observer>, f: impl FnOnce() -> T) -> T {
+    let previous = OBSERVER.with(|o| o.borrow_mut().replace(observer));
+    let _restore = Restore(previous);
+    f()
+}
+
+/// イベントを通知（通知先がなければ何もしない）
+pub fn emit(event: Event) {
+    // 通知先の中で再度 emit できるよう、借用を解放してから呼び出す
+    let observer = OBSERVER.with(|o| o.borrow().clone());
+    if let Some(observer) = observer {
+        observer(&event);
+    }
+}
+
+/// 処理の段階を通知
+pub fn step(message: impl Into<String>) {
+    emit(Event::Step {
+        message: message.into(),
+    });
+}
+
+/// 警告を通知
+pub fn warn(message: impl Into<String>) {
+    emit(Event::Warning {
+        message: message.into(),
+    });
+}
+
+/// 処理の完了を通知
+pub fn completed(message: impl Into<String>) {
+    emit(Event::Completed {
+        message: message.into(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect<T>(f: impl FnOnce() -> T) -> (T, Vec<Event>) {
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let sink = received.clone();
+        let result = with_observer(
+            Rc::new(move |e: &Event| sink.borrow_mut().push(e.clone())),
+            f,
+        );
+        let events = received.borrow().clone();
+        (result, events)
+    }
+
+    #[test]
+    fn test_emit_without_observer() {
+        // 通知先がなくてもパニックしない
+        step("nothing");
+    }
+
+    #[test]
+    fn test_with_observer_receives_and_restores() {
+        let (_, events) = collect(|| {
+            step("a");
+            warn("b");
+        });
+        assert_eq!(
+            events,
+            vec![
+                Event::Step {
+                    message: "a".to_string()
+                },
+                Event::Warning {
+                    message: "b".to_string()
+                },
+            ]
+        );
+
+        // 終了後は通知されない
+        let (_, events) = collect(|| {});
+        step("after");
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_nested_observer() {
+        let (_, outer) = collect(|| {
+            let (_, inner) = collect(|| step("inner"));
+            assert_eq!(inner.len(), 1);
+            step("outer");
+        });
+        assert_eq!(
+            outer,
+            vec![Event::Step {
+                message: "outer".to_string()
+            }]
+        );
+    }
+}
@@ -2,41 +2,43 @@
 //!
 //! AppImageファイルのインストール・削除を処理します。
 //! - ~/Applications/ にAppImage本体を配置
-//! - ~/.local/bin/ にシンボリックリンクを作成
-//! - ~/.local/share/applications/ にデスクトップエントリを作成
+//! - $XDG_BIN_HOME（~/.local/bin/）にシンボリックリンクを作成
+//! - $XDG_DATA_HOME/applications/ にデスクトップエントリを作成
+//!
+//! システム全体モード（`--system`）では /opt/an/<app>/, /usr/local/bin/,
+//! /usr/share/applications/ に配置します。
+//!
+//! インストールはトランザクション（[`Transaction`]）として行い、
+//! 途中で失敗・中断した場合は元の状態に戻します。
 
+use super::{remote, InstallOptions, InstalledPackage, PackageHandler};
+use crate::config;
+use crate::db::{self, app::SourceType, installed, installed::InstallRecord};
 use crate::errors::AnError;
-use crate::utils::ui;
+use crate::events::{self, Event};
+use crate::utils::fs as fs_utils;
+use crate::utils::privilege;
+use crate::utils::transaction::Transaction;
 use anyhow::Result;
 use std::fs;
-use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 
+/// AppImage形式のハンドラ
+pub struct AppImageHandler;
+
 /// AppImage格納ディレクトリを取得
 pub fn apps_dir() -> PathBuf {
-    dirs::home_dir()
-        .expect("ホームディレクトリが見つかりません")
-        .join("Applications")
+    config::get().apps_dir()
 }
 
 /// シンボリックリンク配置先を取得
 pub fn bin_dir() -> PathBuf {
-    dirs::home_dir()
-        .expect("ホームディレクトリが見つかりません")
-        .join(".local")
-        .join("bin")
+    config::get().bin_dir()
 }
 
 /// デスクトップエントリ配置先を取得
 fn desktop_dir() -> PathBuf {
-    dirs::data_dir()
-        .unwrap_or_else(|| {
-            dirs::home_dir()
-                .expect("ホームディレクトリが見つかりません")
-                .join(".local")
-                .join("share")
-        })
-        .join("applications")
+    config::get().desktop_dir()
 }
 
 /// ファイル名からアプリ名を抽出
@@ -79,25 +81,36 @@ pub fn extract_app_name(path: &Path) -> String {
         .unwrap_or_else(|| "app".to_string())
 }
 
-/// インストールオプション
-#[derive(Default)]
-pub struct InstallOptions {
-    /// カスタムコマンド名（指定しない場合は自動抽出）
-    pub name: Option<String>,
-    /// デスクトップエントリを作成するか
-    pub desktop_entry: bool,
-    /// 元ファイルを削除するか（moveモード）
-    pub remove_source: bool,
+/// インストール結果
+#[derive(Debug, Clone)]
+pub struct InstalledAppImage {
+    /// コマンド名
+    pub name: String,
+    /// AppImage本体の配置先
+    pub path: PathBuf,
+    /// シンボリックリンク
+    pub link: PathBuf,
+    /// デスクトップエントリ（作成した場合）
+    pub desktop_entry: Option<PathBuf>,
+}
+
+impl InstalledAppImage {
+    /// 配置したファイル一覧
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files = vec![self.path.clone(), self.link.clone()];
+        files.extend(self.desktop_entry.clone());
+        files
+    }
 }
 
 /// AppImageをインストール
 #[allow(dead_code)]
 pub fn install(path: &Path) -> Result<()> {
-    install_with_options(path, InstallOptions::default())
+    install_with_options(path, InstallOptions::default()).map(|_| ())
 }
 
 /// オプション付きでAppImageをインストール
-pub fn install_with_options(path: &Path, options: InstallOptions) -> Result<()> {
+pub fn install_with_options(path: &Path, options: InstallOptions) -> Result<InstalledAppImage> {
     // ファイル存在確認
     if !path.exists() {
         return Err(AnError::FileNotFound {
@@ -107,72 +120,68 @@ pub fn install_with_options(path: &Path, options: InstallOptions) -> Result<()>
     }
 
     let app_name = options.name.unwrap_or_else(|| extract_app_name(path));
-    ui::info(&format!("AppImageをインストール中: {}", app_name));
+    events::step(format!("AppImageをインストール中: {}", app_name));
 
-    // 格納ディレクトリの確認・作成
-    let apps_directory = apps_dir();
-    if !apps_directory.exists() {
-        ui::info(&format!("ディレクトリ作成: {:?}", apps_directory));
-        fs::create_dir_all(&apps_directory)?;
-    }
-
-    // ファイルコピーまたは移動
-    let original_filename = path.file_name().unwrap();
-    let dest_path = apps_directory.join(original_filename);
+    // 格納先（システム全体モードではアプリごとのサブディレクトリ）
+    let original_filename = fs_utils::file_name(path)?;
+    let dest_path = if config::get().is_system() {
+        apps_dir().join(&app_name).join(original_filename)
+    } else {
+        apps_dir().join(original_filename)
+    };
 
     if dest_path.exists() {
-        ui::warn(&format!("既存ファイルを上書き: {:?}", dest_path));
+        events::warn(format!("既存ファイルを上書き: {:?}", dest_path));
     }
 
-    ui::info(&format!("コピー先: {:?}", dest_path));
-    fs::copy(path, &dest_path)?;
-
-    // 元ファイルを削除（オプション）
-    if options.remove_source {
+    // 本体・リンク・デスクトップエントリをすべて用意してから置き換える
+    let mut transaction = Transaction::begin(&app_name)?;
+    transaction.place_file(path, &dest_path, 0o755)?;
+
+    let link_path = bin_dir().join(&app_name);
+    transaction.place_symlink(&dest_path, &link_path)?;
+
+    let desktop_entry = if options.desktop_entry {
+        Some(create_desktop_entry(
+            &mut transaction,
+            &app_name,
+            &dest_path,
+        )?)
+    } else {
+        None
+    };
+    transaction.commit()?;
+
+    // 元ファイルを削除（オプション、確定後のみ）
+    // 表示モードでは管理者が実行できるようファイルを残す
+    if options.remove_source && !privilege::is_print_mode() {
         fs::remove_file(path)?;
-        ui::info("元ファイルを削除しました");
+        events::emit(Event::FileRemoved {
+            path: path.to_path_buf(),
+        });
     }
 
-    // 実行権限付与
-    let mut perms = fs::metadata(&dest_path)?.permissions();
-    perms.set_mode(0o755);
-    fs::set_permissions(&dest_path, perms)?;
-    ui::info("実行権限を付与");
-
-    // シンボリックリンク作成
-    let bin_directory = bin_dir();
-    if !bin_directory.exists() {
-        fs::create_dir_all(&bin_directory)?;
-    }
-
-    let link_path = bin_directory.join(&app_name);
-    if link_path.exists() || link_path.is_symlink() {
-        fs::remove_file(&link_path)?;
-    }
-
-    ui::info(&format!("シンボリックリンク作成: {:?}", link_path));
-    std::os::unix::fs::symlink(&dest_path, &link_path)?;
-
-    // デスクトップエントリ作成
-    if options.desktop_entry {
-        create_desktop_entry(&app_name, &dest_path)?;
-    }
-
-    ui::success(&format!("AppImage '{}' をインストールしました", app_name));
-    ui::info(&format!("  コマンド: {}", app_name));
-    ui::info(&format!("  場所: {:?}", dest_path));
-
-    Ok(())
+    events::completed(format!(
+        "AppImage '{}' をインストールしました ({})",
+        app_name,
+        dest_path.display()
+    ));
+
+    Ok(InstalledAppImage {
+        name: app_name,
+        path: dest_path,
+        link: link_path,
+        desktop_entry,
+    })
 }
 
 /// デスクトップエントリを作成
-fn create_desktop_entry(app_name: &str, exec_path: &Path) -> Result<()> {
-    let desktop_directory = desktop_dir();
-    if !desktop_directory.exists() {
-        fs::create_dir_all(&desktop_directory)?;
-    }
-
-    let desktop_path = desktop_directory.join(format!("{}.desktop", app_name));
+fn create_desktop_entry(
+    transaction: &mut Transaction,
+    app_name: &str,
+    exec_path: &Path,
+) -> Result<PathBuf> {
+    let desktop_path = desktop_dir().join(format!("{}.desktop", app_name));
 
     // タイトルケースの名前を生成（例: firefox → Firefox）
     let display_name = app_name
@@ -200,15 +209,14 @@ Comment=Installed via AN
         exec_path = exec_path.display()
     );
 
-    fs::write(&desktop_path, desktop_content)?;
-    ui::info(&format!("デスクトップエントリ作成: {:?}", desktop_path));
+    transaction.place_content(&desktop_path, &desktop_content, 0o644)?;
 
-    Ok(())
+    Ok(desktop_path)
 }
 
 /// AppImageを削除
 pub fn remove(identifier: &str) -> Result<()> {
-    ui::info(&format!("AppImageを削除中: {}", identifier));
+    events::step(format!("AppImageを削除中: {}", identifier));
 
     let link_path = bin_dir().join(identifier);
 
@@ -224,23 +232,27 @@ pub fn remove(identifier: &str) -> Result<()> {
     let app_path = fs::read_link(&link_path)?;
 
     // シンボリックリンク削除
-    ui::info(&format!("シンボリックリンク削除: {:?}", link_path));
-    fs::remove_file(&link_path)?;
+    fs_utils::unplace(&link_path)?;
 
     // AppImage実体削除
     if app_path.exists() {
-        ui::info(&format!("ファイル削除: {:?}", app_path));
-        fs::remove_file(&app_path)?;
+        fs_utils::unplace(&app_path)?;
+
+        // システム全体モードのアプリごとのディレクトリを片付け
+        if let Some(parent) = app_path.parent() {
+            if parent != apps_dir() && parent.starts_with(apps_dir()) {
+                fs_utils::unplace_empty_dir(parent)?;
+            }
+        }
     }
 
     // デスクトップエントリ削除（存在すれば）
     let desktop_path = desktop_dir().join(format!("{}.desktop", identifier));
     if desktop_path.exists() {
-        ui::info(&format!("デスクトップエントリ削除: {:?}", desktop_path));
-        fs::remove_file(&desktop_path)?;
+        fs_utils::unplace(&desktop_path)?;
     }
 
-    ui::success("AppImageを削除しました");
+    events::completed("AppImageを削除しました");
     Ok(())
 }
 
@@ -289,6 +301,96 @@ pub fn list_installed() -> Result<Vec<String>> {
     Ok(installed)
 }
 
+/// インストール結果からインストール記録を作成
+pub fn record_for(installed: &InstalledAppImage) -> InstallRecord {
+    let mut record = InstallRecord::new(&installed.name, SourceType::AppImage, &installed.name);
+    record.files = installed.files();
+    record
+}
+
+impl PackageHandler for AppImageHandler {
+    fn source_type(&self) -> SourceType {
+        SourceType::AppImage
+    }
+
+    fn file_extensions(&self) -> &'static [&'static str] {
+        &["appimage"]
+    }
+
+    fn install(&self, path: &Path, options: &InstallOptions) -> Result<Option<InstallRecord>> {
+        let placed = install_with_options(path, options.clone())?;
+        Ok(Some(record_for(&placed)))
+    }
+
+    fn remove(&self, identifier: &str) -> Result<()> {
+        remove(identifier)
+    }
+
+    fn detect(&self, name: &str) -> Option<String> {
+        detect(name)
+    }
+
+    fn list(&self) -> Result<Vec<InstalledPackage>> {
+        let bin_directory = bin_dir();
+        Ok(list_installed()?
+            .into_iter()
+            .map(|name| InstalledPackage {
+                location: fs::read_link(bin_directory.join(&name))
+                    .ok()
+                    .map(|p| p.display().to_string()),
+                identifier: name.clone(),
+                name,
+                source_type: SourceType::AppImage,
+                version: None,
+            })
+            .collect())
+    }
+
+    fn version(&self, identifier: &str) -> Option<String> {
+        installed::load(identifier).ok()??.version
+    }
+
+    fn disk_usage(&self, identifier: &str) -> Option<u64> {
+        // コマンドのシンボリックリンクが指すAppImage本体
+        let target = fs::read_link(bin_dir().join(identifier)).ok()?;
+        Some(fs::metadata(target).ok()?.len())
+    }
+
+    fn upgrade(&self, identifier: &str) -> Result<()> {
+        // AppImageは自己更新の仕組みを持たないため、アプリDBから再取得する
+        let app_config =
+            db::find_by_name(identifier)?.ok_or_else(|| AnError::AppNotInDatabase {
+                name: identifier.to_string(),
+            })?;
+
+        // 複数のソースがある場合もAppImageのソースから取得する
+        let source = app_config
+            .sources()
+            .find(|s| s.source_type == SourceType::AppImage)
+            .ok_or_else(|| AnError::NoSuitableSource {
+                name: identifier.to_string(),
+                message: "AppImage のソースがありません".to_string(),
+            })?;
+        let url = db::app::expand_url(&source.url, &app_config);
+        let downloaded_path = remote::download(&url, &format!("{}.AppImage", identifier))?;
+
+        let options = InstallOptions {
+            name: Some(identifier.to_string()),
+            desktop_entry: desktop_dir()
+                .join(format!("{}.desktop", identifier))
+                .exists(),
+            remove_source: true,
+            via: None,
+            version: None,
+        };
+        let placed = install_with_options(&downloaded_path, options)?;
+
+        let mut record = record_for(&placed);
+        record.version = app_config.metadata.and_then(|m| m.version);
+        installed::save(&record)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -323,6 +425,34 @@ mod tests {
         assert_eq!(extract_app_name(path), "keepassxc");
     }
 
+    #[test]
+    fn test_install_keeps_source_in_print_mode() {
+        let temp = tempfile::tempdir().unwrap();
+        let src = temp.path().join("Tool-1.0.AppImage");
+        fs::write(&src, "dummy").unwrap();
+
+        let mut print = config::Config::default();
+        print.set("privilege.method", "print").unwrap();
+        for (key, dir) in [
+            ("paths.apps_dir", "apps"),
+            ("paths.bin_dir", "bin"),
+            ("paths.state_dir", "state"),
+        ] {
+            print
+                .set(key, temp.path().join(dir).to_str().unwrap())
+                .unwrap();
+        }
+        let options = InstallOptions {
+            remove_source: true,
+            ..Default::default()
+        };
+
+        // 表示した配置コマンドが参照できるよう、元ファイルは削除しない
+        let installed = config::with_config(print, || install_with_options(&src, options)).unwrap();
+        assert!(src.exists());
+        assert_eq!(installed.path, temp.path().join("apps/Tool-1.0.AppImage"));
+    }
+
     #[test]
     fn test_apps_dir() {
         let dir = apps_dir();
@@ -2,32 +2,34 @@
 //!
 //! .debファイルのインストール・削除を処理します。
 
+use super::{InstallOptions, InstalledPackage, PackageHandler};
+use crate::db::app::SourceType;
+use crate::db::installed::{self, InstallRecord};
 use crate::errors::AnError;
-use crate::utils::ui;
+use crate::events;
+use crate::utils::{fs as fs_utils, privilege, process};
 use anyhow::Result;
 use std::path::Path;
-use std::process::Command;
+
+/// Debパッケージ形式のハンドラ
+pub struct DebHandler;
 
 /// .debファイルをインストール
 pub fn install(path: &Path) -> Result<()> {
-    ui::info("Installing Debian package...");
+    events::step("Installing Debian package...");
 
-    // dpkg -i でインストール
-    let output = Command::new("sudo")
-        .args(["dpkg", "-i", path.to_str().unwrap()])
-        .output()?;
+    // dpkg -i でインストール（表示モードでは実行しないため結果を確認しない）
+    let output = privilege::output("dpkg", &["-i", fs_utils::path_str(path)?])?;
 
-    if !output.status.success() {
+    if let Some(output) = output.filter(|o| !o.status.success()) {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        ui::warn(&format!("dpkg warning: {}", stderr));
+        events::warn(format!("dpkg warning: {}", stderr));
 
         // 依存関係エラーの場合、apt -f install で解決
-        ui::info("Resolving dependencies...");
-        let fix_output = Command::new("sudo")
-            .args(["apt", "-f", "install", "-y"])
-            .output()?;
+        events::step("Resolving dependencies...");
+        let fix_output = privilege::output("apt", &["-f", "install", "-y"])?;
 
-        if !fix_output.status.success() {
+        if let Some(fix_output) = fix_output.filter(|o| !o.status.success()) {
             return Err(AnError::DpkgError {
                 message: String::from_utf8_lossy(&fix_output.stderr).to_string(),
             }
@@ -35,20 +37,18 @@ pub fn install(path: &Path) -> Result<()> {
         }
     }
 
-    ui::success("Debian package installed");
+    events::completed("Debian package installed");
     Ok(())
 }
 
 /// Debパッケージを削除（パージ）
 pub fn remove(package: &str) -> Result<()> {
-    ui::info(&format!("Removing package: {}", package));
+    events::step(format!("Removing package: {}", package));
 
     // apt purge で設定ファイルも削除
-    let output = Command::new("sudo")
-        .args(["apt", "purge", "-y", package])
-        .output()?;
+    let output = privilege::output("apt", &["purge", "-y", package])?;
 
-    if !output.status.success() {
+    if let Some(output) = output.filter(|o| !o.status.success()) {
         return Err(AnError::AptPurgeError {
             message: String::from_utf8_lossy(&output.stderr).to_string(),
         }
@@ -56,29 +56,246 @@ pub fn remove(package: &str) -> Result<()> {
     }
 
     // 不要な依存関係を削除
-    ui::info("Removing unused dependencies...");
-    let _ = Command::new("sudo")
-        .args(["apt", "autoremove", "-y"])
-        .output()?;
+    events::step("Removing unused dependencies...");
+    let _ = privilege::output("apt", &["autoremove", "-y"])?;
 
-    ui::success("Package removed");
+    events::completed("Package removed");
     Ok(())
 }
 
+/// .debファイルからパッケージ名を取得
+pub fn package_name(path: &Path) -> Option<String> {
+    let output = process::output("dpkg-deb", &["-f", path.to_str()?, "Package"]).ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
 /// Debパッケージを検出
 pub fn detect(name: &str) -> Option<String> {
-    let output = Command::new("dpkg").args(["-l", name]).output().ok()?;
+    // `dpkg -l` は削除済み・未インストール（un）のパッケージでも成功するため状態を確認する
+    let output = process::output("dpkg-query", &["-W", "-f=${Status}", name]).ok()?;
+    let status = String::from_utf8_lossy(&output.stdout);
+    (output.status.success() && status.trim().ends_with(" installed")).then(|| name.to_string())
+}
 
-    if output.status.success() {
-        // パッケージが見つかった
-        Some(name.to_string())
-    } else {
-        None
+/// インストール済みパッケージのバージョンを取得
+pub fn installed_version(package: &str) -> Option<String> {
+    let output = process::output("dpkg-query", &["-W", "-f=${Version}", package]).ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!version.is_empty()).then_some(version)
+}
+
+/// インストール済みパッケージのサイズ（バイト、dpkgの Installed-Size はKiB単位）
+pub fn installed_size(package: &str) -> Option<u64> {
+    let output = process::output("dpkg-query", &["-W", "-f=${Installed-Size}", package]).ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let kib: u64 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+/// パッケージを最新版に更新
+pub fn upgrade(package: &str) -> Result<()> {
+    events::step(format!("Upgrading package: {}", package));
+    privilege::run("apt", &["install", "--only-upgrade", "-y", package])?;
+    events::completed("Package upgraded");
+    Ok(())
+}
+
+impl PackageHandler for DebHandler {
+    fn source_type(&self) -> SourceType {
+        SourceType::Deb
+    }
+
+    fn file_extensions(&self) -> &'static [&'static str] {
+        &["deb"]
+    }
+
+    fn install(&self, path: &Path, options: &InstallOptions) -> Result<Option<InstallRecord>> {
+        install(path)?;
+        let record = package_name(path)
+            .map(|package| InstallRecord::new(&package, SourceType::Deb, &package));
+
+        // 表示モードでは管理者が実行できるようファイルを残す
+        if options.remove_source && !privilege::is_print_mode() {
+            fs_utils::remove_file(path)?;
+        }
+
+        Ok(record)
+    }
+
+    fn remove(&self, identifier: &str) -> Result<()> {
+        remove(identifier)
+    }
+
+    fn detect(&self, name: &str) -> Option<String> {
+        detect(name)
+    }
+
+    fn list(&self) -> Result<Vec<InstalledPackage>> {
+        // dpkg管理下の全パッケージではなく、anで導入したもの（記録あり）のみ
+        Ok(installed::load_all()?
+            .into_iter()
+            .filter(|r| r.source_type == SourceType::Deb)
+            .map(|r| InstalledPackage {
+                location: Some(r.identifier.clone()),
+                name: r.name,
+                identifier: r.identifier,
+                source_type: SourceType::Deb,
+                version: None,
+            })
+            .collect())
+    }
+
+    fn version(&self, identifier: &str) -> Option<String> {
+        installed_version(identifier)
+    }
+
+    fn disk_usage(&self, identifier: &str) -> Option<u64> {
+        installed_size(identifier)
+    }
+
+    fn upgrade(&self, identifier: &str) -> Result<()> {
+        upgrade(identifier)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    // 統合テストで実際のdpkg操作をテストする
-    // ユニットテストでは構造のみ確認
+    use super::*;
+    use crate::utils::process::fake::{FakeRunner, Reply};
+    use crate::utils::process::with_runner;
+    use std::sync::Arc;
+
+    fn run_with<T>(fake: &Arc<FakeRunner>, f: impl FnOnce() -> T) -> T {
+        with_runner(fake.clone(), f)
+    }
+
+    #[test]
+    fn test_install_success() {
+        let fake = Arc::new(FakeRunner::new());
+        run_with(&fake, || install(Path::new("/tmp/code.deb"))).unwrap();
+
+        assert!(fake.called(&["dpkg", "-i", "/tmp/code.deb"]));
+        assert!(!fake.called(&["apt"]));
+    }
+
+    #[test]
+    fn test_install_falls_back_to_apt() {
+        let fake =
+            Arc::new(FakeRunner::new().on(&["dpkg", "-i"], Reply::fail(1, "dependency problems")));
+        run_with(&fake, || install(Path::new("/tmp/code.deb"))).unwrap();
+
+        assert!(fake.called(&["apt", "-f", "install", "-y"]));
+    }
+
+    #[test]
+    fn test_install_apt_fallback_failure() {
+        let fake = Arc::new(
+            FakeRunner::new()
+                .on(&["dpkg", "-i"], Reply::fail(1, "dependency problems"))
+                .on(&["apt", "-f"], Reply::fail(100, "Unmet dependencies")),
+        );
+        let err = run_with(&fake, || install(Path::new("/tmp/code.deb"))).unwrap_err();
+
+        match err.downcast_ref::<AnError>() {
+            Some(AnError::DpkgError { message }) => assert!(message.contains("Unmet")),
+            other => panic!("Expected DpkgError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_remove_purges_and_autoremoves() {
+        let fake = Arc::new(FakeRunner::new());
+        run_with(&fake, || remove("code")).unwrap();
+
+        assert!(fake.called(&["apt", "purge", "-y", "code"]));
+        assert!(fake.called(&["apt", "autoremove", "-y"]));
+    }
+
+    #[test]
+    fn test_remove_purge_failure() {
+        let fake = Arc::new(FakeRunner::new().on(
+            &["apt", "purge"],
+            Reply::fail(100, "Unable to locate package code"),
+        ));
+        let err = run_with(&fake, || remove("code")).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<AnError>(),
+            Some(AnError::AptPurgeError { .. })
+        ));
+        assert!(!fake.called(&["autoremove"]));
+    }
+
+    #[test]
+    fn test_package_name() {
+        let fake = Arc::new(FakeRunner::new().on(&["dpkg-deb"], Reply::ok("code\n")));
+        let name = run_with(&fake, || package_name(Path::new("/tmp/code.deb")));
+        assert_eq!(name, Some("code".to_string()));
+        assert!(fake.called(&["-f", "/tmp/code.deb", "Package"]));
+
+        let fake = Arc::new(FakeRunner::new().on(&["dpkg-deb"], Reply::fail(2, "not a debian")));
+        assert_eq!(
+            run_with(&fake, || package_name(Path::new("/tmp/x.deb"))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_detect() {
+        let fake = Arc::new(
+            FakeRunner::new()
+                .on(&["code"], Reply::ok("install ok installed"))
+                .on(&["removed"], Reply::ok("deinstall ok config-files"))
+                .on(&["known"], Reply::ok("unknown ok not-installed"))
+                .on(&["missing"], Reply::fail(1, "no packages found")),
+        );
+        assert_eq!(run_with(&fake, || detect("code")), Some("code".to_string()));
+        assert_eq!(run_with(&fake, || detect("removed")), None);
+        assert_eq!(run_with(&fake, || detect("known")), None);
+        assert_eq!(run_with(&fake, || detect("missing")), None);
+        assert!(fake.called(&["dpkg-query", "-W", "-f=${Status}", "code"]));
+    }
+
+    #[test]
+    fn test_installed_version() {
+        let fake = Arc::new(FakeRunner::new().on(&["dpkg-query"], Reply::ok("1.95.3-1731513102")));
+        assert_eq!(
+            run_with(&fake, || installed_version("code")),
+            Some("1.95.3-1731513102".to_string())
+        );
+
+        let fake = Arc::new(FakeRunner::new().on(&["dpkg-query"], Reply::fail(1, "no packages")));
+        assert_eq!(run_with(&fake, || installed_version("code")), None);
+    }
+
+    #[test]
+    fn test_upgrade_failure() {
+        let fake = Arc::new(
+            FakeRunner::new().on(&["--only-upgrade"], Reply::fail(100, "E: not installed")),
+        );
+        let err = run_with(&fake, || upgrade("code")).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<AnError>(),
+            Some(AnError::PrivilegedCommandFailed { .. })
+        ));
+    }
 }
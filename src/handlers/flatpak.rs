@@ -2,13 +2,21 @@
 //!
 //! Flatpakアプリのスキャン・エイリアス生成・削除を処理します。
 
+use super::{InstallOptions, InstalledPackage, PackageHandler};
+use crate::config;
+use crate::db::app::SourceType;
+use crate::db::installed::InstallRecord;
 use crate::errors::AnError;
-use crate::utils::ui;
+use crate::events::{self, Event};
+use crate::utils::privilege::shell_quote;
+use crate::utils::{fs as fs_utils, process};
 use anyhow::Result;
+use std::collections::BTreeMap;
 use std::fs;
-use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::Path;
+
+/// Flatpak形式のハンドラ
+pub struct FlatpakHandler;
 
 /// Flatpakアプリ情報
 #[derive(Debug, Clone)]
@@ -18,28 +26,49 @@ pub struct FlatpakApp {
     pub name: String,
 }
 
-/// シンボリックリンク配置先を取得
-fn bin_dir() -> PathBuf {
-    dirs::home_dir()
-        .expect("ホームディレクトリが見つかりません")
-        .join(".local")
-        .join("bin")
+/// インストール範囲に対応するflatpakのオプション
+///
+/// ユーザー単位モードではflatpakのデフォルト（設定済みのインストール先）に従います。
+pub fn scope_args() -> &'static [&'static str] {
+    if config::get().is_system() {
+        &["--system"]
+    } else {
+        &[]
+    }
+}
+
+/// リモートからアプリ（`ID` または `ID//BRANCH`）をインストール
+pub fn install_from_remote(remote: &str, flatpak_ref: &str) -> Result<()> {
+    let output = process::output(
+        "flatpak",
+        &scoped_args("install", &["-y", remote, flatpak_ref]),
+    )?;
+
+    if !output.status.success() {
+        return Err(AnError::FlatpakInstallError {
+            message: String::from_utf8_lossy(&output.stderr).to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// サブコマンドにインストール範囲のオプションを付けた引数列を構築
+fn scoped_args<'a>(subcommand: &'a str, args: &[&'a str]) -> Vec<&'a str> {
+    std::iter::once(subcommand)
+        .chain(scope_args().iter().copied())
+        .chain(args.iter().copied())
+        .collect()
 }
 
 /// Flatpakがインストールされているか確認
 pub fn is_installed() -> bool {
-    Command::new("flatpak")
-        .arg("--version")
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+    process::succeeds("flatpak", &["--version"])
 }
 
 /// インストール済みFlatpakアプリをスキャン
 pub fn scan_apps() -> Result<Vec<FlatpakApp>> {
-    let output = Command::new("flatpak")
-        .args(["list", "--app", "--columns=application,name"])
-        .output()?;
+    let output = process::output("flatpak", &["list", "--app", "--columns=application,name"])?;
 
     if !output.status.success() {
         return Err(AnError::FlatpakNotInstalled.into());
@@ -75,40 +104,257 @@ pub fn check_name_conflict(name: &str) -> bool {
     which::which(name).is_ok()
 }
 
-/// ラッパースクリプトを生成
-pub fn create_wrapper(app_id: &str, name: &str) -> Result<()> {
-    let bin_directory = bin_dir();
-    if !bin_directory.exists() {
-        fs::create_dir_all(&bin_directory)?;
+/// ラッパーテンプレートのバージョン
+///
+/// テンプレートを変更した場合はこの値を上げること。
+/// 古いバージョンのラッパーは `upgrade_wrappers` で再生成されます。
+pub const WRAPPER_VERSION: u32 = 3;
+
+/// ラッパー識別用マーカー
+const WRAPPER_MARKER: &str = "# AN-generated wrapper for ";
+
+/// ファイル転送する引数の振り分け（存在するパスは `@@`、URIは `@@u`、それ以外はそのまま）
+const FORWARD_ARGS: &str = r#"args=()
+for arg in "$@"; do
+    if [[ -e $arg ]]; then
+        args+=(@@ "$arg" @@)
+    elif [[ $arg =~ ^[A-Za-z][A-Za-z0-9+.-]*: ]]; then
+        args+=(@@u "$arg" @@)
+    else
+        args+=("$arg")
+    fi
+done
+"#;
+
+/// ラッパー生成オプション
+#[derive(Debug, Clone, PartialEq)]
+pub struct WrapperOptions {
+    /// 実行するコマンド（`--command=`）
+    pub command: Option<String>,
+    /// ブランチ（`--branch=`）
+    pub branch: Option<String>,
+    /// サンドボックス内に渡す環境変数（`--env=`）
+    pub env: BTreeMap<String, String>,
+    /// ファイル引数をサンドボックスへ転送するか（`--file-forwarding`）
+    pub file_forwarding: bool,
+}
+
+impl Default for WrapperOptions {
+    fn default() -> Self {
+        Self {
+            command: None,
+            branch: None,
+            env: BTreeMap::new(),
+            file_forwarding: true,
+        }
+    }
+}
+
+/// ヘッダーコメントに書く値をエスケープ（改行を含む値でも1行に収める）
+fn escape_header(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('\n', r"\n")
+        .replace('\r', r"\r")
+}
+
+/// [`escape_header`] でエスケープした値を元に戻す
+fn unescape_header(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// ラッパースクリプトの内容を生成
+///
+/// オプションはヘッダーコメントにも記録され、再生成時に復元されます。
+pub fn render_wrapper(app_id: &str, options: &WrapperOptions) -> String {
+    let mut script = String::from("#!/bin/bash\n");
+    script.push_str(&format!("{}{}\n", WRAPPER_MARKER, app_id));
+    script.push_str(&format!("# AN-wrapper-version: {}\n", WRAPPER_VERSION));
+    if let Some(ref branch) = options.branch {
+        script.push_str(&format!("# AN-wrapper-branch: {}\n", escape_header(branch)));
+    }
+    if let Some(ref command) = options.command {
+        script.push_str(&format!(
+            "# AN-wrapper-command: {}\n",
+            escape_header(command)
+        ));
+    }
+    for (key, value) in &options.env {
+        script.push_str(&format!(
+            "# AN-wrapper-env: {}={}\n",
+            escape_header(key),
+            escape_header(value)
+        ));
+    }
+    script.push_str(&format!(
+        "# AN-wrapper-file-forwarding: {}\n",
+        options.file_forwarding
+    ));
+
+    let mut args = vec!["exec".to_string(), "flatpak".to_string(), "run".to_string()];
+    if let Some(ref branch) = options.branch {
+        args.push(shell_quote(&format!("--branch={}", branch)));
+    }
+    if let Some(ref command) = options.command {
+        args.push(shell_quote(&format!("--command={}", command)));
+    }
+    for (key, value) in &options.env {
+        args.push(shell_quote(&format!("--env={}={}", key, value)));
+    }
+    if options.file_forwarding {
+        script.push_str(FORWARD_ARGS);
+        args.push("--file-forwarding".to_string());
+        args.push(shell_quote(app_id));
+        args.push(r#""${args[@]}""#.to_string());
+    } else {
+        args.push(shell_quote(app_id));
+        args.push(r#""$@""#.to_string());
+    }
+
+    script.push_str(&args.join(" "));
+    script.push('\n');
+    script
+}
+
+/// AN生成ラッパーの解析結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedWrapper {
+    pub app_id: String,
+    pub version: u32,
+    pub options: WrapperOptions,
+}
+
+/// ラッパースクリプトを解析
+///
+/// AN生成でないスクリプトの場合は `None` を返します。
+/// バージョン行のない旧形式（v1）はデフォルトオプションとして扱います。
+/// 解釈できないヘッダー行は無視します（次回の再生成で書き直されます）。
+pub fn parse_wrapper(content: &str) -> Option<ParsedWrapper> {
+    let mut app_id = None;
+    let mut version = 1;
+    let mut options = WrapperOptions::default();
+
+    for line in content.lines() {
+        if let Some(id) = line.strip_prefix(WRAPPER_MARKER) {
+            app_id = Some(id.trim().to_string());
+        } else if let Some(v) = line.strip_prefix("# AN-wrapper-version: ") {
+            if let Ok(v) = v.trim().parse() {
+                version = v;
+            }
+        } else if let Some(branch) = line.strip_prefix("# AN-wrapper-branch: ") {
+            options.branch = Some(unescape_header(branch));
+        } else if let Some(command) = line.strip_prefix("# AN-wrapper-command: ") {
+            options.command = Some(unescape_header(command));
+        } else if let Some(env) = line.strip_prefix("# AN-wrapper-env: ") {
+            if let Some((key, value)) = env.split_once('=') {
+                options
+                    .env
+                    .insert(unescape_header(key), unescape_header(value));
+            }
+        } else if let Some(ff) = line.strip_prefix("# AN-wrapper-file-forwarding: ") {
+            options.file_forwarding = ff.trim() == "true";
+        }
     }
 
-    let wrapper_path = bin_directory.join(name);
+    app_id.map(|app_id| ParsedWrapper {
+        app_id,
+        version,
+        options,
+    })
+}
 
-    let script = format!(
-        r#"#!/bin/bash
-# AN-generated wrapper for {}
-exec flatpak run {} "$@"
-"#,
-        app_id, app_id
-    );
+/// 既存のAN生成ラッパーを読み込む
+pub fn read_wrapper(name: &str) -> Option<ParsedWrapper> {
+    let content = fs::read_to_string(config::get().bin_dir().join(name)).ok()?;
+    parse_wrapper(&content)
+}
 
-    fs::write(&wrapper_path, script)?;
+/// ラッパースクリプトを生成
+pub fn create_wrapper(app_id: &str, name: &str) -> Result<()> {
+    create_wrapper_with_options(app_id, name, &WrapperOptions::default())
+}
 
-    // 実行権限付与
-    let mut perms = fs::metadata(&wrapper_path)?.permissions();
-    perms.set_mode(0o755);
-    fs::set_permissions(&wrapper_path, perms)?;
+/// オプション付きでラッパースクリプトを生成
+pub fn create_wrapper_with_options(
+    app_id: &str,
+    name: &str,
+    options: &WrapperOptions,
+) -> Result<()> {
+    write_wrapper(&config::get().bin_dir().join(name), app_id, options)
+}
 
+/// ラッパースクリプトを書き込み、実行権限を付与
+fn write_wrapper(wrapper_path: &Path, app_id: &str, options: &WrapperOptions) -> Result<()> {
+    fs_utils::place_content(wrapper_path, &render_wrapper(app_id, options), 0o755)?;
+    events::emit(Event::WrapperWritten {
+        name: wrapper_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        app_id: app_id.to_string(),
+    });
     Ok(())
 }
 
+/// 旧テンプレートで生成されたラッパーを再生成
+///
+/// 再生成したラッパー数を返します。
+pub fn upgrade_wrappers() -> Result<usize> {
+    upgrade_wrappers_in(&config::get().bin_dir())
+}
+
+/// 指定ディレクトリ内の旧テンプレートのラッパーを再生成
+fn upgrade_wrappers_in(dir: &Path) -> Result<usize> {
+    let mut upgraded = 0;
+
+    if !dir.exists() {
+        return Ok(upgraded);
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() || path.is_symlink() {
+            continue;
+        }
+
+        // バイナリ等の読めないファイルは無視
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+
+        if let Some(parsed) = parse_wrapper(&content) {
+            if parsed.version < WRAPPER_VERSION {
+                write_wrapper(&path, &parsed.app_id, &parsed.options)?;
+                upgraded += 1;
+            }
+        }
+    }
+
+    Ok(upgraded)
+}
+
 /// .flatpakref ファイルからインストール
 pub fn install_from_ref(ref_file: &std::path::Path) -> Result<()> {
-    ui::info("Flatpakref ファイルからインストール中...");
+    events::step("Flatpakref ファイルからインストール中...");
 
-    let output = Command::new("flatpak")
-        .args(["install", "-y", ref_file.to_str().unwrap()])
-        .output()?;
+    let output = process::output(
+        "flatpak",
+        &scoped_args("install", &["-y", fs_utils::path_str(ref_file)?]),
+    )?;
 
     if !output.status.success() {
         return Err(AnError::FlatpakInstallError {
@@ -117,20 +363,25 @@ pub fn install_from_ref(ref_file: &std::path::Path) -> Result<()> {
         .into());
     }
 
-    ui::success("Flatpak アプリをインストールしました");
+    events::completed("Flatpak アプリをインストールしました");
 
     // インストール後、新しくインストールされたアプリのエイリアスを作成
-    ui::info("エイリアスを作成中...");
+    events::step("エイリアスを作成中...");
 
     // 全Flatpakアプリをスキャンして、新しいアプリのエイリアスを作成
     let apps = scan_apps()?;
     let mut created = 0;
 
     for app in apps {
-        let name = app.id.split('.').next_back().unwrap_or(&app.id).to_lowercase();
+        let name = app
+            .id
+            .split('.')
+            .next_back()
+            .unwrap_or(&app.id)
+            .to_lowercase();
 
         // 既存のラッパーがあればスキップ
-        let wrapper_path = bin_dir().join(&name);
+        let wrapper_path = config::get().bin_dir().join(&name);
         if wrapper_path.exists() {
             continue;
         }
@@ -142,12 +393,11 @@ pub fn install_from_ref(ref_file: &std::path::Path) -> Result<()> {
 
         // ラッパー作成
         create_wrapper(&app.id, &name)?;
-        ui::info(&format!("  {} → {}", name, app.id));
         created += 1;
     }
 
     if created > 0 {
-        ui::success(&format!("エイリアス作成: {} 件", created));
+        events::completed(format!("エイリアス作成: {} 件", created));
     }
 
     Ok(())
@@ -155,11 +405,12 @@ pub fn install_from_ref(ref_file: &std::path::Path) -> Result<()> {
 
 /// Flatpakアプリを削除
 pub fn remove(app_id: &str) -> Result<()> {
-    ui::info(&format!("Removing Flatpak app: {}", app_id));
+    events::step(format!("Removing Flatpak app: {}", app_id));
 
-    let output = Command::new("flatpak")
-        .args(["uninstall", "--delete-data", "-y", app_id])
-        .output()?;
+    let output = process::output(
+        "flatpak",
+        &scoped_args("uninstall", &["--delete-data", "-y", app_id]),
+    )?;
 
     if !output.status.success() {
         return Err(AnError::FlatpakUninstallError {
@@ -175,27 +426,18 @@ pub fn remove(app_id: &str) -> Result<()> {
         .next_back()
         .unwrap_or(app_id)
         .to_lowercase();
-    let wrapper_path = bin_dir().join(&name_guess);
-    if wrapper_path.exists() {
-        // ANが生成したラッパーか確認
-        if let Ok(content) = fs::read_to_string(&wrapper_path) {
-            if content.contains("AN-generated wrapper") && content.contains(app_id) {
-                ui::info(&format!("Removing wrapper: {:?}", wrapper_path));
-                let _ = fs::remove_file(wrapper_path);
-            }
-        }
+    if read_wrapper(&name_guess).is_some_and(|w| w.app_id == app_id) {
+        let wrapper_path = config::get().bin_dir().join(&name_guess);
+        let _ = fs_utils::unplace(&wrapper_path);
     }
 
-    ui::success("Flatpak app removed");
+    events::completed("Flatpak app removed");
     Ok(())
 }
 
 /// Flatpakアプリを検出
 pub fn detect(name: &str) -> Option<String> {
-    let output = Command::new("flatpak")
-        .args(["list", "--app", "--columns=application"])
-        .output()
-        .ok()?;
+    let output = process::output("flatpak", &["list", "--app", "--columns=application"]).ok()?;
 
     if !output.status.success() {
         return None;
@@ -220,15 +462,418 @@ pub fn detect(name: &str) -> Option<String> {
     None
 }
 
+/// `flatpak list --columns=application,version` の出力からバージョンを取得
+fn find_version(listing: &str, app_id: &str) -> Option<String> {
+    listing.lines().find_map(|line| {
+        let mut columns = line.split('\t');
+        if columns.next()?.trim() != app_id {
+            return None;
+        }
+        let version = columns.next()?.trim();
+        (!version.is_empty()).then(|| version.to_string())
+    })
+}
+
+/// インストール済みFlatpakアプリのバージョンを取得
+pub fn installed_version(app_id: &str) -> Option<String> {
+    let output = process::output(
+        "flatpak",
+        &["list", "--app", "--columns=application,version"],
+    )
+    .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    find_version(&String::from_utf8_lossy(&output.stdout), app_id)
+}
+
+/// インストール済みFlatpakアプリの配置先の合計サイズ（バイト）
+///
+/// ランタイムなど他のアプリと共有するものは含みません。
+pub fn installed_size(app_id: &str) -> Option<u64> {
+    let output = process::output(
+        "flatpak",
+        &scoped_args("info", &["--show-location", app_id]),
+    )
+    .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let location = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!location.is_empty()).then(|| fs_utils::disk_usage(Path::new(&location)))
+}
+
+/// Flatpakアプリを更新
+pub fn upgrade(app_id: &str) -> Result<()> {
+    events::step(format!("Updating Flatpak app: {}", app_id));
+
+    let output = process::output("flatpak", &scoped_args("update", &["-y", app_id]))?;
+
+    if !output.status.success() {
+        return Err(AnError::FlatpakInstallError {
+            message: String::from_utf8_lossy(&output.stderr).to_string(),
+        }
+        .into());
+    }
+
+    events::completed("Flatpak app updated");
+    Ok(())
+}
+
+impl PackageHandler for FlatpakHandler {
+    fn source_type(&self) -> SourceType {
+        SourceType::Flatpak
+    }
+
+    fn file_extensions(&self) -> &'static [&'static str] {
+        &["flatpakref"]
+    }
+
+    fn is_available(&self) -> bool {
+        is_installed()
+    }
+
+    fn install(&self, path: &Path, _options: &InstallOptions) -> Result<Option<InstallRecord>> {
+        // .flatpakref は複数アプリを導入しうるため記録しない
+        install_from_ref(path)?;
+        Ok(None)
+    }
+
+    fn remove(&self, identifier: &str) -> Result<()> {
+        remove(identifier)
+    }
+
+    fn detect(&self, name: &str) -> Option<String> {
+        detect(name)
+    }
+
+    fn list(&self) -> Result<Vec<InstalledPackage>> {
+        Ok(scan_apps()?
+            .into_iter()
+            .map(|app| InstalledPackage {
+                name: app.id.split('.').next_back().unwrap_or(&app.id).to_string(),
+                location: Some(app.id.clone()),
+                identifier: app.id,
+                source_type: SourceType::Flatpak,
+                version: None,
+            })
+            .collect())
+    }
+
+    fn version(&self, identifier: &str) -> Option<String> {
+        installed_version(identifier)
+    }
+
+    fn disk_usage(&self, identifier: &str) -> Option<u64> {
+        installed_size(identifier)
+    }
+
+    fn upgrade(&self, identifier: &str) -> Result<()> {
+        upgrade(identifier)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::process::fake::{FakeRunner, Reply};
+    use crate::utils::process::with_runner;
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::Arc;
+
+    const LISTING: &str = "org.gimp.GIMP\tGNU Image Manipulation Program\norg.telegram.desktop\n\n";
+
+    #[test]
+    fn test_is_installed() {
+        let fake = Arc::new(FakeRunner::new());
+        assert!(with_runner(fake, is_installed));
+
+        let fake = Arc::new(FakeRunner::new().on(&["--version"], Reply::fail(127, "")));
+        assert!(!with_runner(fake, is_installed));
+    }
+
+    #[test]
+    fn test_scan_apps_parses_listing() {
+        let fake = Arc::new(FakeRunner::new().on(&["list"], Reply::ok(LISTING)));
+        let apps = with_runner(fake, scan_apps).unwrap();
+
+        assert_eq!(apps.len(), 2);
+        assert_eq!(apps[0].id, "org.gimp.GIMP");
+        assert_eq!(apps[0].name, "GNU Image Manipulation Program");
+        // 表示名の列がない行はIDを名前として扱う
+        assert_eq!(apps[1].id, "org.telegram.desktop");
+        assert_eq!(apps[1].name, "org.telegram.desktop");
+    }
+
+    #[test]
+    fn test_scan_apps_failure() {
+        let fake = Arc::new(FakeRunner::new().on(&["list"], Reply::fail(1, "")));
+        let err = with_runner(fake, scan_apps).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<AnError>(),
+            Some(AnError::FlatpakNotInstalled)
+        ));
+    }
+
+    #[test]
+    fn test_detect_by_id_or_last_segment() {
+        let fake = Arc::new(FakeRunner::new().on(&["list"], Reply::ok("org.gimp.GIMP\n")));
+
+        assert_eq!(
+            with_runner(fake.clone(), || detect("gimp")),
+            Some("org.gimp.GIMP".to_string())
+        );
+        assert_eq!(
+            with_runner(fake.clone(), || detect("org.gimp.gimp")),
+            Some("org.gimp.GIMP".to_string())
+        );
+        assert_eq!(with_runner(fake, || detect("inkscape")), None);
+    }
+
+    #[test]
+    fn test_remove_failure() {
+        let fake = Arc::new(FakeRunner::new().on(
+            &["uninstall"],
+            Reply::fail(1, "error: org.gimp.GIMP/*unspecified*/* not installed"),
+        ));
+        let err = with_runner(fake.clone(), || remove("org.gimp.GIMP")).unwrap_err();
+
+        match err.downcast_ref::<AnError>() {
+            Some(AnError::FlatpakUninstallError { message }) => {
+                assert!(message.contains("not installed"))
+            }
+            other => panic!("Expected FlatpakUninstallError, got {:?}", other),
+        }
+        assert!(fake.called(&["uninstall", "--delete-data", "-y", "org.gimp.GIMP"]));
+    }
+
+    #[test]
+    fn test_install_from_remote_scope() {
+        let fake = Arc::new(FakeRunner::new());
+        with_runner(fake.clone(), || {
+            install_from_remote("flathub", "org.gimp.GIMP//beta").unwrap()
+        });
+        assert_eq!(
+            fake.calls(),
+            vec!["flatpak install -y flathub org.gimp.GIMP//beta"]
+        );
+
+        // システム全体モードでは --system を付ける
+        let fake = Arc::new(FakeRunner::new());
+        let system = config::Config {
+            scope: config::Scope::System,
+            ..Default::default()
+        };
+        config::with_config(system, || {
+            with_runner(fake.clone(), || {
+                install_from_remote("flathub", "org.gimp.GIMP").unwrap()
+            })
+        });
+        assert_eq!(
+            fake.calls(),
+            vec!["flatpak install --system -y flathub org.gimp.GIMP"]
+        );
+    }
+
+    #[test]
+    fn test_install_from_ref_failure() {
+        let fake = Arc::new(FakeRunner::new().on(&["install"], Reply::fail(1, "remote not found")));
+        let err =
+            with_runner(fake, || install_from_ref(Path::new("/tmp/app.flatpakref"))).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<AnError>(),
+            Some(AnError::FlatpakInstallError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_installed_version() {
+        let fake = Arc::new(FakeRunner::new().on(
+            &["--columns=application,version"],
+            Reply::ok("org.gimp.GIMP\t2.10.38\n"),
+        ));
+        assert_eq!(
+            with_runner(fake, || installed_version("org.gimp.GIMP")),
+            Some("2.10.38".to_string())
+        );
+    }
+
+    #[test]
+    fn test_upgrade_failure() {
+        let fake = Arc::new(FakeRunner::new().on(&["update"], Reply::fail(1, "no remote")));
+        let err = with_runner(fake.clone(), || upgrade("org.gimp.GIMP")).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<AnError>(),
+            Some(AnError::FlatpakInstallError { .. })
+        ));
+        assert!(fake.called(&["update", "-y", "org.gimp.GIMP"]));
+    }
+
+    #[test]
+    fn test_find_version() {
+        let listing = "org.mozilla.firefox\t128.0\ncom.spotify.Client\t\n";
+        assert_eq!(
+            find_version(listing, "org.mozilla.firefox"),
+            Some("128.0".to_string())
+        );
+        assert_eq!(find_version(listing, "com.spotify.Client"), None);
+        assert_eq!(find_version(listing, "org.gimp.GIMP"), None);
+    }
+
+    #[test]
+    fn test_render_wrapper_default() {
+        let script = render_wrapper("org.gimp.GIMP", &WrapperOptions::default());
+        assert!(script.starts_with("#!/bin/bash\n# AN-generated wrapper for org.gimp.GIMP\n"));
+        assert!(script.contains(&format!("# AN-wrapper-version: {}", WRAPPER_VERSION)));
+        assert!(script.contains(FORWARD_ARGS));
+        assert!(
+            script.ends_with("exec flatpak run --file-forwarding org.gimp.GIMP \"${args[@]}\"\n")
+        );
+    }
+
+    #[test]
+    fn test_wrapper_forwards_only_paths_and_uris() {
+        let temp = tempfile::tempdir().unwrap();
+        // 受け取った引数を1行ずつ出力する flatpak
+        let flatpak = temp.path().join("flatpak");
+        fs::write(&flatpak, "#!/bin/sh\nprintf '%s\\n' \"$@\"\n").unwrap();
+        fs::set_permissions(&flatpak, fs::Permissions::from_mode(0o755)).unwrap();
+        let file = temp.path().join("photo 1.png");
+        fs::write(&file, "").unwrap();
+        let wrapper = temp.path().join("gimp");
+        fs::write(
+            &wrapper,
+            render_wrapper("org.gimp.GIMP", &WrapperOptions::default()),
+        )
+        .unwrap();
+
+        let output = std::process::Command::new("bash")
+            .arg(&wrapper)
+            .args(["--new-window", "https://example.com/a.png"])
+            .arg(&file)
+            .env(
+                "PATH",
+                format!(
+                    "{}:{}",
+                    temp.path().display(),
+                    std::env::var("PATH").unwrap_or_default()
+                ),
+            )
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            format!(
+                "run\n--file-forwarding\norg.gimp.GIMP\n--new-window\n@@u\nhttps://example.com/a.png\n@@\n@@\n{}\n@@\n",
+                file.display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_wrapper_with_options() {
+        let mut options = WrapperOptions {
+            command: Some("gimp-2.10".to_string()),
+            branch: Some("beta".to_string()),
+            file_forwarding: false,
+            ..Default::default()
+        };
+        options
+            .env
+            .insert("GTK_THEME".to_string(), "Adwaita dark".to_string());
+
+        let script = render_wrapper("org.gimp.GIMP", &options);
+        assert!(script.ends_with(
+            "exec flatpak run --branch=beta --command=gimp-2.10 '--env=GTK_THEME=Adwaita dark' org.gimp.GIMP \"$@\"\n"
+        ));
+    }
+
+    #[test]
+    fn test_parse_wrapper_roundtrip() {
+        let mut options = WrapperOptions {
+            branch: Some("stable".to_string()),
+            ..Default::default()
+        };
+        options.env.insert("FOO".to_string(), "a=b".to_string());
+
+        let parsed = parse_wrapper(&render_wrapper("org.example.App", &options)).unwrap();
+        assert_eq!(parsed.app_id, "org.example.App");
+        assert_eq!(parsed.version, WRAPPER_VERSION);
+        assert_eq!(parsed.options, options);
+    }
 
     #[test]
-    fn test_bin_dir() {
-        let dir = bin_dir();
-        assert!(dir.ends_with(".local/bin"));
+    fn test_parse_wrapper_roundtrip_multiline_values() {
+        let mut options = WrapperOptions {
+            command: Some("sh -c 'a\nb'".to_string()),
+            ..Default::default()
+        };
+        options
+            .env
+            .insert("MULTI".to_string(), "line1\nline2\\n\r".to_string());
+
+        let script = render_wrapper("org.example.App", &options);
+        // 改行を含む値でもヘッダーは1行
+        assert!(script.contains("# AN-wrapper-env: MULTI=line1\\nline2\\\\n\\r\n"));
+        let parsed = parse_wrapper(&script).unwrap();
+        assert_eq!(parsed.options, options);
     }
 
-    // is_installed, scan_apps, remove などは統合テストで実行
+    #[test]
+    fn test_parse_wrapper_legacy() {
+        let legacy = "#!/bin/bash\n# AN-generated wrapper for org.gimp.GIMP\nexec flatpak run org.gimp.GIMP \"$@\"\n";
+        let parsed = parse_wrapper(legacy).unwrap();
+        assert_eq!(parsed.app_id, "org.gimp.GIMP");
+        assert_eq!(parsed.version, 1);
+        assert_eq!(parsed.options, WrapperOptions::default());
+    }
+
+    #[test]
+    fn test_parse_wrapper_skips_invalid_headers() {
+        let script = "#!/bin/bash\n# AN-generated wrapper for org.gimp.GIMP\n# AN-wrapper-version: x\n# AN-wrapper-env: BROKEN\n# AN-wrapper-env: FOO=bar\n# AN-wrapper-file-forwarding: true\n";
+        let parsed = parse_wrapper(script).unwrap();
+        assert_eq!(parsed.app_id, "org.gimp.GIMP");
+        assert_eq!(parsed.version, 1);
+        assert_eq!(
+            parsed.options.env.into_iter().collect::<Vec<_>>(),
+            [("FOO".to_string(), "bar".to_string())]
+        );
+        assert!(parsed.options.file_forwarding);
+    }
+
+    #[test]
+    fn test_parse_wrapper_foreign_script() {
+        assert!(parse_wrapper("#!/bin/sh\necho hello\n").is_none());
+    }
+
+    #[test]
+    fn test_upgrade_wrappers_in() {
+        let temp = tempfile::tempdir().unwrap();
+        let legacy = temp.path().join("gimp");
+        fs::write(
+            &legacy,
+            "#!/bin/bash\n# AN-generated wrapper for org.gimp.GIMP\nexec flatpak run org.gimp.GIMP \"$@\"\n",
+        )
+        .unwrap();
+        let current = temp.path().join("firefox");
+        write_wrapper(&current, "org.mozilla.firefox", &WrapperOptions::default()).unwrap();
+        fs::write(temp.path().join("other"), "#!/bin/sh\necho hi\n").unwrap();
+
+        assert_eq!(upgrade_wrappers_in(temp.path()).unwrap(), 1);
+        let upgraded = fs::read_to_string(&legacy).unwrap();
+        assert!(upgraded.contains("--file-forwarding org.gimp.GIMP"));
+        assert_eq!(upgrade_wrappers_in(temp.path()).unwrap(), 0);
+    }
 }
@@ -1,8 +1,178 @@
 //! ハンドラモジュール
 //!
 //! 各パッケージ形式の処理を提供します。
+//!
+//! 形式ごとの処理は [`PackageHandler`] トレイトとして実装され、
+//! [`registry`] から形式を意識せずに利用できます。
+//! 新しい形式を追加する場合はトレイトを実装して `HANDLERS` に登録します。
 
 pub mod appimage;
 pub mod deb;
 pub mod flatpak;
 pub mod remote;
+
+use crate::db::app::SourceType;
+use crate::db::installed::InstallRecord;
+use crate::errors::AnError;
+use anyhow::Result;
+use std::path::Path;
+
+/// 登録済みハンドラ（検出の優先順）
+static HANDLERS: &[&dyn PackageHandler] = &[
+    &appimage::AppImageHandler,
+    &deb::DebHandler,
+    &flatpak::FlatpakHandler,
+];
+
+/// インストールオプション
+#[derive(Debug, Clone, Default)]
+pub struct InstallOptions {
+    /// カスタムコマンド名（指定しない場合は自動抽出）
+    pub name: Option<String>,
+    /// デスクトップエントリを作成するか
+    pub desktop_entry: bool,
+    /// 元ファイルを削除するか（moveモード）
+    pub remove_source: bool,
+    /// アプリDBからのインストールで使うソースの形式（指定しない場合は優先順に試す）
+    pub via: Option<SourceType>,
+    /// アプリDBからのインストールで使うバージョン（URLの `{version}` を置き換える）
+    pub version: Option<String>,
+}
+
+/// インストール済みパッケージ情報
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstalledPackage {
+    /// コマンド名
+    pub name: String,
+    /// 形式ごとの識別子（パッケージ名、Flatpak ID、コマンド名）
+    pub identifier: String,
+    /// インストール形式
+    pub source_type: SourceType,
+    /// 配置先パスなどの補足情報
+    pub location: Option<String>,
+    /// インストール済みバージョン（判明している場合）
+    pub version: Option<String>,
+}
+
+/// パッケージ形式ごとのハンドラ
+pub trait PackageHandler: Sync {
+    /// 対応するインストール形式
+    fn source_type(&self) -> SourceType;
+
+    /// 対応するローカルファイルの拡張子（小文字）
+    fn file_extensions(&self) -> &'static [&'static str];
+
+    /// この形式を扱うツールが利用可能か
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    /// ローカルファイルからインストール
+    ///
+    /// 記録すべき情報が判明した場合はインストール記録を返します。
+    fn install(&self, path: &Path, options: &InstallOptions) -> Result<Option<InstallRecord>>;
+
+    /// 識別子を指定して削除
+    fn remove(&self, identifier: &str) -> Result<()>;
+
+    /// 名前からインストール済みパッケージを検出し、識別子を返す
+    fn detect(&self, name: &str) -> Option<String>;
+
+    /// インストール済みパッケージ一覧
+    fn list(&self) -> Result<Vec<InstalledPackage>>;
+
+    /// インストール済みバージョン
+    fn version(&self, identifier: &str) -> Option<String>;
+
+    /// ディスク使用量（バイト、判明している場合）
+    fn disk_usage(&self, identifier: &str) -> Option<u64>;
+
+    /// 最新版に更新
+    #[allow(dead_code)]
+    fn upgrade(&self, identifier: &str) -> Result<()>;
+}
+
+/// 登録済みハンドラ一覧
+pub fn registry() -> &'static [&'static dyn PackageHandler] {
+    HANDLERS
+}
+
+/// インストール形式に対応するハンドラを取得
+pub fn for_type(source_type: SourceType) -> Option<&'static dyn PackageHandler> {
+    HANDLERS
+        .iter()
+        .copied()
+        .find(|h| h.source_type() == source_type)
+}
+
+/// ファイルの拡張子に対応するハンドラを取得
+pub fn for_file(path: &Path) -> Result<&'static dyn PackageHandler, AnError> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+
+    let Some(extension) = extension else {
+        return Err(AnError::UnknownFileType {
+            extension: "なし".to_string(),
+        });
+    };
+
+    HANDLERS
+        .iter()
+        .copied()
+        .find(|h| h.file_extensions().contains(&extension.as_str()))
+        .ok_or(AnError::UnknownFileType { extension })
+}
+
+/// 名前からインストール済みパッケージを検出
+pub fn detect(name: &str) -> Option<(&'static dyn PackageHandler, String)> {
+    HANDLERS
+        .iter()
+        .copied()
+        .find_map(|h| h.detect(name).map(|identifier| (h, identifier)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_covers_package_types() {
+        let types: Vec<_> = registry().iter().map(|h| h.source_type()).collect();
+        assert_eq!(
+            types,
+            vec![SourceType::AppImage, SourceType::Deb, SourceType::Flatpak]
+        );
+    }
+
+    #[test]
+    fn test_for_type() {
+        assert_eq!(
+            for_type(SourceType::Deb).unwrap().source_type(),
+            SourceType::Deb
+        );
+        assert!(for_type(SourceType::Script).is_none());
+    }
+
+    #[test]
+    fn test_for_file() {
+        let handler = for_file(Path::new("/tmp/App-1.0.AppImage")).unwrap();
+        assert_eq!(handler.source_type(), SourceType::AppImage);
+        let handler = for_file(Path::new("app.appimage")).unwrap();
+        assert_eq!(handler.source_type(), SourceType::AppImage);
+        let handler = for_file(Path::new("package.deb")).unwrap();
+        assert_eq!(handler.source_type(), SourceType::Deb);
+        let handler = for_file(Path::new("/tmp/app.flatpakref")).unwrap();
+        assert_eq!(handler.source_type(), SourceType::Flatpak);
+    }
+
+    #[test]
+    fn test_for_file_unknown() {
+        match for_file(Path::new("/tmp/file.xyz")) {
+            Err(AnError::UnknownFileType { extension }) => assert_eq!(extension, "xyz"),
+            _ => panic!("Expected UnknownFileType error"),
+        }
+        assert!(for_file(Path::new("/tmp/file")).is_err());
+    }
+}
@@ -1,73 +1,462 @@
 //! リモートダウンロードハンドラ
 //!
 //! URLからファイルをダウンロードする処理を提供します。
+//! 複数のアプリを扱う操作では [`download_all`] で並列にダウンロードします。
 
 use crate::errors::AnError;
-use crate::utils::ui;
+use crate::events::{self, Event};
+use crate::utils::{process, temp};
 use anyhow::Result;
+use reqwest::blocking::Response;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
 
-/// ダウンロード先一時ディレクトリを取得
-fn temp_dir() -> PathBuf {
-    std::env::temp_dir().join("an-downloads")
+/// ダウンロード先一時ディレクトリを取得（プロセスごと）
+fn temp_dir() -> Result<PathBuf> {
+    Ok(temp::dir()?.join("downloads"))
+}
+
+/// サイズ不明時に進捗を通知する間隔（バイト）
+const PROGRESS_INTERVAL: u64 = 1024 * 1024;
+
+/// 並列ダウンロードの対象
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Download {
+    /// 表示名（アプリ名）
+    pub name: String,
+    /// ダウンロード元
+    pub url: String,
+    /// 一時ディレクトリに保存するファイル名
+    pub filename: String,
+}
+
+/// 並列ダウンロードのワーカーからの通知
+enum Message {
+    Progress {
+        index: usize,
+        downloaded: u64,
+        total: u64,
+    },
+    Finished {
+        index: usize,
+        result: Result<PathBuf>,
+    },
+}
+
+/// 条件付きリクエストの検証子
+///
+/// 前回の応答の `ETag` / `Last-Modified` を保存しておき、
+/// 次回 `If-None-Match` / `If-Modified-Since` として送ります。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    fn from_response(response: &Response) -> Self {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
 }
 
 /// URLからファイルをダウンロード
+///
+/// 開始・進捗・完了をイベントとして通知します。
 pub fn download(url: &str, filename: &str) -> Result<PathBuf> {
-    ui::info(&format!("Downloading: {}", url));
+    events::emit(Event::DownloadStarted {
+        url: url.to_string(),
+    });
+
+    let dest_path = download_with_progress(url, filename, progress_notifier())?;
+
+    events::emit(Event::DownloadFinished {
+        path: dest_path.clone(),
+    });
+    Ok(dest_path)
+}
+
+/// 複数のファイルを並列にダウンロード
+///
+/// 同時に `jobs` 件までダウンロードし、結果を `downloads` の順で返します。
+/// 失敗したダウンロードがあっても残りは続けます。
+/// 進捗はワーカースレッドから受け取り、呼び出し元のスレッドでイベントとして通知します。
+/// ワーカースレッドは呼び出し元の外部コマンドの実行方法を引き継ぎます。
+pub fn download_all(downloads: &[Download], jobs: usize) -> Vec<Result<PathBuf>> {
+    events::emit(Event::ParallelDownloadStarted {
+        names: downloads.iter().map(|d| d.name.clone()).collect(),
+    });
+
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<Result<PathBuf>>> = downloads.iter().map(|_| None).collect();
+    let (sender, receiver) = mpsc::channel();
+    let runner = process::current();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, downloads.len().max(1)) {
+            let sender = sender.clone();
+            let next = &next;
+            let runner = runner.clone();
+            scope.spawn(move || {
+                process::with_runner(runner, || loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(download) = downloads.get(index) else {
+                        break;
+                    };
+                    let progress = {
+                        let sender = sender.clone();
+                        throttled(move |downloaded, total| {
+                            let _ = sender.send(Message::Progress {
+                                index,
+                                downloaded,
+                                total,
+                            });
+                        })
+                    };
+                    let result =
+                        download_with_progress(&download.url, &download.filename, progress);
+                    let _ = sender.send(Message::Finished { index, result });
+                })
+            });
+        }
+        // 全ワーカーの終了で受信を終える
+        drop(sender);
+
+        for message in receiver {
+            match message {
+                Message::Progress {
+                    index,
+                    downloaded,
+                    total,
+                } => events::emit(Event::ParallelDownloadProgress {
+                    index,
+                    downloaded,
+                    total: (total > 0).then_some(total),
+                }),
+                Message::Finished { index, result } => {
+                    events::emit(Event::ParallelDownloadFinished {
+                        index,
+                        success: result.is_ok(),
+                    });
+                    results[index] = Some(result);
+                }
+            }
+        }
+    });
 
+    results
+        .into_iter()
+        .map(|result| result.expect("全てのダウンロードの結果を受信済み"))
+        .collect()
+}
+
+/// 進捗コールバック付きダウンロード
+///
+/// コールバックには（受信済みバイト数, 全体のバイト数）を渡します。
+/// 全体のサイズが不明な場合は 0 を渡します。
+pub fn download_with_progress<F>(url: &str, filename: &str, callback: F) -> Result<PathBuf>
+where
+    F: Fn(u64, u64),
+{
     // 一時ディレクトリ作成
-    let temp_directory = temp_dir();
+    let temp_directory = temp_dir()?;
     if !temp_directory.exists() {
         std::fs::create_dir_all(&temp_directory)?;
     }
 
     let dest_path = temp_directory.join(filename);
 
-    // ダウンロード
-    let response = reqwest::blocking::get(url).map_err(|_| AnError::DownloadFailed {
-        message: url.to_string(),
-    })?;
+    let response = reqwest::blocking::get(url).map_err(|_| download_failed(url))?;
+
+    if !response.status().is_success() {
+        return Err(download_failed(url).into());
+    }
+
+    save_body(url, response, &dest_path, callback)?;
+    Ok(dest_path)
+}
+
+/// 前回から変更があればダウンロード
+///
+/// `validators` を条件付きリクエストとして送り、`304 Not Modified` の場合は
+/// 何もせず `None` を返します。ダウンロードした場合は新しい検証子を返します。
+pub fn download_if_modified(
+    url: &str,
+    dest_path: &Path,
+    validators: &Validators,
+) -> Result<Option<Validators>> {
+    let mut request = reqwest::blocking::Client::new().get(url);
+    if let Some(ref etag) = validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(ref last_modified) = validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let response = request.send().map_err(|_| download_failed(url))?;
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(download_failed(url).into());
+    }
+
+    events::emit(Event::DownloadStarted {
+        url: url.to_string(),
+    });
+    let validators = Validators::from_response(&response);
+    save_body(url, response, dest_path, progress_notifier())?;
+    events::emit(Event::DownloadFinished {
+        path: dest_path.to_path_buf(),
+    });
+
+    Ok(Some(validators))
+}
+
+/// 小さなテキスト（署名ファイルなど）を取得
+pub fn fetch_text(url: &str) -> Result<String> {
+    let response = reqwest::blocking::get(url).map_err(|_| download_failed(url))?;
+    if !response.status().is_success() {
+        return Err(download_failed(url).into());
+    }
+    Ok(response.text().map_err(|_| download_failed(url))?)
+}
+
+/// URLにアクセスできるか確認（本文はダウンロードしない）
+///
+/// `HEAD` に対応していないサーバーには `GET` で確認します。
+pub fn check_url(url: &str) -> Result<()> {
+    let client = reqwest::blocking::Client::new();
+    let mut response = client.head(url).send().map_err(|_| download_failed(url))?;
+    if matches!(
+        response.status(),
+        StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+    ) {
+        response = client.get(url).send().map_err(|_| download_failed(url))?;
+    }
 
     if !response.status().is_success() {
         return Err(AnError::DownloadFailed {
-            message: url.to_string(),
+            message: format!("{} ({})", url, response.status()),
         }
         .into());
     }
+    Ok(())
+}
 
-    let bytes = response.bytes().map_err(|_| AnError::DownloadFailed {
+fn download_failed(url: &str) -> AnError {
+    AnError::DownloadFailed {
         message: url.to_string(),
-    })?;
+    }
+}
 
-    // ファイルに書き込み
-    let mut file = File::create(&dest_path)?;
-    file.write_all(&bytes)?;
+/// 進捗をイベントとして通知するコールバック
+fn progress_notifier() -> impl Fn(u64, u64) {
+    throttled(|downloaded, total| {
+        events::emit(Event::DownloadProgress {
+            downloaded,
+            total: (total > 0).then_some(total),
+        })
+    })
+}
 
-    ui::success(&format!("Downloaded to: {:?}", dest_path));
-    Ok(dest_path)
+/// 百分率が変わったとき（サイズ不明時は一定量ごと）のみ `report` を呼ぶ進捗コールバック
+fn throttled(report: impl Fn(u64, u64)) -> impl Fn(u64, u64) {
+    let last = Cell::new(None);
+    move |downloaded, total| {
+        let mark = match total {
+            0 => downloaded / PROGRESS_INTERVAL,
+            total => downloaded * 100 / total,
+        };
+        if last.replace(Some(mark)) != Some(mark) {
+            report(downloaded, total);
+        }
+    }
 }
 
-/// プログレスバー付きダウンロード
-#[allow(dead_code)]
-pub fn download_with_progress<F>(url: &str, filename: &str, _callback: F) -> Result<PathBuf>
+/// 応答の本文をファイルに保存
+fn save_body<F>(url: &str, mut response: Response, dest_path: &Path, callback: F) -> Result<()>
 where
     F: Fn(u64, u64),
 {
-    // TODO: プログレス表示の実装
-    // 現在は通常のダウンロードにフォールバック
-    download(url, filename)
+    let total = response.content_length().unwrap_or(0);
+    let mut file = File::create(dest_path)?;
+    let mut buffer = vec![0; 64 * 1024];
+    let mut downloaded = 0;
+
+    callback(0, total);
+    loop {
+        let read = response
+            .read(&mut buffer)
+            .map_err(|_| download_failed(url))?;
+        if read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..read])?;
+        downloaded += read as u64;
+        callback(downloaded, total);
+    }
+
+    Ok(())
+}
+
+/// テスト用のHTTPサーバー
+#[cfg(test)]
+pub(crate) mod test_server {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// ETagに対応した簡易HTTPサーバー（指定回数の要求に応答）
+    ///
+    /// `routes` のパス以外は404を返します。ベースURLを返します。
+    pub fn serve(routes: Vec<(&'static str, Vec<u8>)>, requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+
+                let request = String::from_utf8_lossy(&request).to_lowercase();
+                let path = request.split_whitespace().nth(1).unwrap_or("").to_string();
+                let body = routes.iter().find(|(p, _)| *p == path).map(|(_, b)| b);
+
+                match body {
+                    _ if request.contains("if-none-match: \"v1\"") => stream
+                        .write_all(b"HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n")
+                        .unwrap(),
+                    Some(body) => {
+                        let header = format!(
+                            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                            body.len()
+                        );
+                        stream.write_all(header.as_bytes()).unwrap();
+                        stream.write_all(body).unwrap();
+                    }
+                    None => stream
+                        .write_all(
+                            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        )
+                        .unwrap(),
+                }
+            }
+        });
+
+        base
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
     #[test]
     fn test_temp_dir() {
-        let dir = temp_dir();
-        assert!(dir.ends_with("an-downloads"));
+        let cache = tempfile::tempdir().unwrap();
+        let mut config = crate::config::Config::default();
+        config
+            .set("paths.cache_dir", cache.path().to_str().unwrap())
+            .unwrap();
+
+        let dir = crate::config::with_config(config, temp_dir).unwrap();
+        assert_eq!(
+            dir,
+            cache
+                .path()
+                .join(format!("tmp/{}/downloads", std::process::id()))
+        );
+    }
+
+    #[test]
+    fn test_download_all() {
+        let base = test_server::serve(
+            vec![
+                ("/a.appimage", b"aaaa".to_vec()),
+                ("/c.deb", b"cc".to_vec()),
+            ],
+            3,
+        );
+        let download = |name: &str, path: &str| Download {
+            name: name.to_string(),
+            url: format!("{}{}", base, path),
+            filename: format!("test-download-all-{}", path.trim_start_matches('/')),
+        };
+        let downloads = vec![
+            download("a", "/a.appimage"),
+            download("b", "/missing.appimage"),
+            download("c", "/c.deb"),
+        ];
+
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let sink = received.clone();
+        let results = events::with_observer(
+            Rc::new(move |e: &Event| sink.borrow_mut().push(e.clone())),
+            || download_all(&downloads, 2),
+        );
+
+        // 結果は指定順で、失敗しても残りはダウンロードする
+        assert_eq!(results.len(), 3);
+        let a = results[0].as_ref().unwrap();
+        assert_eq!(std::fs::read(a).unwrap(), b"aaaa");
+        assert!(results[1].is_err());
+        let c = results[2].as_ref().unwrap();
+        assert_eq!(std::fs::read(c).unwrap(), b"cc");
+        let _ = std::fs::remove_file(a);
+        let _ = std::fs::remove_file(c);
+
+        let events = received.borrow();
+        assert_eq!(
+            events[0],
+            Event::ParallelDownloadStarted {
+                names: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            }
+        );
+        assert!(events.contains(&Event::ParallelDownloadFinished {
+            index: 1,
+            success: false,
+        }));
+        assert!(events.contains(&Event::ParallelDownloadProgress {
+            index: 0,
+            downloaded: 4,
+            total: Some(4),
+        }));
+    }
+
+    #[test]
+    fn test_throttled() {
+        let calls = RefCell::new(Vec::new());
+        let progress = throttled(|downloaded, total| calls.borrow_mut().push((downloaded, total)));
+        for downloaded in [0, 1, 2, 50, 100] {
+            progress(downloaded, 200);
+        }
+        // 百分率が変わったときのみ
+        assert_eq!(*calls.borrow(), [(0, 200), (2, 200), (50, 200), (100, 200)]);
     }
 }
@@ -1,10 +1,48 @@
 //! AN (安装) - Unified Package Manager for Linux
 //!
-//! ライブラリとしてのエントリーポイント（テスト用）
+//! .deb、AppImage、Flatpak を統一インターフェースで管理するライブラリです。
+//! 組み込み用途では [`Client`] を使用します。`an` コマンドもこのAPIの利用者です。
+//!
+//! ```no_run
+//! use an_installer::{Client, SearchOptions};
+//!
+//! let client = Client::new();
+//! for app in client.search("editor", &SearchOptions::default())? {
+//!     println!("{} - {}", app.app.name, app.app.description);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
 
-pub mod cli;
-pub mod commands;
+// 以下のモジュールは `an` コマンドが使用する内部実装で、安定したAPIではありません。
+// 公開APIは下の `pub use` で再エクスポートした型のみです。
+#[doc(hidden)]
+pub mod client;
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
 pub mod db;
+#[doc(hidden)]
 pub mod errors;
+#[doc(hidden)]
+pub mod events;
+#[doc(hidden)]
 pub mod handlers;
+#[doc(hidden)]
 pub mod utils;
+
+pub use client::{
+    BatchResult, Client, InstallDetails, InstallOptions, InstallStatus, PackageGroup, SearchOptions,
+};
+pub use config::{Config, Scope};
+pub use db::app::{AppConfig, SourceType};
+pub use db::installed::InstallRecord;
+pub use db::legacy::ImportSummary;
+pub use db::lint::LintReport;
+pub use db::manifest;
+pub use db::manifest::{Manifest, ManifestEntry};
+pub use db::plan::{Action, Plan};
+pub use db::repo::Repository;
+pub use db::sync::{AppChange, ChangeKind, SyncResult, SyncSummary};
+pub use errors::AnError;
+pub use events::Event;
+pub use handlers::InstalledPackage;
@@ -2,38 +2,93 @@
 //!
 //! ANは.deb、AppImage、Flatpakを統一インターフェースで管理する
 //! パッケージマネージャーです。
+//!
+//! 処理の本体はライブラリ（`an_installer`）にあり、このバイナリはその利用者です。
+//! 引数の解析・確認プロンプト・表示（`cli` / `commands` / `ui`）はバイナリのみに含めます。
 
 mod cli;
 mod commands;
-mod db;
-mod errors;
-mod handlers;
-mod utils;
+mod ui;
+
+// コマンドの実装からライブラリの内部モジュールを `crate::` で参照する
+use an_installer::{client, config, db, errors, events, handlers, utils};
 
+use an_installer::SearchOptions;
 use anyhow::Result;
 use clap::Parser;
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, ConfigAction, DbAction, RepoAction};
+use std::path::Path;
+use std::rc::Rc;
+use ui::Verbosity;
+use utils::{lock, temp};
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    match cli.command {
+    ui::set_verbosity(if cli.quiet {
+        Verbosity::Quiet
+    } else if cli.verbose {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
+    });
+
+    // 設定を読み込み（ファイル < 環境変数 < CLIオプション）
+    let mut config = config::Config::load(cli.config.as_deref(), &cli.options)?;
+    if cli.system {
+        config.scope = config::Scope::System;
+    }
+    config::init(config)?;
+
+    // 処理中のイベントはすべてCLIの表示に流す
+    let result = events::with_observer(Rc::new(commands::render), || {
+        // 変更を伴うコマンドは他の an と同時に実行しない（終了まで保持）
+        let _lock = if cli.command.mutates() {
+            Some(lock::acquire()?)
+        } else {
+            None
+        };
+        run(cli.command, cli.config.as_deref())
+    });
+    temp::cleanup();
+    result
+}
+
+/// サブコマンドを実行
+fn run(command: Commands, config_file: Option<&Path>) -> Result<()> {
+    match command {
         Commands::Install {
-            target,
+            targets,
+            file,
             name,
             desktop,
             move_file,
+            via,
         } => {
             let options = commands::install::InstallOptions {
                 name,
                 desktop,
                 move_file,
+                via,
             };
-            commands::install::run_with_options(&target, options)?;
+            match (targets.as_slice(), file) {
+                ([target], None) => commands::install::run_with_options(target, options)?,
+                (targets, file) => commands::install::run_batch(targets, file.as_deref(), options)?,
+            }
         }
         Commands::Remove { target } => {
             commands::remove::run(&target)?;
         }
+        Commands::Apply {
+            file,
+            prune,
+            dry_run,
+        } => {
+            commands::apply::run(&file, prune, dry_run)?;
+        }
+        Commands::Export { file } => {
+            commands::apply::export(file.as_deref())?;
+        }
         Commands::Link => {
             commands::link::run()?;
         }
@@ -43,16 +98,52 @@ fn main() -> Result<()> {
         Commands::List => {
             commands::list::run()?;
         }
-        Commands::Search { query } => match query {
-            Some(q) => commands::search::run(&q)?,
-            None => commands::search::list_all()?,
-        },
+        Commands::Search {
+            query,
+            limit,
+            category,
+            source_type,
+            arch,
+        } => {
+            let options = SearchOptions {
+                limit,
+                category,
+                source_type,
+                arch,
+            };
+            match query {
+                Some(q) => commands::search::run(&q, &options)?,
+                None => commands::search::list_all(&options)?,
+            }
+        }
+        Commands::Categories => commands::search::list_categories()?,
         Commands::Info { name } => {
             commands::search::show_details(&name)?;
         }
-        Commands::Sync => {
-            commands::sync::run()?;
+        Commands::Sync { repo, json } => {
+            commands::sync::run(repo.as_deref(), json)?;
         }
+        Commands::Repo { action } => match action {
+            RepoAction::Add {
+                name,
+                url,
+                priority,
+                public_key,
+            } => commands::repo::add(&name, &url, priority, public_key.as_deref())?,
+            RepoAction::Remove { name } => commands::repo::remove(&name)?,
+            RepoAction::List => commands::repo::list()?,
+        },
+        Commands::Config { action } => match action {
+            ConfigAction::Get { key } => commands::config::get(&key)?,
+            ConfigAction::Set { key, value } => commands::config::set(&key, &value, config_file)?,
+            ConfigAction::List => commands::config::list()?,
+        },
+        Commands::Db { action } => match action {
+            DbAction::ImportLegacy { legacy_dir, output } => {
+                commands::db::import_legacy(&legacy_dir, &output)?
+            }
+            DbAction::Lint { path, check_urls } => commands::db::lint(path.as_deref(), check_urls)?,
+        },
     }
 
     Ok(())
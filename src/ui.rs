@@ -0,0 +1,247 @@
+//! UIユーティリティ
+//!
+//! カラー出力、プロンプトなどのUI関連機能を提供します。
+
+use colored::*;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+
+/// 出力の詳細度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// 警告とエラーのみ
+    Quiet,
+    /// 通常
+    Normal,
+    /// 詳細（配置したファイルや実行したコマンドも表示）
+    Verbose,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// 複数行のプログレス表示の各行のラベル
+static MULTI_PROGRESS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// プログレスバーの幅（文字数）
+const BAR_WIDTH: usize = 30;
+
+/// 出力の詳細度を設定
+pub fn set_verbosity(verbosity: Verbosity) {
+    VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+/// 現在の出力の詳細度
+pub fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        _ => Verbosity::Verbose,
+    }
+}
+
+/// 情報メッセージを出力
+pub fn info(message: &str) {
+    if verbosity() >= Verbosity::Normal {
+        println!("{}", message);
+    }
+}
+
+/// 詳細メッセージを出力（`--verbose` 時のみ）
+pub fn detail(message: &str) {
+    if verbosity() >= Verbosity::Verbose {
+        println!("  {}", message.dimmed());
+    }
+}
+
+/// 成功メッセージを出力
+pub fn success(message: &str) {
+    if verbosity() >= Verbosity::Normal {
+        println!("{} {}", "✓".green(), message.green());
+    }
+}
+
+/// 警告メッセージを出力
+pub fn warn(message: &str) {
+    eprintln!("{} {}", "Warning:".yellow(), message);
+}
+
+/// root権限で実行するコマンドを出力（表示モード）
+///
+/// 実行されないままにならないよう `--quiet` でも省略せず、その場合は標準出力を汚さないよう標準エラー出力へ出力します。
+pub fn root_command(command: &str) {
+    if verbosity() >= Verbosity::Normal {
+        println!("# root権限で実行してください:");
+        println!("{}", command);
+    } else {
+        eprintln!("# root権限で実行してください:");
+        eprintln!("{}", command);
+    }
+}
+
+/// エラーメッセージを出力
+pub fn error(message: &str) {
+    eprintln!("{} {}", "Error:".red(), message);
+}
+
+/// ユーザー確認プロンプト
+pub fn confirm(message: &str) -> io::Result<bool> {
+    print!("{} [y/N]: ", message);
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+
+    let input = input.trim().to_lowercase();
+    Ok(input == "y" || input == "yes")
+}
+
+/// 進捗率（%）を計算
+fn percentage(current: u64, total: u64) -> u32 {
+    if total > 0 {
+        (current as f64 / total as f64 * 100.0) as u32
+    } else {
+        0
+    }
+}
+
+/// プログレスバーを表示（簡易版）
+pub fn progress(current: u64, total: u64) {
+    if verbosity() < Verbosity::Normal {
+        return;
+    }
+
+    print!("\r{}", bar(percentage(current, total)));
+    io::stdout().flush().unwrap();
+
+    if current >= total {
+        println!();
+    }
+}
+
+/// プログレスバーの文字列（例: `[███░░░] 50%`）
+fn bar(percentage: u32) -> String {
+    let filled = (percentage as usize * BAR_WIDTH / 100).min(BAR_WIDTH);
+    let empty = BAR_WIDTH - filled;
+    format!(
+        "[{}{}] {}%",
+        "█".repeat(filled),
+        "░".repeat(empty),
+        percentage
+    )
+}
+
+/// 複数行のプログレス表示を開始（ラベルごとに1行）
+///
+/// 端末以外への出力では行を書き換えられないため、終了した行のみ表示します。
+pub fn multi_progress_start(labels: &[String]) {
+    let mut lines = MULTI_PROGRESS.lock().unwrap();
+    *lines = labels.to_vec();
+    if verbosity() < Verbosity::Normal || !io::stdout().is_terminal() {
+        return;
+    }
+
+    let width = label_width(&lines);
+    for label in lines.iter() {
+        println!("{:<width$} {}", label, "待機中".dimmed());
+    }
+}
+
+/// 複数行のプログレス表示の行を更新（`total` はサイズ不明なら `None`）
+pub fn multi_progress_update(index: usize, current: u64, total: Option<u64>) {
+    let status = match total {
+        Some(total) => bar(percentage(current, total)),
+        None => format_size(current),
+    };
+    redraw(index, &status, false);
+}
+
+/// 複数行のプログレス表示の行を終了として更新
+pub fn multi_progress_finish(index: usize, success: bool) {
+    let status = if success {
+        format!("{}", "✓ 完了".green())
+    } else {
+        format!("{}", "✗ 失敗".red())
+    };
+    redraw(index, &status, true);
+}
+
+/// 行を書き換える（端末以外では終了した行のみ追記）
+fn redraw(index: usize, status: &str, finished: bool) {
+    if verbosity() < Verbosity::Normal {
+        return;
+    }
+    let lines = MULTI_PROGRESS.lock().unwrap();
+    let Some(label) = lines.get(index) else {
+        return;
+    };
+    let line = format!("{:<width$} {}", label, status, width = label_width(&lines));
+
+    if !io::stdout().is_terminal() {
+        if finished {
+            println!("{}", line);
+        }
+        return;
+    }
+    // カーソルを対象の行へ上げて書き換え、表示の下端に戻す
+    let up = lines.len() - index;
+    print!("\x1b[{}A\r\x1b[2K{}\x1b[{}B\r", up, line, up);
+    io::stdout().flush().unwrap();
+}
+
+/// ラベルの表示幅（最長のラベルに揃える）
+fn label_width(labels: &[String]) -> usize {
+    labels.iter().map(|l| l.chars().count()).max().unwrap_or(0)
+}
+
+/// バイト数を読みやすい単位で表示（例: 1.5 MB）
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(150 * 1024 * 1024), "150.0 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn test_progress_percentage() {
+        // プログレス計算のテスト（出力は確認しない）
+        let percentage = (50_f64 / 100_f64 * 100.0) as u32;
+        assert_eq!(percentage, 50);
+    }
+
+    #[test]
+    fn test_bar() {
+        assert_eq!(bar(0), format!("[{}] 0%", "░".repeat(BAR_WIDTH)));
+        assert_eq!(
+            bar(50),
+            format!("[{}{}] 50%", "█".repeat(15), "░".repeat(15))
+        );
+        assert_eq!(bar(100), format!("[{}] 100%", "█".repeat(BAR_WIDTH)));
+    }
+
+    #[test]
+    fn test_verbosity_order() {
+        assert!(Verbosity::Quiet < Verbosity::Normal);
+        assert!(Verbosity::Normal < Verbosity::Verbose);
+        assert_eq!(verbosity(), Verbosity::Normal);
+    }
+}
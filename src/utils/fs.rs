@@ -1,10 +1,213 @@
 //! ファイルシステムユーティリティ
 //!
 //! ファイル操作のヘルパー関数を提供します。
+//!
+//! `place_*` 系の関数はインストール先への配置に使用し、
+//! システム全体モードでは特権昇格して実行します。
+//! 配置・削除したファイルはイベントとして通知します。
 
+use crate::config;
+use crate::errors::AnError;
+use crate::events::{self, Event};
+use crate::utils::privilege;
 use anyhow::Result;
+use std::ffi::OsStr;
+use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 
+/// 配置操作に特権昇格が必要か
+fn needs_privilege() -> bool {
+    config::get().is_system() && !privilege::is_root()
+}
+
+/// 外部コマンドの引数としてパスを文字列に変換（UTF-8でない場合はエラー）
+pub fn path_str(path: &Path) -> Result<&str> {
+    path.to_str().ok_or_else(|| {
+        AnError::ValidationError {
+            message: format!("パスがUTF-8ではありません: {}", path.display()),
+        }
+        .into()
+    })
+}
+
+/// パスのファイル名（`/` や `..` で終わりファイル名がない場合はエラー）
+pub fn file_name(path: &Path) -> Result<&OsStr> {
+    path.file_name().ok_or_else(|| {
+        AnError::ValidationError {
+            message: format!("ファイル名がありません: {}", path.display()),
+        }
+        .into()
+    })
+}
+
+/// ファイルを配置（親ディレクトリを作成し、パーミッションを設定）
+pub fn place_file(src: &Path, dest: &Path, mode: u32) -> Result<()> {
+    copy_file(src, dest, mode)?;
+    events::emit(Event::FilePlaced {
+        path: dest.to_path_buf(),
+    });
+    Ok(())
+}
+
+/// 内容を書き込んでファイルを配置
+pub fn place_content(dest: &Path, content: &str, mode: u32) -> Result<()> {
+    write_file(dest, content, mode)?;
+    events::emit(Event::FilePlaced {
+        path: dest.to_path_buf(),
+    });
+    Ok(())
+}
+
+/// シンボリックリンクを配置（既存のリンク・ファイルは置き換え）
+pub fn place_symlink(target: &Path, link: &Path) -> Result<()> {
+    make_symlink(target, link)?;
+    events::emit(Event::SymlinkCreated {
+        link: link.to_path_buf(),
+        target: target.to_path_buf(),
+    });
+    Ok(())
+}
+
+/// ファイルをコピー（`place_file` と同じだが通知しない）
+pub fn copy_file(src: &Path, dest: &Path, mode: u32) -> Result<()> {
+    if needs_privilege() {
+        let mode = format!("{:o}", mode);
+        privilege::run(
+            "install",
+            &["-D", "-m", &mode, path_str(src)?, path_str(dest)?],
+        )?;
+        return Ok(());
+    }
+
+    if let Some(parent) = dest.parent() {
+        ensure_dir(parent)?;
+    }
+    std::fs::copy(src, dest)?;
+    std::fs::set_permissions(dest, std::fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+/// 内容を書き込む（`place_content` と同じだが通知しない）
+pub fn write_file(dest: &Path, content: &str, mode: u32) -> Result<()> {
+    if needs_privilege() {
+        let temp = tempfile::NamedTempFile::new()?;
+        std::fs::write(temp.path(), content)?;
+        copy_file(temp.path(), dest, mode)?;
+        // 表示モードでは管理者が後で実行できるよう一時ファイルを残す
+        if privilege::is_print_mode() {
+            temp.into_temp_path().keep()?;
+        }
+        return Ok(());
+    }
+
+    if let Some(parent) = dest.parent() {
+        ensure_dir(parent)?;
+    }
+    std::fs::write(dest, content)?;
+    std::fs::set_permissions(dest, std::fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+/// シンボリックリンクを作成（`place_symlink` と同じだが通知しない）
+pub fn make_symlink(target: &Path, link: &Path) -> Result<()> {
+    if needs_privilege() {
+        if let Some(parent) = link.parent() {
+            privilege::run("mkdir", &["-p", path_str(parent)?])?;
+        }
+        privilege::run("ln", &["-sfn", path_str(target)?, path_str(link)?])?;
+        return Ok(());
+    }
+
+    if let Some(parent) = link.parent() {
+        ensure_dir(parent)?;
+    }
+    if link.exists() || link.is_symlink() {
+        std::fs::remove_file(link)?;
+    }
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+/// ファイル・リンクを置き換えるように名前を変更（同じファイルシステム内ではアトミック）
+pub fn rename(from: &Path, to: &Path) -> Result<()> {
+    if needs_privilege() {
+        privilege::run("mv", &["-fT", path_str(from)?, path_str(to)?])?;
+        return Ok(());
+    }
+
+    std::fs::rename(from, to)?;
+    Ok(())
+}
+
+/// ファイル・リンクの控えを作成（元のファイルはそのまま残す）
+///
+/// ファイルはハードリンク（できない場合はコピー）、シンボリックリンクは同じ先を指すリンクを作成します。
+pub fn backup(path: &Path, backup: &Path) -> Result<()> {
+    // 前回の控えが残っている場合、`cp` はその中（リンク先）に書き込むため先に削除する
+    discard(backup)?;
+    if needs_privilege() {
+        privilege::run("cp", &["-a", path_str(path)?, path_str(backup)?])?;
+        return Ok(());
+    }
+
+    if path.is_symlink() {
+        std::os::unix::fs::symlink(std::fs::read_link(path)?, backup)?;
+    } else if std::fs::hard_link(path, backup).is_err() {
+        std::fs::copy(path, backup)?;
+    }
+    Ok(())
+}
+
+/// ファイル・リンクを削除（`unplace` と同じだが通知しない、存在しなければ何もしない）
+pub fn discard(path: &Path) -> Result<()> {
+    if !path.exists() && !path.is_symlink() {
+        return Ok(());
+    }
+
+    if needs_privilege() {
+        privilege::run("rm", &["-f", path_str(path)?])?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// 配置済みのファイル・リンクを削除
+pub fn unplace(path: &Path) -> Result<()> {
+    if !path.exists() && !path.is_symlink() {
+        return Ok(());
+    }
+
+    if needs_privilege() {
+        privilege::run("rm", &["-f", path_str(path)?])?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+
+    events::emit(Event::FileRemoved {
+        path: path.to_path_buf(),
+    });
+    Ok(())
+}
+
+/// 配置先の空ディレクトリを削除（空でなければ何もしない）
+pub fn unplace_empty_dir(path: &Path) -> Result<()> {
+    let is_empty = std::fs::read_dir(path)
+        .map(|mut entries| entries.next().is_none())
+        .unwrap_or(false);
+    if !is_empty {
+        return Ok(());
+    }
+
+    if needs_privilege() {
+        privilege::run("rmdir", &[path_str(path)?])?;
+        return Ok(());
+    }
+
+    std::fs::remove_dir(path)?;
+    Ok(())
+}
+
 /// ファイルを削除
 pub fn remove_file(path: &Path) -> Result<()> {
     if path.exists() {
@@ -43,9 +246,29 @@ pub fn dir_exists(path: &Path) -> bool {
     path.exists() && path.is_dir()
 }
 
+/// ファイル・ディレクトリの合計サイズ（バイト、シンボリックリンクは辿らない）
+pub fn disk_usage(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| disk_usage(&e.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
     use tempfile::tempdir;
 
     #[test]
@@ -75,6 +298,107 @@ mod tests {
         assert!(!dir_exists(&temp.path().join("nonexistent")));
     }
 
+    #[test]
+    fn test_disk_usage() {
+        let temp = tempdir().unwrap();
+        std::fs::write(temp.path().join("a"), "12345").unwrap();
+        std::fs::create_dir(temp.path().join("sub")).unwrap();
+        std::fs::write(temp.path().join("sub").join("b"), "123").unwrap();
+        std::os::unix::fs::symlink(temp.path().join("a"), temp.path().join("link")).unwrap();
+
+        let link_len = std::fs::symlink_metadata(temp.path().join("link"))
+            .unwrap()
+            .len();
+        assert_eq!(disk_usage(temp.path()), 8 + link_len);
+        assert_eq!(disk_usage(&temp.path().join("a")), 5);
+        assert_eq!(disk_usage(&temp.path().join("missing")), 0);
+    }
+
+    #[test]
+    fn test_place_file_creates_parent() {
+        let temp = tempdir().unwrap();
+        let src = temp.path().join("src.bin");
+        std::fs::write(&src, "binary").unwrap();
+        let dest = temp.path().join("nested").join("dest.bin");
+
+        place_file(&src, &dest, 0o755).unwrap();
+        assert_eq!(std::fs::read_to_string(&dest).unwrap(), "binary");
+        let mode = std::fs::metadata(&dest).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+
+    #[test]
+    fn test_place_symlink_replaces_existing() {
+        let temp = tempdir().unwrap();
+        let target = temp.path().join("target");
+        std::fs::write(&target, "x").unwrap();
+        let link = temp.path().join("bin").join("app");
+
+        place_content(&link, "old", 0o644).unwrap();
+        place_symlink(&target, &link).unwrap();
+        assert_eq!(std::fs::read_link(&link).unwrap(), target);
+
+        unplace(&link).unwrap();
+        assert!(!link.is_symlink());
+        unplace_empty_dir(&temp.path().join("bin")).unwrap();
+        assert!(!temp.path().join("bin").exists());
+    }
+
+    #[test]
+    fn test_place_emits_events() {
+        let temp = tempdir().unwrap();
+        let target = temp.path().join("target");
+        let link = temp.path().join("link");
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let sink = received.clone();
+
+        events::with_observer(
+            Rc::new(move |e: &Event| sink.borrow_mut().push(e.clone())),
+            || {
+                place_content(&target, "x", 0o644).unwrap();
+                place_symlink(&target, &link).unwrap();
+                unplace(&link).unwrap();
+            },
+        );
+
+        assert_eq!(
+            *received.borrow(),
+            vec![
+                Event::FilePlaced {
+                    path: target.clone()
+                },
+                Event::SymlinkCreated {
+                    link: link.clone(),
+                    target: target.clone()
+                },
+                Event::FileRemoved { path: link },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_path_str_rejects_non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(path_str(Path::new("/opt/an")).unwrap(), "/opt/an");
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"/opt/an/\xff.AppImage"));
+        let err = path_str(path).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AnError>(),
+            Some(AnError::ValidationError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(
+            file_name(Path::new("/tmp/app.AppImage")).unwrap(),
+            "app.AppImage"
+        );
+        assert!(file_name(Path::new("/")).is_err());
+        assert!(file_name(Path::new("/tmp/..")).is_err());
+    }
+
     #[test]
     fn test_remove_file() {
         let temp = tempdir().unwrap();
@@ -0,0 +1,228 @@
+//! グローバルロック
+//!
+//! インストール先・インストール記録・アプリDBを変更する操作の実行中は、
+//! アプリDBディレクトリと同じ場所のロックファイル（例: `~/.config/an/.apps.lock`）を
+//! 排他ロック（`flock`）します。ユーザーモードとシステム全体モードは同じアプリDBを
+//! 使用するため、同じロックを取得します。
+//! 同時に実行した AN が同じファイル・リンク・DBディレクトリを上書きしないよう、
+//! 2つ目の AN は待たずに E906 で終了します。
+//! ロックはプロセスの終了時（異常終了を含む）にOSが解放します。
+
+use crate::config;
+use crate::errors::AnError;
+use crate::events;
+use crate::utils::{temp, transaction};
+use anyhow::Result;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// プロセスが保持中のロックと取得の入れ子の数
+static HELD: Mutex<Option<(Lock, usize)>> = Mutex::new(None);
+
+/// 保持中のロック（ドロップで解放）
+#[derive(Debug)]
+pub struct Lock {
+    _file: File,
+}
+
+/// [`acquire`] で取得したグローバルロック（すべてドロップすると解放）
+#[derive(Debug)]
+pub struct Guard {
+    _private: (),
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let mut held = HELD.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((_, count)) = held.as_mut() {
+            *count -= 1;
+            if *count == 0 {
+                *held = None;
+            }
+        }
+    }
+}
+
+/// ロックファイルのパス（アプリDBディレクトリと同じ場所）
+pub fn lock_path() -> PathBuf {
+    let db_dir = config::get().db_dir();
+    let name = db_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    db_dir.with_file_name(format!(".{}.lock", name))
+}
+
+/// グローバルロックを取得
+///
+/// 同じプロセスで既に保持している場合はそのまま使用します（CLI と [`crate::Client`] の
+/// 操作が入れ子で取得するため）。
+/// 最初の取得後、異常終了した AN が残した作業ディレクトリを削除し、
+/// 中断したインストールを元に戻します。
+pub fn acquire() -> Result<Guard> {
+    let mut held = HELD.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some((_, count)) = held.as_mut() {
+        *count += 1;
+        return Ok(Guard { _private: () });
+    }
+    *held = Some((acquire_at(&lock_path())?, 1));
+    drop(held);
+
+    let guard = Guard { _private: () };
+    temp::remove_stale();
+    recover(&transaction::journal_path())?;
+    Ok(guard)
+}
+
+/// 中断したインストールを元に戻す
+///
+/// 記録が壊れているなどで元に戻せない場合は、以降の操作がすべて失敗しないよう
+/// 記録を退避して警告します。
+fn recover(journal: &Path) -> Result<()> {
+    if let Err(e) = transaction::recover_at(journal) {
+        let aside = transaction::set_aside_at(journal)?;
+        events::warn(format!(
+            "前回中断したインストールを元に戻せませんでした（{}）。記録を {} に退避しました",
+            e,
+            aside.display()
+        ));
+    }
+    Ok(())
+}
+
+/// 指定したファイルで排他ロックを取得（他のプロセスが保持している場合は E906）
+pub fn acquire_at(path: &Path) -> Result<Lock> {
+    let (mut file, writable) = open(path)?;
+
+    // SAFETY: `file` が有効なファイルディスクリプタを保持している間のみ呼び出す
+    let locked = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0;
+    if !locked {
+        let error = std::io::Error::last_os_error();
+        if error.kind() != ErrorKind::WouldBlock {
+            return Err(error.into());
+        }
+        let pid = std::fs::read_to_string(path)
+            .ok()
+            .map(|pid| pid.trim().to_string())
+            .filter(|pid| !pid.is_empty())
+            .unwrap_or_else(|| "不明".to_string());
+        return Err(AnError::AlreadyRunning { pid }.into());
+    }
+
+    // 保持しているプロセスを表示できるよう PID を書き込む
+    if writable {
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+    }
+    Ok(Lock { _file: file })
+}
+
+/// ロックファイルを開く（書き込めるか）
+///
+/// 別のユーザー（root など）が作成したロックファイルは読み取り専用で開きます。
+fn open(path: &Path) -> Result<(File, bool)> {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+    {
+        Ok(file) => Ok((file, true)),
+        Err(e) if e.kind() == ErrorKind::PermissionDenied && path.exists() => {
+            Ok((File::open(path)?, false))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Event;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_acquire_at() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("state").join("an.lock");
+
+        let lock = acquire_at(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            std::process::id().to_string()
+        );
+
+        // 保持中は取得できず、保持しているプロセスを表示する
+        let err = acquire_at(&path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "E906: 別の an が実行中です（PID {}）。終了してから再実行してください",
+                std::process::id()
+            )
+        );
+
+        // 解放後は取得できる
+        drop(lock);
+        assert!(acquire_at(&path).is_ok());
+    }
+
+    #[test]
+    fn test_lock_path() {
+        let mut config = config::Config::default();
+        config
+            .set("paths.db_dir", "/home/user/.config/an/apps")
+            .unwrap();
+        config::with_config(config.clone(), || {
+            assert_eq!(
+                lock_path(),
+                PathBuf::from("/home/user/.config/an/.apps.lock")
+            );
+        });
+
+        // システム全体モードでも同じアプリDBのロックを使用する
+        config.scope = config::Scope::System;
+        config::with_config(config, || {
+            assert_eq!(
+                lock_path(),
+                PathBuf::from("/home/user/.config/an/.apps.lock")
+            );
+        });
+    }
+
+    #[test]
+    fn test_recover_sets_aside_broken_journal() {
+        let dir = tempdir().unwrap();
+        let journal = dir.path().join("journal.toml");
+        std::fs::write(&journal, "壊れた記録").unwrap();
+
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let sink = received.clone();
+        let result = events::with_observer(
+            Rc::new(move |e: &Event| sink.borrow_mut().push(e.clone())),
+            || recover(&journal),
+        );
+        assert!(result.is_ok());
+        assert!(!journal.exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("journal.toml.broken")).unwrap(),
+            "壊れた記録"
+        );
+        assert!(matches!(
+            &received.borrow()[..],
+            [Event::Warning { message }] if message.contains("退避")
+        ));
+
+        // 退避後は記録がないため何もしない
+        assert!(recover(&journal).is_ok());
+    }
+}
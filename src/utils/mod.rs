@@ -3,4 +3,9 @@
 //! 共通のヘルパー関数を提供します。
 
 pub mod fs;
-pub mod ui;
+pub mod lock;
+pub mod privilege;
+pub mod process;
+pub mod temp;
+pub mod transaction;
+pub mod xdg;
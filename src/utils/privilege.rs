@@ -0,0 +1,256 @@
+//! 特権昇格ユーティリティ
+//!
+//! システム全体モードや .deb の操作など、root権限が必要な操作のみを昇格して実行します。
+//! 昇格方法は設定 `privilege.method` で選択するか、利用可能なものを自動検出します。
+//!
+//! `print` を選択した場合はコマンドを実行せず、管理者が実行するためのコマンドを表示します。
+
+use super::process;
+use crate::config;
+use crate::errors::AnError;
+use crate::events::{self, Event};
+use anyhow::Result;
+use std::process::Output;
+
+/// 自動検出で試す昇格コマンド（優先順）
+const DETECT_ORDER: &[Method] = &[Method::Sudo, Method::Doas, Method::Run0, Method::Pkexec];
+
+/// 特権昇格の方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// 既にroot権限で実行中（昇格不要）
+    Root,
+    Sudo,
+    Doas,
+    Run0,
+    Pkexec,
+    /// 実行せずにコマンドを表示
+    Print,
+}
+
+impl Method {
+    /// 設定値から変換（`auto` は `None`）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sudo" => Some(Self::Sudo),
+            "doas" => Some(Self::Doas),
+            "run0" => Some(Self::Run0),
+            "pkexec" => Some(Self::Pkexec),
+            "print" => Some(Self::Print),
+            _ => None,
+        }
+    }
+
+    /// 昇格に使用するプログラム名
+    pub fn program(&self) -> Option<&'static str> {
+        match self {
+            Self::Sudo => Some("sudo"),
+            Self::Doas => Some("doas"),
+            Self::Run0 => Some("run0"),
+            Self::Pkexec => Some("pkexec"),
+            Self::Root | Self::Print => None,
+        }
+    }
+
+    /// 実行するコマンドライン（argv）を構築
+    pub fn argv(&self, program: &str, args: &[&str]) -> Vec<String> {
+        self.program()
+            .into_iter()
+            .chain(std::iter::once(program))
+            .chain(args.iter().copied())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// 設定で指定可能な昇格方法の名前
+pub const METHOD_NAMES: &[&str] = &["auto", "sudo", "doas", "run0", "pkexec", "print"];
+
+/// root権限で実行中か確認（実効UIDが0）
+pub fn is_root() -> bool {
+    // SAFETY: geteuid は引数を取らず、常に成功する
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// 利用可能な昇格方法を検出
+fn detect_with(is_root: bool, available: impl Fn(&str) -> bool) -> Option<Method> {
+    if is_root {
+        return Some(Method::Root);
+    }
+
+    DETECT_ORDER
+        .iter()
+        .copied()
+        .find(|m| m.program().map(&available).unwrap_or(false))
+}
+
+/// 使用する昇格方法を決定
+///
+/// 設定 `privilege.method` が `auto`（デフォルト）の場合は自動検出します。
+pub fn method() -> Result<Method> {
+    let configured = config::get().privilege_method();
+
+    if let Some(method) = Method::from_name(configured) {
+        // print 以外はroot実行中なら昇格不要
+        if method != Method::Print && is_root() {
+            return Ok(Method::Root);
+        }
+        return Ok(method);
+    }
+
+    detect_with(is_root(), process::is_available).ok_or_else(|| AnError::NoPrivilegeMethod.into())
+}
+
+/// 表示モード（コマンドを実行しない）か
+pub fn is_print_mode() -> bool {
+    config::get().privilege_method() == "print"
+}
+
+/// シェル用に文字列をクォート（記号を含まない場合はそのまま）
+pub fn shell_quote(value: &str) -> String {
+    if !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@+".contains(c))
+    {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r#"'\''"#))
+    }
+}
+
+/// シェルに貼り付け可能な形式でコマンドラインを整形
+fn format_command(argv: &[String]) -> String {
+    argv.iter()
+        .map(|arg| shell_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 特権付きでコマンドを実行し、出力を返す（終了ステータスは確認しない）
+///
+/// 表示モードではコマンドを実行せずに通知（[`Event::RootCommandRequired`]）し、
+/// 出力がないため `None` を返します。
+pub fn output(program: &str, args: &[&str]) -> Result<Option<Output>> {
+    let method = method()?;
+    let argv = method.argv(program, args);
+
+    if method == Method::Print {
+        events::emit(Event::RootCommandRequired {
+            command: format_command(&argv),
+        });
+        return Ok(None);
+    }
+
+    let args: Vec<&str> = argv[1..].iter().map(String::as_str).collect();
+    Ok(Some(process::output(&argv[0], &args)?))
+}
+
+/// 特権付きでコマンドを実行し、失敗時はエラーを返す（表示モードでは通知のみ）
+pub fn run(program: &str, args: &[&str]) -> Result<()> {
+    let Some(output) = output(program, args)? else {
+        return Ok(());
+    };
+
+    if !output.status.success() {
+        return Err(AnError::PrivilegedCommandFailed {
+            command: format!("{} {}", program, args.join(" ")),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Method::from_name("doas"), Some(Method::Doas));
+        assert_eq!(Method::from_name("print"), Some(Method::Print));
+        assert_eq!(Method::from_name("auto"), None);
+    }
+
+    #[test]
+    fn test_argv() {
+        assert_eq!(
+            Method::Run0.argv("dpkg", &["-i", "app.deb"]),
+            vec!["run0", "dpkg", "-i", "app.deb"]
+        );
+        assert_eq!(
+            Method::Root.argv("dpkg", &["-i", "app.deb"]),
+            vec!["dpkg", "-i", "app.deb"]
+        );
+        assert_eq!(
+            Method::Print.argv("rm", &["-f", "/x"]),
+            vec!["rm", "-f", "/x"]
+        );
+    }
+
+    #[test]
+    fn test_detect_prefers_root() {
+        assert_eq!(detect_with(true, |_| false), Some(Method::Root));
+    }
+
+    #[test]
+    fn test_detect_order() {
+        assert_eq!(detect_with(false, |_| true), Some(Method::Sudo));
+        assert_eq!(
+            detect_with(false, |p| p == "doas" || p == "pkexec"),
+            Some(Method::Doas)
+        );
+        assert_eq!(detect_with(false, |p| p == "run0"), Some(Method::Run0));
+    }
+
+    #[test]
+    fn test_detect_none_available() {
+        assert_eq!(detect_with(false, |_| false), None);
+    }
+
+    #[test]
+    fn test_format_command_quotes() {
+        let argv = vec![
+            "install".to_string(),
+            "/tmp/my app.AppImage".to_string(),
+            "it's".to_string(),
+        ];
+        assert_eq!(
+            format_command(&argv),
+            r#"install '/tmp/my app.AppImage' 'it'\''s'"#
+        );
+    }
+    #[test]
+    fn test_print_mode_notifies_without_output() {
+        let mut print = config::Config::default();
+        print.set("privilege.method", "print").unwrap();
+        let events = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let observed = events.clone();
+
+        config::with_config(print, || {
+            events::with_observer(
+                std::rc::Rc::new(move |event: &Event| observed.borrow_mut().push(event.clone())),
+                || {
+                    // 実行しないため出力はない
+                    assert!(output("apt", &["purge", "-y", "app"]).unwrap().is_none());
+                    run("rm", &["-f", "/opt/an/my app"])
+                },
+            )
+        })
+        .unwrap();
+
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                Event::RootCommandRequired {
+                    command: "apt purge -y app".to_string()
+                },
+                Event::RootCommandRequired {
+                    command: "rm -f '/opt/an/my app'".to_string()
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! 外部コマンド実行ユーティリティ
+//!
+//! dpkg・apt・flatpak・git などの外部コマンドは全てこのモジュール経由で実行します。
+//! 実行方法は [`CommandRunner`] として差し替え可能で、テストでは
+//! [`with_runner`] で記録用の実装に置き換えて実際のコマンドを実行せずに検証します。
+
+use crate::events::{self, Event};
+use std::cell::RefCell;
+use std::io;
+use std::process::{Command, Output};
+use std::sync::Arc;
+
+/// 外部コマンドの実行方法（ワーカースレッドと共有できる）
+pub trait CommandRunner: Send + Sync {
+    /// コマンドを実行し、出力を返す（終了ステータスは確認しない）
+    fn output(&self, program: &str, args: &[&str]) -> io::Result<Output>;
+
+    /// プログラムがPATH上に存在するか
+    fn is_available(&self, program: &str) -> bool {
+        which::which(program).is_ok()
+    }
+}
+
+/// 実際にプロセスを起動する実装
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn output(&self, program: &str, args: &[&str]) -> io::Result<Output> {
+        Command::new(program).args(args).output()
+    }
+}
+
+thread_local! {
+    /// 差し替え中の実行方法（スレッドごと）
+    static RUNNER: RefCell<Option<Arc<dyn CommandRunner>>> = const { RefCell::new(None) };
+}
+
+/// 差し替えを元に戻すガード（パニック時も復元する）
+struct Restore(Option<Arc<dyn CommandRunner>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        RUNNER.with(|r| *r.borrow_mut() = previous);
+    }
+}
+
+/// 実行方法を差し替えて処理を実行
+///
+/// 差し替えは現在のスレッドのみに作用し、`f` の終了後に元に戻ります。
+/// ワーカースレッドでは [`current`] で取得した呼び出し元の実行方法を渡します。
+pub fn with_runner<T>(runner: Arc<dyn CommandRunner>, f: impl FnOnce() -> T) -> T {
+    let previous = RUNNER.with(|r| r.borrow_mut().replace(runner));
+    let _restore = Restore(previous);
+    f()
+}
+
+/// 現在の実行方法を取得
+pub fn current() -> Arc<dyn CommandRunner> {
+    RUNNER
+        .with(|r| r.borrow().clone())
+        .unwrap_or_else(|| Arc::new(SystemRunner))
+}
+
+/// コマンドを実行し、出力を返す（終了ステータスは確認しない）
+///
+/// 実行したコマンドはイベントとして通知します。
+pub fn output(program: &str, args: &[&str]) -> io::Result<Output> {
+    let output = current().output(program, args);
+    events::emit(Event::CommandExecuted {
+        command: std::iter::once(program)
+            .chain(args.iter().copied())
+            .collect::<Vec<_>>()
+            .join(" "),
+        success: output.as_ref().is_ok_and(|o| o.status.success()),
+    });
+    output
+}
+
+/// コマンドを実行し、成功したか返す（起動できない場合も失敗）
+pub fn succeeds(program: &str, args: &[&str]) -> bool {
+    output(program, args)
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// プログラムがPATH上に存在するか
+pub fn is_available(program: &str) -> bool {
+    current().is_available(program)
+}
+
+#[cfg(test)]
+pub mod fake {
+    //! テスト用の記録・応答スクリプト付き実装
+
+    use super::CommandRunner;
+    use std::io;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    /// 応答内容
+    #[derive(Debug, Clone)]
+    pub struct Reply {
+        code: i32,
+        stdout: String,
+        stderr: String,
+    }
+
+    impl Reply {
+        /// 成功（終了コード0）
+        pub fn ok(stdout: &str) -> Self {
+            Self {
+                code: 0,
+                stdout: stdout.to_string(),
+                stderr: String::new(),
+            }
+        }
+
+        /// 失敗
+        pub fn fail(code: i32, stderr: &str) -> Self {
+            Self {
+                code,
+                stdout: String::new(),
+                stderr: stderr.to_string(),
+            }
+        }
+
+        fn to_output(&self) -> Output {
+            Output {
+                status: ExitStatus::from_raw(self.code << 8),
+                stdout: self.stdout.as_bytes().to_vec(),
+                stderr: self.stderr.as_bytes().to_vec(),
+            }
+        }
+    }
+
+    /// 実行したコマンドを記録し、登録した応答を返す実装
+    ///
+    /// 応答はコマンドライン中に連続して現れる引数列で照合します
+    /// （昇格コマンドが前置されても一致します）。
+    /// 一致する応答がない場合は空の出力で成功します。
+    #[derive(Default)]
+    pub struct FakeRunner {
+        rules: Mutex<Vec<(Vec<String>, Reply)>>,
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl FakeRunner {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// 引数列 `pattern` を含むコマンドへの応答を登録（先に登録したものが優先）
+        pub fn on(self, pattern: &[&str], reply: Reply) -> Self {
+            self.rules
+                .lock()
+                .unwrap()
+                .push((pattern.iter().map(|s| s.to_string()).collect(), reply));
+            self
+        }
+
+        /// 実行されたコマンドライン一覧
+        pub fn calls(&self) -> Vec<String> {
+            self.calls
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|c| c.join(" "))
+                .collect()
+        }
+
+        /// 引数列 `pattern` を含むコマンドが実行されたか
+        pub fn called(&self, pattern: &[&str]) -> bool {
+            self.calls
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|c| contains(c, pattern))
+        }
+    }
+
+    fn contains<S: AsRef<str>>(argv: &[String], pattern: &[S]) -> bool {
+        pattern.is_empty()
+            || argv
+                .windows(pattern.len())
+                .any(|w| w.iter().zip(pattern).all(|(a, p)| a.as_str() == p.as_ref()))
+    }
+
+    impl CommandRunner for FakeRunner {
+        fn output(&self, program: &str, args: &[&str]) -> io::Result<Output> {
+            let argv: Vec<String> = std::iter::once(program)
+                .chain(args.iter().copied())
+                .map(str::to_string)
+                .collect();
+
+            let reply = self
+                .rules
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(pattern, _)| contains(&argv, pattern))
+                .map(|(_, reply)| reply.clone())
+                .unwrap_or_else(|| Reply::ok(""));
+
+            self.calls.lock().unwrap().push(argv);
+            Ok(reply.to_output())
+        }
+
+        fn is_available(&self, _program: &str) -> bool {
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::{FakeRunner, Reply};
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_with_runner_records_and_restores() {
+        let fake = Arc::new(FakeRunner::new().on(&["--version"], Reply::fail(1, "boom")));
+
+        let ok = with_runner(fake.clone(), || succeeds("flatpak", &["--version"]));
+        assert!(!ok);
+        assert_eq!(fake.calls(), vec!["flatpak --version"]);
+
+        // 差し替え終了後は記録されない
+        let _ = output("true", &[]);
+        assert_eq!(fake.calls().len(), 1);
+    }
+
+    #[test]
+    fn test_current_runner_in_worker_thread() {
+        let fake = Arc::new(FakeRunner::new());
+
+        with_runner(fake.clone(), || {
+            let runner = current();
+            std::thread::spawn(move || with_runner(runner, || succeeds("git", &["fetch"])))
+                .join()
+                .unwrap()
+        });
+        assert_eq!(fake.calls(), vec!["git fetch"]);
+    }
+
+    #[test]
+    fn test_fake_reply_output() {
+        let fake = Arc::new(FakeRunner::new().on(&["dpkg", "-l"], Reply::ok("ii  code")));
+        let output = with_runner(fake.clone(), || output("sudo", &["dpkg", "-l", "code"])).unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "ii  code");
+        assert!(fake.called(&["-l", "code"]));
+        assert!(!fake.called(&["code", "-l"]));
+    }
+
+    #[test]
+    fn test_fake_exit_code() {
+        let fake = Arc::new(FakeRunner::new().on(&["git"], Reply::fail(128, "fatal")));
+        let output = with_runner(fake, || output("git", &["clone"])).unwrap();
+        assert_eq!(output.status.code(), Some(128));
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "fatal");
+    }
+
+    #[test]
+    fn test_output_emits_command_executed() {
+        let fake = Arc::new(FakeRunner::new().on(&["git"], Reply::fail(1, "")));
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let sink = received.clone();
+
+        events::with_observer(
+            Rc::new(move |e: &Event| sink.borrow_mut().push(e.clone())),
+            || with_runner(fake, || succeeds("git", &["pull", "--ff-only"])),
+        );
+
+        assert_eq!(
+            *received.borrow(),
+            vec![Event::CommandExecuted {
+                command: "git pull --ff-only".to_string(),
+                success: false
+            }]
+        );
+    }
+}
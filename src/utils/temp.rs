@@ -0,0 +1,95 @@
+//! プロセスごとの作業ディレクトリ
+//!
+//! ダウンロードなどの一時ファイルは、キャッシュディレクトリの `tmp/<PID>/` に作成します。
+//! 同時に実行した AN が同じ一時ファイルを上書き・削除しないよう、プロセスごとに分けています。
+//! 終了時に [`cleanup`] で削除し、異常終了で残ったディレクトリは
+//! 次にグローバルロックを取得した AN が削除します。
+
+use crate::config;
+use crate::utils::privilege;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// 作業ディレクトリを置くディレクトリ
+fn base_dir() -> PathBuf {
+    config::get().cache_dir().join("tmp")
+}
+
+/// このプロセスの作業ディレクトリ（なければ作成）
+pub fn dir() -> Result<PathBuf> {
+    dir_in(&base_dir())
+}
+
+fn dir_in(base: &Path) -> Result<PathBuf> {
+    let dir = base.join(std::process::id().to_string());
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// このプロセスの作業ディレクトリを削除
+///
+/// 表示モードでは、表示した配置コマンドが参照するダウンロードを残します
+/// （次に変更を伴うコマンドを実行したときに削除）。
+pub fn cleanup() {
+    if privilege::is_print_mode() {
+        return;
+    }
+    cleanup_in(&base_dir());
+}
+
+fn cleanup_in(base: &Path) {
+    let _ = std::fs::remove_dir_all(base.join(std::process::id().to_string()));
+}
+
+/// 終了したプロセスの作業ディレクトリを削除
+pub fn remove_stale() {
+    remove_stale_in(&base_dir(), |pid| Path::new("/proc").join(pid).exists());
+}
+
+fn remove_stale_in(base: &Path, is_running: impl Fn(&str) -> bool) {
+    let Ok(entries) = std::fs::read_dir(base) else {
+        return;
+    };
+    let current = std::process::id().to_string();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_pid = !name.is_empty() && name.chars().all(|c| c.is_ascii_digit());
+        if is_pid && name != current && !is_running(&name) {
+            let _ = std::fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_dir_is_per_process() {
+        let base = tempdir().unwrap();
+        let dir = dir_in(base.path()).unwrap();
+        assert_eq!(dir, base.path().join(std::process::id().to_string()));
+        assert!(dir.is_dir());
+
+        cleanup_in(base.path());
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_remove_stale_in() {
+        let base = tempdir().unwrap();
+        let current = std::process::id().to_string();
+        for name in [current.as_str(), "1", "999999999", "keep"] {
+            std::fs::create_dir_all(base.path().join(name)).unwrap();
+        }
+
+        remove_stale_in(base.path(), |pid| pid == "1");
+
+        // 実行中のプロセスと自分自身、PID以外の名前は残す
+        assert!(base.path().join(&current).exists());
+        assert!(base.path().join("1").exists());
+        assert!(base.path().join("keep").exists());
+        assert!(!base.path().join("999999999").exists());
+    }
+}
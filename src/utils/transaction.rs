@@ -0,0 +1,493 @@
+//! インストールのトランザクション
+//!
+//! 配置するファイル・リンクは、まず配置先と同じディレクトリに一時的な名前（`.<名前>.an-new`）で用意し、
+//! すべて揃ってから `rename` でアトミックに置き換えます。
+//! 置き換える前のファイル・リンクは `.<名前>.an-old` に控えておき、確定後に削除します。
+//!
+//! 各段階の前に状態ディレクトリの `journal.toml` に予定を記録します。
+//! 途中で失敗した場合はその場で、中断した場合（Ctrl-C・異常終了）は次回の実行時に
+//! 記録から元の状態に戻すため、片方だけ置き換わったファイルや壊れたリンクが残りません。
+//!
+//! 表示モード（`privilege.method = "print"`）ではコマンドを実行しないため、
+//! ジャーナル・控え・一時的な名前を使わず、配置先に直接配置するコマンドを表示します。
+
+use crate::config;
+use crate::events::{self, Event};
+use crate::utils::fs as fs_utils;
+use crate::utils::privilege;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 用意したファイル・リンクの接尾辞
+const STAGED_SUFFIX: &str = "an-new";
+
+/// 置き換える前のファイル・リンクの接尾辞
+const BACKUP_SUFFIX: &str = "an-old";
+
+/// トランザクションの段階
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Phase {
+    /// 用意中（配置先はまだ変更していない）
+    Staging,
+    /// 置き換え中
+    Committing,
+}
+
+/// 配置するファイル・リンク
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    /// 配置先
+    path: PathBuf,
+    /// シンボリックリンクの場合はリンク先
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<PathBuf>,
+    /// 置き換える前の控え（配置先が既にあった場合）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    backup: Option<PathBuf>,
+}
+
+/// ジャーナル（`journal.toml`）の内容
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Journal {
+    /// 対象のアプリ名
+    name: String,
+    phase: Phase,
+    #[serde(default)]
+    entries: Vec<Entry>,
+}
+
+/// 進行中のトランザクション
+///
+/// [`Transaction::commit`] せずにドロップした場合（エラーで中断した場合を含む）は元の状態に戻します。
+#[derive(Debug)]
+pub struct Transaction {
+    /// ジャーナルのパス
+    path: PathBuf,
+    journal: Journal,
+    /// 表示モードのため配置先に直接配置するか
+    direct: bool,
+    /// 確定または元に戻し済みか
+    finished: bool,
+}
+
+/// ジャーナルのパス
+pub fn journal_path() -> PathBuf {
+    config::get().state_dir().join("journal.toml")
+}
+
+/// 配置先と同じディレクトリの一時的な名前（例: `.firefox.AppImage.an-new`）
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+fn exists(path: &Path) -> bool {
+    path.exists() || path.is_symlink()
+}
+
+impl Transaction {
+    /// トランザクションを開始（中断したトランザクションがあれば先に元に戻す）
+    pub fn begin(name: &str) -> Result<Self> {
+        Self::begin_at(journal_path(), name)
+    }
+
+    /// 指定したジャーナルでトランザクションを開始
+    pub fn begin_at(path: PathBuf, name: &str) -> Result<Self> {
+        recover_at(&path)?;
+        Ok(Transaction {
+            path,
+            journal: Journal {
+                name: name.to_string(),
+                phase: Phase::Staging,
+                entries: Vec::new(),
+            },
+            direct: privilege::is_print_mode(),
+            finished: false,
+        })
+    }
+
+    /// ファイルを配置する予定を追加
+    pub fn place_file(&mut self, src: &Path, dest: &Path, mode: u32) -> Result<()> {
+        if self.direct {
+            return fs_utils::place_file(src, dest, mode);
+        }
+        let staged = self.stage(dest, None)?;
+        fs_utils::copy_file(src, &staged, mode)
+    }
+
+    /// 内容を書き込んだファイルを配置する予定を追加
+    pub fn place_content(&mut self, dest: &Path, content: &str, mode: u32) -> Result<()> {
+        if self.direct {
+            return fs_utils::place_content(dest, content, mode);
+        }
+        let staged = self.stage(dest, None)?;
+        fs_utils::write_file(&staged, content, mode)
+    }
+
+    /// シンボリックリンクを配置する予定を追加
+    pub fn place_symlink(&mut self, target: &Path, link: &Path) -> Result<()> {
+        if self.direct {
+            return fs_utils::place_symlink(target, link);
+        }
+        let staged = self.stage(link, Some(target))?;
+        fs_utils::make_symlink(target, &staged)
+    }
+
+    /// 予定を記録して既存のファイルを控え、用意する一時的なパスを返す
+    fn stage(&mut self, path: &Path, target: Option<&Path>) -> Result<PathBuf> {
+        let backup = exists(path).then(|| sibling(path, BACKUP_SUFFIX));
+        self.journal.entries.push(Entry {
+            path: path.to_path_buf(),
+            target: target.map(Path::to_path_buf),
+            backup: backup.clone(),
+        });
+        // 一時ファイルを作る前に記録し、中断しても次回に削除できるようにする
+        self.save()?;
+
+        if let Some(ref backup) = backup {
+            fs_utils::backup(path, backup)?;
+        }
+        Ok(sibling(path, STAGED_SUFFIX))
+    }
+
+    /// 用意したファイル・リンクで配置先を置き換えて確定
+    ///
+    /// 置き換えの途中で失敗した場合は、置き換え済みのものも元に戻します。
+    pub fn commit(mut self) -> Result<()> {
+        if self.direct {
+            self.finished = true;
+            return Ok(());
+        }
+        self.journal.phase = Phase::Committing;
+        self.save()?;
+        for entry in &self.journal.entries {
+            fs_utils::rename(&sibling(&entry.path, STAGED_SUFFIX), &entry.path)?;
+        }
+        self.finished = true;
+
+        // ここから先は失敗しても新しい状態のまま確定している
+        for entry in &self.journal.entries {
+            events::emit(match entry.target {
+                Some(ref target) => Event::SymlinkCreated {
+                    link: entry.path.clone(),
+                    target: target.clone(),
+                },
+                None => Event::FilePlaced {
+                    path: entry.path.clone(),
+                },
+            });
+        }
+        let cleaned = self
+            .journal
+            .entries
+            .iter()
+            .filter_map(|entry| entry.backup.as_deref())
+            .try_for_each(fs_utils::discard)
+            .and_then(|_| fs_utils::discard(&self.path));
+        if let Err(e) = cleaned {
+            events::warn(format!("置き換え前のファイルを削除できませんでした: {}", e));
+        }
+        Ok(())
+    }
+
+    /// ジャーナルを書き込む
+    fn save(&self) -> Result<()> {
+        let content = toml::to_string_pretty(&self.journal)?;
+        fs_utils::write_file(&self.path, &content, 0o644)
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        // 表示モードでは配置先に直接配置したため戻すものはない
+        if self.finished || self.direct {
+            return;
+        }
+        // 元に戻せなかった場合はジャーナルを残し、次回の実行時に再度試みる
+        match rollback(&self.journal).and_then(|_| fs_utils::discard(&self.path)) {
+            Ok(()) => events::warn(format!(
+                "{} のインストールを中断し、元の状態に戻しました",
+                self.journal.name
+            )),
+            Err(e) => events::warn(format!(
+                "{} のインストールを元に戻せませんでした（次回の実行時に再試行します）: {}",
+                self.journal.name, e
+            )),
+        }
+    }
+}
+
+/// 中断したトランザクションがあれば元に戻す（戻した場合はそのアプリ名を返す）
+pub fn recover() -> Result<Option<String>> {
+    recover_at(&journal_path())
+}
+
+/// 指定したジャーナルのトランザクションを元に戻す
+///
+/// 表示モードでは何もしません（記録したトランザクションは次回コマンドを実行するときに戻します）。
+pub fn recover_at(path: &Path) -> Result<Option<String>> {
+    if privilege::is_print_mode() {
+        return Ok(None);
+    }
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    let journal: Journal = toml::from_str(&content)?;
+    rollback(&journal)?;
+    fs_utils::discard(path)?;
+
+    events::warn(format!(
+        "前回中断した {} のインストールを元に戻しました",
+        journal.name
+    ));
+    Ok(Some(journal.name))
+}
+
+/// 元に戻せなかったジャーナルを退避（例: `journal.toml.broken`）して退避先を返す
+pub fn set_aside_at(path: &Path) -> Result<PathBuf> {
+    let aside = path.with_extension("toml.broken");
+    fs_utils::rename(path, &aside)?;
+    Ok(aside)
+}
+
+/// 記録した予定を後ろから取り消す
+///
+/// 途中で中断した取り消しを再度実行しても同じ結果になります。
+fn rollback(journal: &Journal) -> Result<()> {
+    for entry in journal.entries.iter().rev() {
+        let staged = sibling(&entry.path, STAGED_SUFFIX);
+        let replaced = journal.phase == Phase::Committing && !exists(&staged);
+
+        if !replaced {
+            // 配置先はそのまま
+            fs_utils::discard(&staged)?;
+            if let Some(ref backup) = entry.backup {
+                fs_utils::discard(backup)?;
+            }
+            continue;
+        }
+
+        match entry.backup {
+            Some(ref backup) if exists(backup) => fs_utils::rename(backup, &entry.path)?,
+            // 取り消し済み
+            Some(_) => {}
+            None => fs_utils::discard(&entry.path)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// 配置先に旧版のファイル・リンクがある状態を作成
+    fn setup(dir: &Path) -> (PathBuf, PathBuf, PathBuf) {
+        let file = dir.join("apps").join("app.AppImage");
+        let link = dir.join("bin").join("app");
+        let src = dir.join("new.AppImage");
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::create_dir_all(link.parent().unwrap()).unwrap();
+        fs::write(&file, "old").unwrap();
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+        fs::write(&src, "new").unwrap();
+        (src, file, link)
+    }
+
+    /// 一時的なファイル・控え・ジャーナルが残っていない
+    fn assert_clean(dir: &Path) {
+        for sub in ["apps", "bin"] {
+            let leftovers: Vec<_> = fs::read_dir(dir.join(sub))
+                .unwrap()
+                .filter_map(|e| e.ok())
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .filter(|name| name.starts_with('.'))
+                .collect();
+            assert!(leftovers.is_empty(), "{:?}", leftovers);
+        }
+        assert!(!dir.join("journal.toml").exists());
+    }
+
+    #[test]
+    fn test_sibling() {
+        assert_eq!(
+            sibling(Path::new("/opt/apps/Firefox.AppImage"), STAGED_SUFFIX),
+            PathBuf::from("/opt/apps/.Firefox.AppImage.an-new")
+        );
+    }
+
+    #[test]
+    fn test_commit() {
+        let temp = tempdir().unwrap();
+        let (src, file, link) = setup(temp.path());
+        let target = temp.path().join("apps").join("app-2.AppImage");
+        let desktop = temp.path().join("apps").join("app.desktop");
+
+        let mut transaction =
+            Transaction::begin_at(temp.path().join("journal.toml"), "app").unwrap();
+        transaction.place_file(&src, &file, 0o755).unwrap();
+        transaction.place_symlink(&target, &link).unwrap();
+        transaction
+            .place_content(&desktop, "[Desktop Entry]", 0o644)
+            .unwrap();
+
+        // 確定するまで配置先は変わらない
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+        assert_eq!(fs::read_link(&link).unwrap(), file);
+        assert!(!desktop.exists());
+        assert!(temp.path().join("journal.toml").exists());
+
+        transaction.commit().unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+        assert_eq!(fs::read_link(&link).unwrap(), target);
+        assert_eq!(fs::read_to_string(&desktop).unwrap(), "[Desktop Entry]");
+        assert_clean(temp.path());
+    }
+
+    #[test]
+    fn test_drop_rolls_back() {
+        let temp = tempdir().unwrap();
+        let (src, file, link) = setup(temp.path());
+        let desktop = temp.path().join("apps").join("app.desktop");
+
+        {
+            let mut transaction =
+                Transaction::begin_at(temp.path().join("journal.toml"), "app").unwrap();
+            transaction.place_file(&src, &file, 0o755).unwrap();
+            transaction.place_symlink(&src, &link).unwrap();
+            transaction.place_content(&desktop, "x", 0o644).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+        assert_eq!(fs::read_link(&link).unwrap(), file);
+        assert!(!desktop.exists());
+        assert_clean(temp.path());
+    }
+
+    #[test]
+    fn test_commit_failure_rolls_back() {
+        let temp = tempdir().unwrap();
+        let (src, file, link) = setup(temp.path());
+        let desktop = temp.path().join("apps").join("app.desktop");
+
+        let mut transaction =
+            Transaction::begin_at(temp.path().join("journal.toml"), "app").unwrap();
+        transaction.place_file(&src, &file, 0o755).unwrap();
+        transaction.place_content(&desktop, "x", 0o644).unwrap();
+        transaction.place_symlink(&src, &link).unwrap();
+        // 最後のリンクの置き換えに失敗させる
+        fs::remove_file(sibling(&link, STAGED_SUFFIX)).unwrap();
+
+        assert!(transaction.commit().is_err());
+        // 置き換え済みのファイルも元に戻る
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+        assert!(!desktop.exists());
+        assert_eq!(fs::read_link(&link).unwrap(), file);
+        assert_clean(temp.path());
+    }
+
+    #[test]
+    fn test_recover_interrupted_staging() {
+        let temp = tempdir().unwrap();
+        let (src, file, link) = setup(temp.path());
+        let journal = temp.path().join("journal.toml");
+
+        let mut transaction = Transaction::begin_at(journal.clone(), "app").unwrap();
+        transaction.place_file(&src, &file, 0o755).unwrap();
+        transaction.place_symlink(&src, &link).unwrap();
+        // 用意中に強制終了した（ドロップされない）
+        std::mem::forget(transaction);
+        assert!(sibling(&file, STAGED_SUFFIX).exists());
+
+        assert_eq!(recover_at(&journal).unwrap().as_deref(), Some("app"));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+        assert_eq!(fs::read_link(&link).unwrap(), file);
+        assert_clean(temp.path());
+
+        // 戻すものがなければ何もしない
+        assert_eq!(recover_at(&journal).unwrap(), None);
+    }
+
+    #[test]
+    fn test_print_mode_places_directly() {
+        let temp = tempdir().unwrap();
+        let (src, file, link) = setup(temp.path());
+        let desktop = temp.path().join("apps").join("app.desktop");
+        let journal = temp.path().join("journal.toml");
+        let mut print = config::Config::default();
+        print.set("privilege.method", "print").unwrap();
+
+        config::with_config(print, || {
+            let mut transaction = Transaction::begin_at(journal.clone(), "app").unwrap();
+            transaction.place_file(&src, &file, 0o755).unwrap();
+            transaction.place_symlink(&src, &link).unwrap();
+            transaction.place_content(&desktop, "x", 0o644).unwrap();
+            // ジャーナル・控え・一時的なファイルを作らない
+            assert_clean(temp.path());
+            transaction.commit().unwrap();
+
+            // 記録したトランザクションは表示モードでは戻さない
+            fs::write(&journal, "name = \"other\"\nphase = \"staging\"\n").unwrap();
+            assert_eq!(recover_at(&journal).unwrap(), None);
+            assert!(journal.exists());
+        });
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+        assert_eq!(fs::read_link(&link).unwrap(), src);
+        assert_eq!(fs::read_to_string(&desktop).unwrap(), "x");
+    }
+
+    #[test]
+    fn test_recover_interrupted_commit() {
+        let temp = tempdir().unwrap();
+        let (src, file, link) = setup(temp.path());
+        let desktop = temp.path().join("apps").join("app.desktop");
+        let journal = temp.path().join("journal.toml");
+
+        // ファイルとデスクトップエントリを置き換えた後、リンクの置き換え前に強制終了した状態
+        fs::rename(&file, sibling(&file, BACKUP_SUFFIX)).unwrap();
+        fs::rename(&src, &file).unwrap();
+        fs::write(&desktop, "x").unwrap();
+        std::os::unix::fs::symlink(&file, sibling(&link, STAGED_SUFFIX)).unwrap();
+        std::os::unix::fs::symlink(&file, sibling(&link, BACKUP_SUFFIX)).unwrap();
+        let content = toml::to_string_pretty(&Journal {
+            name: "app".to_string(),
+            phase: Phase::Committing,
+            entries: vec![
+                Entry {
+                    path: file.clone(),
+                    target: None,
+                    backup: Some(sibling(&file, BACKUP_SUFFIX)),
+                },
+                Entry {
+                    path: desktop.clone(),
+                    target: None,
+                    backup: None,
+                },
+                Entry {
+                    path: link.clone(),
+                    target: Some(file.clone()),
+                    backup: Some(sibling(&link, BACKUP_SUFFIX)),
+                },
+            ],
+        })
+        .unwrap();
+        fs::write(&journal, content).unwrap();
+
+        // 次のトランザクションの開始時に元に戻す
+        let transaction = Transaction::begin_at(journal.clone(), "other").unwrap();
+        transaction.commit().unwrap();
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+        assert!(!desktop.exists());
+        assert_eq!(fs::read_link(&link).unwrap(), file);
+        assert_clean(temp.path());
+    }
+}
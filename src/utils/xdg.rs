@@ -0,0 +1,90 @@
+//! XDG Base Directory ユーティリティ
+//!
+//! ANが使用する全てのディレクトリはこのモジュールを経由して解決します。
+//! `XDG_*_HOME` が未設定・空・相対パスの場合は仕様に従い `$HOME` 基準のデフォルトを使用します。
+
+use std::path::PathBuf;
+
+/// 環境変数の値を解決（絶対パスのみ有効）
+fn resolve(value: Option<String>, default: impl FnOnce() -> PathBuf) -> PathBuf {
+    match value {
+        Some(v) if !v.is_empty() && PathBuf::from(&v).is_absolute() => PathBuf::from(v),
+        _ => default(),
+    }
+}
+
+/// XDG環境変数を解決
+fn xdg_env(var: &str, default: impl FnOnce() -> PathBuf) -> PathBuf {
+    resolve(std::env::var(var).ok(), default)
+}
+
+/// ホームディレクトリ（`$HOME`）
+pub fn home_dir() -> PathBuf {
+    resolve(std::env::var("HOME").ok(), || {
+        dirs::home_dir().expect("ホームディレクトリが見つかりません")
+    })
+}
+
+/// 設定ディレクトリ（`$XDG_CONFIG_HOME`、デフォルト: ~/.config）
+pub fn config_home() -> PathBuf {
+    xdg_env("XDG_CONFIG_HOME", || home_dir().join(".config"))
+}
+
+/// データディレクトリ（`$XDG_DATA_HOME`、デフォルト: ~/.local/share）
+pub fn data_home() -> PathBuf {
+    xdg_env("XDG_DATA_HOME", || home_dir().join(".local").join("share"))
+}
+
+/// 状態ディレクトリ（`$XDG_STATE_HOME`、デフォルト: ~/.local/state）
+pub fn state_home() -> PathBuf {
+    xdg_env("XDG_STATE_HOME", || home_dir().join(".local").join("state"))
+}
+
+/// キャッシュディレクトリ（`$XDG_CACHE_HOME`、デフォルト: ~/.cache）
+pub fn cache_home() -> PathBuf {
+    xdg_env("XDG_CACHE_HOME", || home_dir().join(".cache"))
+}
+
+/// 実行ファイルディレクトリ（`$XDG_BIN_HOME`、デフォルト: ~/.local/bin）
+pub fn bin_home() -> PathBuf {
+    xdg_env("XDG_BIN_HOME", || home_dir().join(".local").join("bin"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_absolute() {
+        let dir = resolve(Some("/custom/data".to_string()), || {
+            PathBuf::from("/default")
+        });
+        assert_eq!(dir, PathBuf::from("/custom/data"));
+    }
+
+    #[test]
+    fn test_resolve_unset_or_empty() {
+        assert_eq!(
+            resolve(None, || PathBuf::from("/default")),
+            PathBuf::from("/default")
+        );
+        assert_eq!(
+            resolve(Some(String::new()), || PathBuf::from("/default")),
+            PathBuf::from("/default")
+        );
+    }
+
+    #[test]
+    fn test_resolve_relative_is_ignored() {
+        let dir = resolve(Some("relative/path".to_string()), || {
+            PathBuf::from("/default")
+        });
+        assert_eq!(dir, PathBuf::from("/default"));
+    }
+
+    #[test]
+    fn test_bin_home() {
+        let dir = bin_home();
+        assert!(dir.is_absolute());
+    }
+}
@@ -0,0 +1,274 @@
+//! apply / export コマンド統合テスト
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+/// テスト用のアプリDBを作成するヘルパー
+fn setup_test_db() -> tempfile::TempDir {
+    let dir = tempdir().unwrap();
+
+    let firefox_toml = r#"
+[app]
+name = "firefox"
+description = "Mozilla Firefox Web Browser"
+
+[source]
+type = "appimage"
+url = "https://example.com/firefox.AppImage"
+architecture = ["x86_64", "aarch64"]
+"#;
+
+    let obsidian_toml = r#"
+[app]
+name = "obsidian"
+description = "Obsidian - A second brain, for you, forever"
+
+[source]
+type = "appimage"
+url = "https://example.com/Obsidian-{version}.AppImage"
+architecture = ["x86_64", "aarch64"]
+
+[metadata]
+version = "1.5.3"
+"#;
+
+    let blender_toml = r#"
+[app]
+name = "blender"
+description = "Blender - 3D Creation Suite"
+
+[source]
+type = "flatpak"
+flatpak_id = "org.blender.Blender"
+architecture = ["x86_64", "aarch64"]
+"#;
+
+    let gimp_toml = r#"
+[app]
+name = "gimp"
+description = "GNU Image Manipulation Program"
+
+[source]
+type = "appimage"
+url = "https://example.com/GIMP.AppImage"
+architecture = ["x86_64", "aarch64"]
+
+[[alternatives]]
+type = "flatpak"
+flatpak_id = "org.gimp.GIMP"
+architecture = ["x86_64", "aarch64"]
+"#;
+
+    fs::write(dir.path().join("firefox.toml"), firefox_toml).unwrap();
+    fs::write(dir.path().join("obsidian.toml"), obsidian_toml).unwrap();
+    fs::write(dir.path().join("blender.toml"), blender_toml).unwrap();
+    fs::write(dir.path().join("gimp.toml"), gimp_toml).unwrap();
+
+    dir
+}
+
+/// AppImageのインストール済み状態（本体・コマンド・インストール記録）を作成するヘルパー
+fn install_appimage(home: &Path, name: &str, version: Option<&str>) {
+    let appimage = home.join("Applications").join(format!("{}.AppImage", name));
+    let link = home.join(".local/bin").join(name);
+    fs::create_dir_all(appimage.parent().unwrap()).unwrap();
+    fs::create_dir_all(link.parent().unwrap()).unwrap();
+    fs::write(&appimage, "dummy").unwrap();
+    std::os::unix::fs::symlink(&appimage, &link).unwrap();
+
+    let records = home.join(".local/state/an/installed");
+    fs::create_dir_all(&records).unwrap();
+    let version = version
+        .map(|v| format!("version = \"{}\"\n", v))
+        .unwrap_or_default();
+    fs::write(
+        records.join(format!("{}.toml", name)),
+        format!(
+            "name = \"{0}\"\nsource_type = \"appimage\"\nidentifier = \"{0}\"\n{1}files = [\"{2}\", \"{3}\"]\n",
+            name,
+            version,
+            appimage.display(),
+            link.display()
+        ),
+    )
+    .unwrap();
+}
+
+/// HOMEとXDGディレクトリを一時ディレクトリに隔離したコマンド
+fn an(home: &Path, db_dir: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_DATA_HOME", home.join(".local/share"))
+        .env("XDG_STATE_HOME", home.join(".local/state"))
+        .env("XDG_CACHE_HOME", home.join(".cache"))
+        .env("XDG_BIN_HOME", home.join(".local/bin"))
+        .env("AN_DB_DIR", db_dir)
+        .env_remove("AN_CONFIG")
+        .env_remove("AN_APPS_DIR")
+        .env_remove("AN_BIN_DIR");
+    cmd
+}
+
+/// export でインストール記録をAnfileの形式で書き出す
+#[test]
+fn test_export() {
+    let db_dir = setup_test_db();
+    let home = tempdir().unwrap();
+    install_appimage(home.path(), "firefox", Some("120.0"));
+    install_appimage(home.path(), "obsidian", Some("1.5.0"));
+    install_appimage(home.path(), "tool", None);
+
+    an(home.path(), db_dir.path())
+        .arg("export")
+        .assert()
+        .success()
+        // URLでバージョンを固定できるソースのみバージョンを含める
+        .stdout(predicate::str::contains(
+            "[[apps]]\nname = \"firefox\"\nsource = \"appimage\"\n",
+        ))
+        .stdout(predicate::str::contains(
+            "[[apps]]\nname = \"obsidian\"\nversion = \"1.5.0\"\nsource = \"appimage\"\n",
+        ))
+        .stdout(predicate::str::contains("tool").not())
+        .stderr(predicate::str::contains(
+            "tool はアプリDBにないため書き出しません",
+        ));
+
+    let anfile = home.path().join("Anfile");
+    an(home.path(), db_dir.path())
+        .arg("export")
+        .arg(&anfile)
+        .assert()
+        .success();
+
+    // 書き出した状態を適用しても変更はない
+    an(home.path(), db_dir.path())
+        .arg("apply")
+        .arg(&anfile)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("変更はありません（2 件"));
+}
+
+/// apply --dry-run で計画のみ表示
+#[test]
+fn test_apply_dry_run() {
+    let db_dir = setup_test_db();
+    let home = tempdir().unwrap();
+    install_appimage(home.path(), "firefox", None);
+    install_appimage(home.path(), "obsidian", Some("1.5.0"));
+    install_appimage(home.path(), "tool", None);
+
+    let anfile = home.path().join("Anfile");
+    fs::write(
+        &anfile,
+        "[[apps]]\nname = \"firefox\"\n\n[[apps]]\nname = \"obsidian\"\n\n[[apps]]\nname = \"blender\"\n",
+    )
+    .unwrap();
+
+    an(home.path(), db_dir.path())
+        .args(["apply", "--dry-run", "--prune"])
+        .arg(&anfile)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("3 件の変更があります"))
+        .stdout(predicate::str::contains("+ blender"))
+        .stdout(predicate::str::contains("~ obsidian 1.5.0 -> 1.5.3"))
+        .stdout(predicate::str::contains("- tool (AppImage)"))
+        .stdout(predicate::str::contains("変更なし: firefox"))
+        .stdout(predicate::str::contains("続行しますか").not());
+
+    // 計画のみのため何も変わらない
+    assert!(home.path().join(".local/bin/tool").is_symlink());
+}
+
+/// apply でエイリアスを作成し、remove で削除する
+#[test]
+fn test_apply_aliases() {
+    let db_dir = setup_test_db();
+    let home = tempdir().unwrap();
+    install_appimage(home.path(), "firefox", None);
+
+    let anfile = home.path().join("Anfile");
+    fs::write(
+        &anfile,
+        "[[apps]]\nname = \"firefox\"\naliases = [\"ff\"]\n",
+    )
+    .unwrap();
+
+    an(home.path(), db_dir.path())
+        .arg("apply")
+        .arg(&anfile)
+        .write_stdin("y\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("= firefox（エイリアス: ff）"))
+        .stdout(predicate::str::contains("1 件の変更を適用しました"));
+
+    let alias = home.path().join(".local/bin/ff");
+    assert_eq!(
+        fs::read_link(&alias).unwrap(),
+        home.path().join(".local/bin/firefox")
+    );
+    let record =
+        fs::read_to_string(home.path().join(".local/state/an/installed/firefox.toml")).unwrap();
+    assert!(record.contains("aliases = [\"ff\"]"), "{}", record);
+
+    an(home.path(), db_dir.path())
+        .args(["remove", "firefox"])
+        .assert()
+        .success();
+    assert!(!alias.is_symlink());
+}
+
+/// 形式の変更で新しい形式のインストールに失敗した場合は元の形式を残す
+#[test]
+fn test_apply_source_change_failure_keeps_old() {
+    let db_dir = setup_test_db();
+    let home = tempdir().unwrap();
+    install_appimage(home.path(), "gimp", None);
+
+    let anfile = home.path().join("Anfile");
+    fs::write(&anfile, "[[apps]]\nname = \"gimp\"\nsource = \"flatpak\"\n").unwrap();
+
+    // flatpak が使えない環境ではFlatpakのインストールに失敗する
+    let mut cmd = an(home.path(), db_dir.path());
+    cmd.env("PATH", home.path().join("empty-path"));
+    cmd.arg("apply")
+        .arg(&anfile)
+        .write_stdin("y\n")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("~ gimp (AppImage -> Flatpak)"));
+
+    let bin = home.path().join(".local/bin");
+    assert_eq!(
+        fs::read_link(bin.join("gimp")).unwrap(),
+        home.path().join("Applications/gimp.AppImage")
+    );
+    assert!(home.path().join("Applications/gimp.AppImage").exists());
+    assert!(!bin.join(".gimp.an-old").is_symlink());
+    let record =
+        fs::read_to_string(home.path().join(".local/state/an/installed/gimp.toml")).unwrap();
+    assert!(record.contains("source_type = \"appimage\""), "{}", record);
+}
+
+/// AnfileのアプリがアプリDBにない場合はエラー
+#[test]
+fn test_apply_unknown_app() {
+    let db_dir = setup_test_db();
+    let home = tempdir().unwrap();
+    let anfile = home.path().join("Anfile");
+    fs::write(&anfile, "[[apps]]\nname = \"unknownapp\"\n").unwrap();
+
+    an(home.path(), db_dir.path())
+        .arg("apply")
+        .arg(&anfile)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("E003"));
+}
@@ -160,3 +160,23 @@ fn test_link_alias() {
     let mut cmd = Command::cargo_bin("an").unwrap();
     cmd.args(["l", "--help"]).assert().success();
 }
+
+/// db lint: 同梱のアプリDBは問題なし、壊れたエントリがあれば失敗
+#[test]
+fn test_db_lint_exit_code() {
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.args(["db", "lint", "apps"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("問題はありません"));
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("broken.toml"), "[app").unwrap();
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.args(["db", "lint"])
+        .arg(dir.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("broken.toml"))
+        .stderr(predicate::str::contains("E905"));
+}
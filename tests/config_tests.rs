@@ -0,0 +1,172 @@
+//! config コマンド統合テスト
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::tempdir;
+
+/// config list で全キーが表示される
+#[test]
+fn test_config_list() {
+    let dir = tempdir().unwrap();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_CONFIG", dir.path().join("config.toml"))
+        .args(["config", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("paths.apps_dir"))
+        .stdout(predicate::str::contains("flatpak.remote = flathub"));
+}
+
+/// config set で設定ファイルに保存され、config get で取得できる
+#[test]
+fn test_config_set_and_get() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_CONFIG", &config_path)
+        .args(["config", "set", "paths.apps_dir", "/opt/an-apps"])
+        .assert()
+        .success();
+
+    let content = fs::read_to_string(&config_path).unwrap();
+    assert!(content.contains("apps_dir = \"/opt/an-apps\""));
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_CONFIG", &config_path)
+        .args(["config", "get", "paths.apps_dir"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("/opt/an-apps"));
+}
+
+/// --config を指定した場合は config set もそのファイルに保存する
+#[test]
+fn test_config_set_with_config_option() {
+    let dir = tempdir().unwrap();
+    let default_path = dir.path().join("config.toml");
+    let custom_path = dir.path().join("custom.toml");
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_CONFIG", &default_path)
+        .arg("--config")
+        .arg(&custom_path)
+        .args(["config", "set", "flatpak.remote", "custom-remote"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("custom.toml"));
+
+    assert!(fs::read_to_string(&custom_path)
+        .unwrap()
+        .contains("remote = \"custom-remote\""));
+    assert!(!default_path.exists());
+
+    // 読み込みも同じファイルから行う
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_CONFIG", &default_path)
+        .arg("--config")
+        .arg(&custom_path)
+        .args(["config", "get", "flatpak.remote"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("custom-remote"));
+}
+
+/// 環境変数・CLIオプションは設定ファイルより優先される
+#[test]
+fn test_config_overrides() {
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    fs::write(&config_path, "[flatpak]\nremote = \"from-file\"\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_CONFIG", &config_path)
+        .env("AN_FLATPAK_REMOTE", "from-env")
+        .args(["config", "get", "flatpak.remote"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("from-env"));
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_CONFIG", &config_path)
+        .env("AN_FLATPAK_REMOTE", "from-env")
+        .args([
+            "-o",
+            "flatpak.remote=from-cli",
+            "config",
+            "get",
+            "flatpak.remote",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("from-cli"));
+}
+
+/// 不明なキーはE904エラー
+#[test]
+fn test_config_unknown_key() {
+    let dir = tempdir().unwrap();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_CONFIG", dir.path().join("config.toml"))
+        .args(["config", "get", "paths.unknown"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("E904"));
+}
+
+/// XDG環境変数に従ってデフォルトパスが解決される
+#[test]
+fn test_config_xdg_paths() {
+    let home = tempdir().unwrap();
+    let xdg = home.path().join("xdg");
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("HOME", home.path())
+        .env_remove("AN_CONFIG")
+        .env_remove("AN_DB_DIR")
+        .env("XDG_CONFIG_HOME", xdg.join("config"))
+        .env("XDG_STATE_HOME", xdg.join("state"))
+        .env("XDG_CACHE_HOME", xdg.join("cache"))
+        .env("XDG_BIN_HOME", xdg.join("bin"))
+        .args(["config", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "paths.bin_dir = {}",
+            xdg.join("bin").display()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "paths.db_dir = {}",
+            xdg.join("config/an/apps").display()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "paths.state_dir = {}",
+            xdg.join("state/an").display()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "paths.apps_dir = {}",
+            home.path().join("Applications").display()
+        )));
+}
+
+/// 不正な特権昇格方法は拒否される
+#[test]
+fn test_config_invalid_privilege_method() {
+    let dir = tempdir().unwrap();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_CONFIG", dir.path().join("config.toml"))
+        .args(["config", "set", "privilege.method", "su"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("E903"));
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_CONFIG", dir.path().join("config.toml"))
+        .args(["config", "set", "privilege.method", "doas"])
+        .assert()
+        .success();
+}
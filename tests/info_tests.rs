@@ -3,6 +3,7 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 use std::fs;
+use std::path::Path;
 use tempfile::tempdir;
 
 /// テスト用のアプリDBを作成するヘルパー
@@ -135,3 +136,73 @@ fn test_info_shows_architecture() {
         .stdout(predicate::str::contains("x86_64"))
         .stdout(predicate::str::contains("aarch64"));
 }
+
+/// HOMEとXDGディレクトリを一時ディレクトリに隔離し、firefox 119.0 をインストール済みにする
+fn install_old_firefox(cmd: &mut assert_cmd::Command, home: &Path) {
+    let appimage = home.join("Applications").join("firefox.AppImage");
+    let link = home.join(".local/bin").join("firefox");
+    fs::create_dir_all(appimage.parent().unwrap()).unwrap();
+    fs::create_dir_all(link.parent().unwrap()).unwrap();
+    fs::write(&appimage, vec![0u8; 2048]).unwrap();
+    std::os::unix::fs::symlink(&appimage, &link).unwrap();
+
+    let records = home.join(".local/state/an/installed");
+    fs::create_dir_all(&records).unwrap();
+    fs::write(
+        records.join("firefox.toml"),
+        format!(
+            "name = \"firefox\"\nsource_type = \"appimage\"\nidentifier = \"firefox\"\nversion = \"119.0\"\nfiles = [\"{}\", \"{}\"]\n",
+            appimage.display(),
+            link.display()
+        ),
+    )
+    .unwrap();
+
+    cmd.env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_DATA_HOME", home.join(".local/share"))
+        .env("XDG_STATE_HOME", home.join(".local/state"))
+        .env("XDG_CACHE_HOME", home.join(".cache"))
+        .env("XDG_BIN_HOME", home.join(".local/bin"))
+        .env_remove("AN_CONFIG")
+        .env_remove("AN_APPS_DIR")
+        .env_remove("AN_BIN_DIR");
+}
+
+/// info コマンドでインストール状態・配置先・ディスク使用量を表示
+#[test]
+fn test_info_shows_installed_status() {
+    let db_dir = setup_test_db();
+    let home = tempdir().unwrap();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    install_old_firefox(&mut cmd, home.path());
+    cmd.env("AN_DB_DIR", db_dir.path())
+        .args(["info", "firefox"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "状態: 更新あり（AppImage 119.0 → 120.0）",
+        ))
+        .stdout(predicate::str::contains("インストール先:"))
+        .stdout(predicate::str::contains("firefox.AppImage"))
+        .stdout(predicate::str::contains("コマンド:"))
+        .stdout(predicate::str::contains("ディスク使用量: 2.0 KB"));
+}
+
+/// search の結果にインストール状態を表示
+#[test]
+fn test_search_shows_installed_status() {
+    let db_dir = setup_test_db();
+    let home = tempdir().unwrap();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    install_old_firefox(&mut cmd, home.path());
+    cmd.env("AN_DB_DIR", db_dir.path())
+        .args(["search", "firefox"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "状態: 更新あり（AppImage 119.0 → 120.0）",
+        ));
+}
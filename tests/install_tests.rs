@@ -3,6 +3,7 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 use std::fs;
+use std::path::Path;
 use tempfile::tempdir;
 
 /// テスト用のアプリDBを作成するヘルパー
@@ -29,6 +30,19 @@ categories = ["Network", "WebBrowser"]
     dir
 }
 
+/// HOMEとXDGディレクトリを一時ディレクトリに隔離するヘルパー
+fn isolate_home(cmd: &mut assert_cmd::Command, home: &Path) {
+    cmd.env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_DATA_HOME", home.join(".local/share"))
+        .env("XDG_STATE_HOME", home.join(".local/state"))
+        .env("XDG_CACHE_HOME", home.join(".cache"))
+        .env("XDG_BIN_HOME", home.join(".local/bin"))
+        .env_remove("AN_CONFIG")
+        .env_remove("AN_APPS_DIR")
+        .env_remove("AN_BIN_DIR");
+}
+
 /// install で存在しないファイルを指定した場合のエラー
 #[test]
 fn test_install_file_not_found() {
@@ -67,6 +81,20 @@ fn test_install_app_not_in_db() {
         .stderr(predicate::str::contains("E003").or(predicate::str::contains("見つかりません")));
 }
 
+/// install で名前を打ち間違えた場合は近いアプリ名を提示する
+#[test]
+fn test_install_suggests_similar_name() {
+    let db_dir = setup_test_db();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_DB_DIR", db_dir.path())
+        .args(["install", "firefx"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("もしかして: firefox"))
+        .stderr(predicate::str::contains("E003"));
+}
+
 /// install エイリアス 'i' のテスト
 #[test]
 fn test_install_alias_i() {
@@ -87,7 +115,9 @@ fn test_install_name_option() {
     // --name オプションが正しくパースされることを確認
     // (実際のインストールはファイルが無効なので失敗するが、オプションのパースは成功する)
     let mut cmd = Command::cargo_bin("an").unwrap();
-    cmd.args(["install", file_path.to_str().unwrap(), "--name", "myapp"])
+    isolate_home(&mut cmd, dir.path());
+    let _ = cmd
+        .args(["install", file_path.to_str().unwrap(), "--name", "myapp"])
         .assert();
     // パースエラーが出ないことを確認
 }
@@ -100,7 +130,9 @@ fn test_install_desktop_option() {
     fs::write(&file_path, "dummy").unwrap();
 
     let mut cmd = Command::cargo_bin("an").unwrap();
-    cmd.args(["install", file_path.to_str().unwrap(), "--desktop"])
+    isolate_home(&mut cmd, dir.path());
+    let _ = cmd
+        .args(["install", file_path.to_str().unwrap(), "--desktop"])
         .assert();
     // パースエラーが出ないことを確認
 }
@@ -113,11 +145,103 @@ fn test_install_move_option() {
     fs::write(&file_path, "dummy").unwrap();
 
     let mut cmd = Command::cargo_bin("an").unwrap();
-    cmd.args(["install", file_path.to_str().unwrap(), "--move"])
+    isolate_home(&mut cmd, dir.path());
+    let _ = cmd
+        .args(["install", file_path.to_str().unwrap(), "--move"])
         .assert();
     // パースエラーが出ないことを確認
 }
 
+/// AppImageがXDGディレクトリに従って配置される
+#[test]
+fn test_install_appimage_into_xdg_dirs() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("Tool-1.0-x86_64.AppImage");
+    fs::write(&file_path, "dummy").unwrap();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    isolate_home(&mut cmd, dir.path());
+    cmd.args(["install", file_path.to_str().unwrap(), "--desktop"])
+        .assert()
+        .success();
+
+    let installed = dir.path().join("Applications/Tool-1.0-x86_64.AppImage");
+    assert!(installed.exists());
+    let link = dir.path().join(".local/bin/tool");
+    assert_eq!(fs::read_link(&link).unwrap(), installed);
+    assert!(dir
+        .path()
+        .join(".local/share/applications/tool.desktop")
+        .exists());
+
+    // インストール記録が状態ディレクトリに保存される
+    let record =
+        fs::read_to_string(dir.path().join(".local/state/an/installed/tool.toml")).unwrap();
+    assert!(record.contains("source_type = \"appimage\""));
+    assert!(record.contains("system = false"));
+
+    // 記録を元に削除できる
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    isolate_home(&mut cmd, dir.path());
+    cmd.args(["remove", "tool"]).assert().success();
+    assert!(!installed.exists());
+    assert!(!link.is_symlink());
+    assert!(!dir
+        .path()
+        .join(".local/state/an/installed/tool.toml")
+        .exists());
+}
+
+/// 複数のアプリ・マニフェストを一括インストールし、失敗があっても続行して一覧を表示
+#[test]
+fn test_install_multiple_with_manifest() {
+    let dir = tempdir().unwrap();
+    let db_dir = setup_test_db();
+    let first = dir.path().join("First-1.0-x86_64.AppImage");
+    let second = dir.path().join("Second-2.0-x86_64.AppImage");
+    fs::write(&first, "dummy").unwrap();
+    fs::write(&second, "dummy").unwrap();
+    let manifest = dir.path().join("team-apps.toml");
+    fs::write(
+        &manifest,
+        format!(
+            "[[apps]]\nname = \"firefx\"\n\n[[apps]]\nname = \"{}\"\n",
+            second.display()
+        ),
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    isolate_home(&mut cmd, dir.path());
+    cmd.env("AN_DB_DIR", db_dir.path())
+        .args(["install", first.to_str().unwrap(), "-f"])
+        .arg(&manifest)
+        .write_stdin("y\n")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("=== インストール結果 ==="))
+        .stdout(predicate::str::is_match("First-1.0-x86_64.AppImage +✓ 成功").unwrap())
+        .stdout(predicate::str::is_match("firefx +✗ 失敗  E003: .*もしかして: firefox").unwrap())
+        .stdout(predicate::str::is_match("Second-2.0-x86_64.AppImage +✓ 成功").unwrap())
+        .stderr(predicate::str::contains(
+            "E010: 3 件中 1 件のインストールに失敗しました",
+        ));
+
+    // 失敗したアプリがあっても残りはインストールされる
+    assert!(dir.path().join(".local/bin/first").is_symlink());
+    assert!(dir.path().join(".local/bin/second").is_symlink());
+}
+
+/// 複数のアプリを指定した場合は --name を使えない
+#[test]
+fn test_install_multiple_rejects_name() {
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.args(["install", "firefox", "gimp", "--name", "browser"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--name"));
+}
+
 /// install 引数なしでエラー
 #[test]
 fn test_install_no_args() {
@@ -127,3 +251,166 @@ fn test_install_no_args() {
         .failure()
         .stderr(predicate::str::contains("required"));
 }
+
+/// 指定したパスに応答する簡易HTTPサーバー（ベースURLを返す）
+fn serve(routes: Vec<(&'static str, &'static [u8])>) -> String {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            let request = String::from_utf8_lossy(&request);
+            let path = request.split_whitespace().nth(1).unwrap_or("");
+            let response = match routes.iter().find(|(p, _)| *p == path) {
+                Some((_, body)) => {
+                    let mut response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    )
+                    .into_bytes();
+                    response.extend_from_slice(body);
+                    response
+                }
+                None => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_vec(),
+            };
+            let _ = stream.write_all(&response);
+        }
+    });
+
+    base
+}
+
+/// 複数のアプリのダウンロードは並列に行い、インストールは1件ずつ行う
+#[test]
+fn test_install_multiple_downloads_in_parallel() {
+    let dir = tempdir().unwrap();
+    let db_dir = tempdir().unwrap();
+    let base = serve(vec![
+        ("/alpha.AppImage", b"alpha"),
+        ("/beta.AppImage", b"beta"),
+    ]);
+    for name in ["alpha", "beta", "gamma"] {
+        fs::write(
+            db_dir.path().join(format!("{}.toml", name)),
+            format!(
+                "[app]\nname = \"{0}\"\ndescription = \"{0}\"\n\n[source]\ntype = \"appimage\"\nurl = \"{1}/{0}.AppImage\"\narchitecture = [\"x86_64\", \"aarch64\"]\n",
+                name, base
+            ),
+        )
+        .unwrap();
+    }
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    isolate_home(&mut cmd, dir.path());
+    cmd.env("AN_DB_DIR", db_dir.path())
+        .env("AN_INSTALL_PARALLEL_DOWNLOADS", "2")
+        .args(["install", "alpha", "beta", "gamma"])
+        .write_stdin("y\n")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("3 件を並列にダウンロード中"))
+        .stdout(predicate::str::is_match("alpha +✓ 完了").unwrap())
+        .stdout(predicate::str::is_match("gamma +✗ 失敗").unwrap())
+        .stdout(predicate::str::is_match("gamma +✗ 失敗  E004: ").unwrap())
+        .stdout(predicate::str::contains("ダウンロード中: ").not());
+
+    let alpha = dir.path().join("Applications/alpha.AppImage");
+    assert_eq!(fs::read(&alpha).unwrap(), b"alpha");
+    assert_eq!(
+        fs::read_link(dir.path().join(".local/bin/beta")).unwrap(),
+        dir.path().join("Applications/beta.AppImage")
+    );
+    // 使用済みのダウンロードとプロセスの作業ディレクトリは残らない
+    let temp = dir.path().join(".cache/an/tmp");
+    assert_eq!(fs::read_dir(temp).unwrap().count(), 0);
+}
+
+/// 別の an が変更を伴うコマンドを実行中の場合は E906 で終了する
+#[test]
+fn test_install_while_another_instance_is_running() {
+    let dir = tempdir().unwrap();
+    let lock =
+        an_installer::utils::lock::acquire_at(&dir.path().join(".config/an/.apps.lock")).unwrap();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    isolate_home(&mut cmd, dir.path());
+    cmd.args(["install", "firefox"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "E906: 別の an が実行中です（PID {}）",
+            std::process::id()
+        )));
+
+    // 変更を伴わないコマンドは実行できる
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    isolate_home(&mut cmd, dir.path());
+    cmd.arg("export").assert().success();
+
+    drop(lock);
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    isolate_home(&mut cmd, dir.path());
+    cmd.env("AN_DB_DIR", setup_test_db().path())
+        .args(["install", "firefx"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("E003"));
+}
+
+/// 中断したインストールは次回の実行時に元に戻す
+#[test]
+fn test_install_rolls_back_interrupted_install() {
+    let dir = tempdir().unwrap();
+    let apps = dir.path().join("Applications");
+    let installed = apps.join("tool.AppImage");
+    let backup = apps.join(".tool.AppImage.an-old");
+    let link = dir.path().join(".local/bin/tool");
+    fs::create_dir_all(&apps).unwrap();
+    fs::create_dir_all(link.parent().unwrap()).unwrap();
+
+    // 本体を置き換えた後、リンクを作成する前に強制終了した状態
+    fs::write(&installed, "new").unwrap();
+    fs::write(&backup, "old").unwrap();
+    std::os::unix::fs::symlink(&installed, dir.path().join(".local/bin/.tool.an-new")).unwrap();
+    let state = dir.path().join(".local/state/an");
+    fs::create_dir_all(&state).unwrap();
+    fs::write(
+        state.join("journal.toml"),
+        format!(
+            "name = \"tool\"\nphase = \"committing\"\n\n[[entries]]\npath = \"{}\"\nbackup = \"{}\"\n\n[[entries]]\npath = \"{}\"\ntarget = \"{}\"\n",
+            installed.display(),
+            backup.display(),
+            link.display(),
+            installed.display()
+        ),
+    )
+    .unwrap();
+
+    let file_path = dir.path().join("Other.AppImage");
+    fs::write(&file_path, "dummy").unwrap();
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    isolate_home(&mut cmd, dir.path());
+    cmd.args(["install", file_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "前回中断した tool のインストールを元に戻しました",
+        ));
+
+    assert_eq!(fs::read_to_string(&installed).unwrap(), "old");
+    assert!(!backup.exists());
+    assert!(!link.is_symlink());
+    assert!(!dir.path().join(".local/bin/.tool.an-new").is_symlink());
+    assert!(!state.join("journal.toml").exists());
+    // 新しいインストールは通常どおり行われる
+    assert!(dir.path().join(".local/bin/other").is_symlink());
+}
@@ -0,0 +1,162 @@
+//! repo コマンド統合テスト
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+/// テスト用のアプリ定義を作成するヘルパー
+fn write_app(dir: &Path, name: &str, description: &str) {
+    fs::create_dir_all(dir).unwrap();
+    let content = format!(
+        r#"
+[app]
+name = "{}"
+description = "{}"
+
+[source]
+type = "flatpak"
+flatpak_id = "org.example.{}"
+architecture = ["x86_64"]
+"#,
+        name, description, name
+    );
+    fs::write(dir.join(format!("{}.toml", name)), content).unwrap();
+}
+
+fn an(db_dir: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_DB_DIR", db_dir);
+    cmd
+}
+
+/// ローカルディレクトリのリポジトリを追加して検索・詳細に表示
+#[test]
+fn test_repo_add_path_and_info() {
+    let db_dir = tempdir().unwrap();
+    let team = tempdir().unwrap();
+    write_app(&team.path().join("apps"), "internal-tool", "Team tool");
+
+    an(db_dir.path())
+        .args(["repo", "add", "team"])
+        .arg(team.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("team: 1 件追加"));
+
+    an(db_dir.path())
+        .args(["info", "internal-tool"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("リポジトリ: team"));
+
+    an(db_dir.path())
+        .args(["search", "tool"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("リポジトリ: team"));
+
+    an(db_dir.path())
+        .args(["repo", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("team"))
+        .stdout(predicate::str::contains("main"));
+
+    an(db_dir.path())
+        .args(["repo", "remove", "team"])
+        .assert()
+        .success();
+
+    an(db_dir.path())
+        .args(["info", "internal-tool"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("見つかりません"));
+}
+
+/// 優先度の高いリポジトリのエントリを使用
+#[test]
+fn test_repo_priority_on_collision() {
+    let db_dir = tempdir().unwrap();
+    let high = tempdir().unwrap();
+    let low = tempdir().unwrap();
+    write_app(high.path(), "firefox", "High priority build");
+    write_app(low.path(), "firefox", "Low priority build");
+
+    an(db_dir.path())
+        .args(["repo", "add", "low", "--priority", "1"])
+        .arg(low.path())
+        .assert()
+        .success();
+    an(db_dir.path())
+        .args(["repo", "add", "high", "--priority", "20"])
+        .arg(high.path())
+        .assert()
+        .success();
+
+    an(db_dir.path())
+        .args(["info", "firefox"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("High priority build"))
+        .stdout(predicate::str::contains("リポジトリ: high"));
+}
+
+/// 同期に失敗したリポジトリは登録されない
+#[test]
+fn test_repo_add_missing_path_fails() {
+    let db_dir = tempdir().unwrap();
+
+    an(db_dir.path())
+        .args(["repo", "add", "broken", "/nonexistent/an-repo"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("E403"));
+
+    an(db_dir.path())
+        .args(["repo", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("broken").not());
+}
+
+/// 登録済みの名前は追加できない
+#[test]
+fn test_repo_add_duplicate() {
+    let db_dir = tempdir().unwrap();
+
+    an(db_dir.path())
+        .args(["repo", "add", "main", "/tmp"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("E402"));
+}
+
+/// `an sync <repo> --json` でアプリごとの変更をJSONで出力
+#[test]
+fn test_sync_json_reports_changes() {
+    let db_dir = tempdir().unwrap();
+    let team = tempdir().unwrap();
+    write_app(team.path(), "internal-tool", "Team tool");
+    write_app(team.path(), "old-tool", "Old tool");
+
+    an(db_dir.path())
+        .args(["repo", "add", "team"])
+        .arg(team.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("+ internal-tool"));
+
+    fs::remove_file(team.path().join("old-tool.toml")).unwrap();
+
+    an(db_dir.path())
+        .args(["sync", "team", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("["))
+        .stdout(predicate::str::contains(r#""name": "old-tool""#))
+        .stdout(predicate::str::contains(r#""kind": "removed""#))
+        .stdout(predicate::str::contains("同期中").not());
+}
@@ -2,7 +2,6 @@
 
 use assert_cmd::Command;
 use predicates::prelude::*;
-use std::env;
 use std::fs;
 use tempfile::tempdir;
 
@@ -146,3 +145,59 @@ fn test_search_grouped_by_type() {
         .stdout(predicate::str::contains("Deb"))
         .stdout(predicate::str::contains("Flatpak"));
 }
+
+/// 完全一致を先頭に表示し、--limit で件数を制限する
+#[test]
+fn test_search_ranked_with_limit() {
+    let db_dir = setup_test_db();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_DB_DIR", db_dir.path())
+        .args(["search", "code", "--limit", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 件のアプリ"))
+        .stdout(predicate::str::contains("code - Visual Studio Code"));
+}
+
+/// 打ち間違えた名前でも検索できる
+#[test]
+fn test_search_typo() {
+    let db_dir = setup_test_db();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_DB_DIR", db_dir.path())
+        .args(["search", "telgram"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("telegram"));
+}
+
+/// --category / --type で絞り込む
+#[test]
+fn test_search_filters() {
+    let db_dir = setup_test_db();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_DB_DIR", db_dir.path())
+        .args(["search", "--category", "network", "--type", "flatpak"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("telegram"))
+        .stdout(predicate::str::contains("firefox").not())
+        .stdout(predicate::str::contains("code").not());
+}
+
+/// categories でカテゴリとアプリ数を表示
+#[test]
+fn test_categories() {
+    let db_dir = setup_test_db();
+
+    let mut cmd = Command::cargo_bin("an").unwrap();
+    cmd.env("AN_DB_DIR", db_dir.path())
+        .arg("categories")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"Network\s+2").unwrap())
+        .stdout(predicate::str::is_match(r"IDE\s+1").unwrap());
+}